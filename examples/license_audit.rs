@@ -0,0 +1,45 @@
+//! Group the voices below a directory by license, and list the ones without usable licensing.
+//!
+//! ```sh
+//! cargo run --example license_audit --features std,alloc -- /usr/share/flite/voices
+//! ```
+
+use std::env;
+use std::process::ExitCode;
+
+use serde_cst::audit::{audit_dir, Flag};
+
+fn main() -> ExitCode {
+    let Some(dir) = env::args_os().nth(1) else {
+        eprintln!("usage: license_audit <directory>");
+        return ExitCode::FAILURE;
+    };
+    let audit = match audit_dir(&dir) {
+        Ok(audit) => audit,
+        Err(err) => {
+            eprintln!("{}: {err}", dir.to_string_lossy());
+            return ExitCode::FAILURE;
+        }
+    };
+    for (license, voices) in &audit.groups {
+        println!("{license:?}");
+        for voice in voices {
+            println!("    {} ({})", voice.name, voice.path.display());
+        }
+    }
+    for (voice, flag) in &audit.flagged {
+        let why = match flag {
+            Flag::Missing => "no copyright",
+            Flag::Unknown => "unknown copyright",
+        };
+        println!("warning: {}: {why}", voice.path.display());
+    }
+    for (path, err) in &audit.unreadable {
+        println!("error: {}: {err}", path.display());
+    }
+    if audit.flagged.is_empty() && audit.unreadable.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,13 @@
+#![no_main]
+
+use std::collections::BTreeMap;
+
+use libfuzzer_sys::fuzz_target;
+use serde_cst::de::from_bytes;
+
+// The length-prefixed entries on their own, without any of the typed structure on top.
+fuzz_target!(|data: &[u8]| {
+    let _ = from_bytes::<Vec<&str>>(data);
+    let _ = from_bytes::<BTreeMap<&str, &str>>(data);
+    let _ = from_bytes::<(bool, &str, u32, f32)>(data);
+});
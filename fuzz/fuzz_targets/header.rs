@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serde_cst::{de::from_bytes, Header};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = from_bytes::<Header>(data);
+});
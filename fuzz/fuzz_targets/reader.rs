@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serde::Deserialize;
+use serde_cst::{de::Deserializer, voice::TreeDb};
+
+// A reader is buffered up to the sizes the input declares, so those have to be limited.
+fuzz_target!(|data: &[u8]| {
+    let mut de = Deserializer::from_reader(data).max_allocation(1 << 20);
+    let _ = TreeDb::deserialize(&mut de);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serde_cst::{de::from_bytes, voice::TreeDb};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = from_bytes::<TreeDb>(data);
+});
@@ -0,0 +1,100 @@
+//! Memory estimates for decoding a voice, taken from its size prefixes alone.
+//!
+//! Embedded and mobile integrators need to know what a voice will cost before committing to it;
+//! [`estimate`] walks the file like [`preflight`](crate::preflight::preflight) does and tallies
+//! what every section will occupy once it is decoded.
+
+use crate::preflight::{Inconsistency, Sink, Walker};
+
+/// The estimated cost of one section of the voice.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SectionUsage {
+    pub section: &'static str,
+    /// Heap bytes: strings, arrays and tree nodes, and the pointer tables of lists.
+    pub bytes: usize,
+    /// Number of separate heap allocations.
+    pub allocations: usize,
+    /// Bytes of numbers, like counts and tree values, which flite keeps inline in the struct
+    /// which owns them rather than on the heap.
+    pub inline: usize,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct MemoryReport {
+    /// Every section, in the order it first appears in the file.
+    pub sections: Vec<SectionUsage>,
+    /// Decoding keeps every section alive until the voice is dropped, so this is the sum of the
+    /// heap and inline bytes of all sections. The input buffer itself is not included.
+    pub peak: usize,
+}
+impl MemoryReport {
+    pub fn section(&self, section: &str) -> Option<&SectionUsage> {
+        self.sections.iter().find(|usage| usage.section == section)
+    }
+    fn usage(&mut self, section: &'static str) -> &mut SectionUsage {
+        match self
+            .sections
+            .iter()
+            .position(|usage| usage.section == section)
+        {
+            Some(idx) => &mut self.sections[idx],
+            None => {
+                self.sections.push(SectionUsage {
+                    section,
+                    bytes: 0,
+                    allocations: 0,
+                    inline: 0,
+                });
+                self.sections.last_mut().unwrap()
+            }
+        }
+    }
+}
+
+impl Sink for MemoryReport {
+    fn account(&mut self, section: &'static str, bytes: usize, allocations: usize) {
+        self.peak = self.peak.saturating_add(bytes);
+        let usage = self.usage(section);
+        usage.bytes = usage.bytes.saturating_add(bytes);
+        usage.allocations += allocations;
+    }
+    fn inline(&mut self, section: &'static str, bytes: usize) {
+        self.peak = self.peak.saturating_add(bytes);
+        let usage = self.usage(section);
+        usage.inline = usage.inline.saturating_add(bytes);
+    }
+}
+
+/// Estimate the per-section and peak memory needed to decode the voice in `input`.
+pub fn estimate(input: &[u8]) -> Result<MemoryReport, Inconsistency> {
+    let (input, _) = crate::trailer::strip_trailers(input);
+    let mut walker = Walker::new(input, MemoryReport::default())?;
+    walker.voice()?;
+    walker.finish()
+}
+
+#[test]
+fn test_estimate() {
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let report = estimate(data).unwrap();
+    assert_eq!(
+        report.peak,
+        report
+            .sections
+            .iter()
+            .map(|usage| usage.bytes + usage.inline)
+            .sum::<usize>()
+    );
+    // numbers stay inline, so a section of them needs no heap
+    let sample_rate = report.section("sample_rate").unwrap();
+    assert_eq!((sample_rate.bytes, sample_rate.inline), (0, 4));
+    // and the values of tree nodes are mostly numbers
+    assert!(report.section("f0_trees").unwrap().inline > 0);
+    // the model vectors dominate a CG voice
+    let vectors = report.section("model_vectors").unwrap();
+    assert!(vectors.bytes > data.len() / 2);
+    // one allocation per frame, plus the row table of each of the three models
+    assert_eq!(vectors.allocations, 30238 + 30135 + 30220 + 3);
+    assert_eq!(report.section("name").unwrap().allocations, 1);
+    assert!(report.section("spamf0_accent_tree").is_none());
+}
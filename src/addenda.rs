@@ -0,0 +1,142 @@
+//! User lexicon addenda: the `word pos phones` text files shipped next to voices.
+//!
+//! Every line holds one entry: the word, its part of speech (`nil` if it has none) and its
+//! phones, separated by whitespace:
+//!
+//! ```text
+//! ; comments start with a semicolon
+//! flite nil f l ay1 t
+//! read vbd r eh1 d
+//! ```
+
+use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub word: String,
+    /// `None` for `nil`.
+    pub pos: Option<String>,
+    pub phones: Vec<String>,
+}
+impl Display for Entry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.word, self.pos.as_deref().unwrap_or("nil"))?;
+        for phone in &self.phones {
+            write!(f, " {phone}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Why a line could not be parsed. Lines are numbered from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The line has a word but no part of speech.
+    MissingPos { line: usize },
+    /// The line has a word and a part of speech, but no phones.
+    MissingPhones { line: usize },
+    /// A phone with characters other than ASCII letters and digits.
+    BadPhone { line: usize, phone: String },
+}
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingPos { line } => write!(f, "line {line}: no part of speech"),
+            ParseError::MissingPhones { line } => write!(f, "line {line}: no phones"),
+            ParseError::BadPhone { line, phone } => write!(f, "line {line}: bad phone {phone:?}"),
+        }
+    }
+}
+
+/// The entries of an addenda file, in file order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Addenda {
+    pub entries: Vec<Entry>,
+}
+impl Addenda {
+    /// The first entry for `word` and `pos`, or for `word` with any part of speech if `pos` is
+    /// `None`. Words are compared case-insensitively, like flite's lookup of lowercased tokens.
+    pub fn lookup(&self, word: &str, pos: Option<&str>) -> Option<&Entry> {
+        self.entries.iter().find(|entry| {
+            entry.word.eq_ignore_ascii_case(word) && (pos.is_none() || entry.pos.as_deref() == pos)
+        })
+    }
+}
+impl FromStr for Addenda {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Addenda, ParseError> {
+        let mut entries = Vec::new();
+        for (idx, line) in s.lines().enumerate() {
+            let line_no = idx + 1;
+            let content = line.split(';').next().unwrap_or_default();
+            let mut fields = content.split_whitespace();
+            let Some(word) = fields.next() else {
+                continue;
+            };
+            let pos = fields
+                .next()
+                .ok_or(ParseError::MissingPos { line: line_no })?;
+            let phones: Vec<String> = fields.map(str::to_string).collect();
+            if phones.is_empty() {
+                return Err(ParseError::MissingPhones { line: line_no });
+            }
+            if let Some(phone) = phones
+                .iter()
+                .find(|phone| !phone.bytes().all(|b| b.is_ascii_alphanumeric()))
+            {
+                return Err(ParseError::BadPhone {
+                    line: line_no,
+                    phone: phone.clone(),
+                });
+            }
+            entries.push(Entry {
+                word: word.to_string(),
+                pos: (pos != "nil").then(|| pos.to_string()),
+                phones,
+            });
+        }
+        Ok(Addenda { entries })
+    }
+}
+impl Display for Addenda {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{entry}")?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_addenda() {
+    let text = "; test addenda\n\nflite nil f l ay1 t\nread vbd  r eh1 d ; past tense\n";
+    let addenda: Addenda = text.parse().unwrap();
+    assert_eq!(
+        addenda.entries[1],
+        Entry {
+            word: "read".to_string(),
+            pos: Some("vbd".to_string()),
+            phones: vec!["r".to_string(), "eh1".to_string(), "d".to_string()],
+        }
+    );
+    assert_eq!(addenda.lookup("Flite", None).unwrap().pos, None);
+    assert_eq!(addenda.lookup("read", Some("vb")), None);
+    assert_eq!(
+        addenda.to_string(),
+        "flite nil f l ay1 t\nread vbd r eh1 d\n"
+    );
+    assert_eq!(addenda.to_string().parse::<Addenda>().unwrap(), addenda);
+
+    assert_eq!(
+        "ok nil ow1\nbroken\n".parse::<Addenda>(),
+        Err(ParseError::MissingPos { line: 2 })
+    );
+    assert_eq!(
+        "word nil w (er1) d".parse::<Addenda>(),
+        Err(ParseError::BadPhone {
+            line: 1,
+            phone: "(er1)".to_string()
+        })
+    );
+}
@@ -0,0 +1,315 @@
+//! Arrays: the big numeric tables of a voice.
+//!
+//! flite stores an array as a byte count followed by the bytes, in the byte order of the host
+//! which built the voice. [`ArrayRef`] borrows one from the input, so structs deserialized with
+//! [`from_bytes`](crate::de::from_bytes) can hold tables without copying them. [`Array2d`] keeps
+//! every row of a two dimensional array in a single buffer, so the tens of thousands of frames
+//! of a voice take two allocations rather than one each.
+//!
+//! The accessors read little-endian values, the byte order nearly every voice is built with.
+
+use core::fmt;
+
+use serde::de::{Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+/// An array borrowed from the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ArrayRef<'a>(pub &'a [u8]);
+impl<'a> ArrayRef<'a> {
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+    /// The bytes as `u16`s, as the frames of the base model shape are stored.
+    pub fn u16s(&self) -> impl Iterator<Item = u16> + 'a {
+        self.0
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes(b.try_into().unwrap()))
+    }
+    pub fn f32s(&self) -> impl Iterator<Item = f32> + 'a {
+        self.0
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+    }
+    pub fn f64s(&self) -> impl Iterator<Item = f64> + 'a {
+        self.0
+            .chunks_exact(8)
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+    }
+    /// The `idx`th `u16`, or `None` past the end.
+    pub fn u16_at(&self, idx: usize) -> Option<u16> {
+        let bytes = self.0.get(idx.checked_mul(2)?..)?.get(..2)?;
+        Some(u16::from_le_bytes(bytes.try_into().unwrap()))
+    }
+    /// The `idx`th `f32`, or `None` past the end.
+    pub fn f32_at(&self, idx: usize) -> Option<f32> {
+        let bytes = self.0.get(idx.checked_mul(4)?..)?.get(..4)?;
+        Some(f32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+struct ArrayRefVisitor;
+impl<'de> Visitor<'de> for ArrayRefVisitor {
+    type Value = ArrayRef<'de>;
+    fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("An array borrowed from the input")
+    }
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<ArrayRef<'de>, E> {
+        Ok(ArrayRef(v))
+    }
+}
+impl<'de: 'a, 'a> Deserialize<'de> for ArrayRef<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<ArrayRef<'a>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(ArrayRefVisitor)
+    }
+}
+impl Serialize for ArrayRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub use owned::{Array, Array2d};
+
+#[cfg(feature = "alloc")]
+mod owned {
+    extern crate alloc;
+
+    use alloc::vec::Vec;
+    use core::fmt;
+
+    use serde::de::{Deserialize, DeserializeSeed, Deserializer, SeqAccess, Visitor};
+    use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+    use super::ArrayRef;
+
+    /// An array copied out of the input.
+    #[derive(Debug, Clone, PartialEq, Eq, Default)]
+    pub struct Array(pub Vec<u8>);
+    impl Array {
+        pub fn view(&self) -> ArrayRef<'_> {
+            ArrayRef(&self.0)
+        }
+        pub fn f32s(&self) -> impl Iterator<Item = f32> + '_ {
+            self.view().f32s()
+        }
+        pub fn f64s(&self) -> impl Iterator<Item = f64> + '_ {
+            self.view().f64s()
+        }
+    }
+    struct ArrayVisitor;
+    impl<'de> Visitor<'de> for ArrayVisitor {
+        type Value = Array;
+        fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+            fmt.write_str("An array of bytes")
+        }
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<Array, E> {
+            Ok(Array(v.to_vec()))
+        }
+        fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Array, E> {
+            Ok(Array(v))
+        }
+    }
+    impl Serialize for Array {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+    impl<'de> Deserialize<'de> for Array {
+        fn deserialize<D>(deserializer: D) -> Result<Array, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_byte_buf(ArrayVisitor)
+        }
+    }
+
+    /// A two dimensional array: the number of rows, then every row as an array.
+    #[derive(Debug, Clone, PartialEq, Eq, Default)]
+    pub struct Array2d {
+        bytes: Vec<u8>,
+        // where every row ends in `bytes`
+        ends: Vec<usize>,
+    }
+    impl Array2d {
+        pub fn new() -> Self {
+            Array2d::default()
+        }
+        pub fn push(&mut self, row: &[u8]) {
+            self.bytes.extend_from_slice(row);
+            self.ends.push(self.bytes.len());
+        }
+        /// The number of rows.
+        pub fn len(&self) -> usize {
+            self.ends.len()
+        }
+        pub fn is_empty(&self) -> bool {
+            self.ends.is_empty()
+        }
+        pub fn row(&self, idx: usize) -> Option<ArrayRef<'_>> {
+            let end = *self.ends.get(idx)?;
+            let start = match idx {
+                0 => 0,
+                _ => self.ends[idx - 1],
+            };
+            Some(ArrayRef(&self.bytes[start..end]))
+        }
+        pub fn rows(&self) -> impl Iterator<Item = ArrayRef<'_>> + '_ {
+            (0..self.len()).filter_map(|idx| self.row(idx))
+        }
+        /// Every row, back to back.
+        pub fn as_bytes(&self) -> &[u8] {
+            &self.bytes
+        }
+        /// The length every row has, or `None` if they differ. An array without rows has rows of
+        /// any length, so 0 is returned.
+        pub fn row_len(&self) -> Option<usize> {
+            let len = self.ends.first().copied().unwrap_or(0);
+            let mut ends = self.ends.iter().zip(1..);
+            ends.all(|(&end, rows)| Some(end) == len.checked_mul(rows))
+                .then_some(len)
+        }
+    }
+    impl<R: AsRef<[u8]>> FromIterator<R> for Array2d {
+        fn from_iter<I: IntoIterator<Item = R>>(rows: I) -> Self {
+            let mut array = Array2d::new();
+            for row in rows {
+                array.push(row.as_ref());
+            }
+            array
+        }
+    }
+
+    // Appends the next array to the rows, without an allocation of its own.
+    struct Row<'a>(&'a mut Array2d);
+    impl<'de> DeserializeSeed<'de> for Row<'_> {
+        type Value = ();
+        fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_bytes(self)
+        }
+    }
+    impl<'de> Visitor<'de> for Row<'_> {
+        type Value = ();
+        fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+            fmt.write_str("A row of bytes")
+        }
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<(), E> {
+            self.0.push(v);
+            Ok(())
+        }
+    }
+
+    struct Array2dVisitor;
+    impl<'de> Visitor<'de> for Array2dVisitor {
+        type Value = Array2d;
+        fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+            fmt.write_str("A sequence of arrays")
+        }
+        fn visit_seq<A>(self, mut seq: A) -> Result<Array2d, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut array = Array2d::new();
+            while seq.next_element_seed(Row(&mut array))?.is_some() {}
+            Ok(array)
+        }
+    }
+    impl Serialize for Array2d {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let mut seq = serializer.serialize_seq(Some(self.len()))?;
+            for row in self.rows() {
+                seq.serialize_element(&row)?;
+            }
+            seq.end()
+        }
+    }
+    impl<'de> Deserialize<'de> for Array2d {
+        fn deserialize<D>(deserializer: D) -> Result<Array2d, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_seq(Array2dVisitor)
+        }
+    }
+
+    #[test]
+    fn test_array2d() {
+        use crate::de::from_bytes;
+        use crate::ser::to_bytes;
+        let mut data = crate::FLITEVOX_MAGIC.to_vec();
+        data.extend(b"\x01\0\0\0\x02\0\0\0\x04\0\0\0\x01\0\x02\0\x02\0\0\0\xff\xff");
+        let array: Array2d = from_bytes(&data).unwrap();
+        assert_eq!(array.len(), 2);
+        assert_eq!(array.row(0).unwrap().u16s().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(array.row(1).unwrap().u16_at(0), Some(u16::MAX));
+        assert_eq!(array.row(1).unwrap().u16_at(1), None);
+        assert_eq!(array.row(2), None);
+        assert_eq!(array.as_bytes(), b"\x01\0\x02\0\xff\xff");
+        assert_eq!(array.row_len(), None);
+        assert_eq!(to_bytes(&array).unwrap(), data);
+        assert_eq!(
+            [&[1u8, 0, 2, 0][..], &[0xff, 0xff]]
+                .into_iter()
+                .collect::<Array2d>(),
+            array
+        );
+        let frames: Array2d = [[1u8, 2], [3, 4], [5, 6]].into_iter().collect();
+        assert_eq!(frames.row_len(), Some(2));
+        assert_eq!(Array2d::new().row_len(), Some(0));
+    }
+
+    #[test]
+    fn test_serde_bytes() {
+        use crate::de::from_bytes;
+        use crate::ser::to_bytes;
+        use serde::{Deserialize, Serialize};
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Tables<'a>(
+            #[serde(with = "serde_bytes")] &'a [u8],
+            #[serde(with = "serde_bytes")] Vec<u8>,
+        );
+        let tables = Tables(b"\x01\x02", vec![3, 4, 5]);
+        let data = to_bytes(&[&tables][..]).unwrap();
+        assert_eq!(data[34..], *b"\x02\0\0\0\x01\x02\x03\0\0\0\x03\x04\x05");
+        assert_eq!(from_bytes::<Vec<Tables>>(&data).unwrap(), [tables]);
+    }
+}
+
+#[test]
+fn test_array_ref() {
+    use crate::de::from_bytes;
+    let mut data = crate::FLITEVOX_MAGIC.to_vec();
+    data.extend(b"\x01\0\0\0\x08\0\0\0");
+    data.extend(1.5f32.to_le_bytes());
+    data.extend((-2.0f32).to_le_bytes());
+    let array: ArrayRef = from_bytes(&data).unwrap();
+    // borrowed, not copied
+    assert_eq!(array.as_bytes().as_ptr(), data[34..].as_ptr());
+    assert!(array.f32s().eq([1.5, -2.0]));
+    assert_eq!(array.f32_at(1), Some(-2.0));
+    assert_eq!(array.f32_at(2), None);
+    assert_eq!(array.f32_at(usize::MAX), None);
+
+    // a reader can't lend its bytes
+    #[cfg(feature = "std")]
+    {
+        let mut de = crate::de::Deserializer::from_reader(&data[..]);
+        assert!(ArrayRef::deserialize(&mut de).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+//! Audit the licensing of a directory of voices.
+//!
+//! [`audit_dir`] reads only the header of every `.flitevox` file below a directory, groups the
+//! voices by their copyright text, and flags the ones a packager cannot ship as is.
+
+extern crate std;
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::de::Deserializer;
+use crate::error::Error;
+use crate::registry::Fingerprint;
+use crate::Header;
+
+// The header of every known voice fits in this; longer ones are read in full.
+pub(crate) const HEADER_PREFIX: u64 = 4096;
+
+/// Why the header of a voice file could not be read.
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    Parse(Error),
+}
+impl Display for ReadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io(err) => write!(f, "{err}"),
+            ReadError::Parse(err) => write!(f, "{err}"),
+        }
+    }
+}
+impl std::error::Error for ReadError {}
+impl From<io::Error> for ReadError {
+    fn from(err: io::Error) -> ReadError {
+        ReadError::Io(err)
+    }
+}
+impl From<Error> for ReadError {
+    fn from(err: Error) -> ReadError {
+        ReadError::Parse(err)
+    }
+}
+
+fn parse_header(data: &[u8]) -> Result<(Header, u64), Error> {
+    let mut de = Deserializer::from_bytes(data);
+    let header = Header::deserialize(&mut de)?;
+    Ok((header, (data.len() - de.remaining()) as u64))
+}
+
+/// Read the header of the voice at `path`, without reading the rest of the file. Also returns
+/// the fingerprint of the file and the offset of its body.
+pub(crate) fn read_header(path: &Path) -> Result<(Header, Fingerprint, u64), ReadError> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut data = Vec::new();
+    file.by_ref().take(HEADER_PREFIX).read_to_end(&mut data)?;
+    let fingerprint = Fingerprint::new(len, &data);
+    let (header, body_offset) = match parse_header(&data) {
+        Err(Error::Eof) => {
+            file.read_to_end(&mut data)?;
+            parse_header(&data)?
+        }
+        header => header?,
+    };
+    Ok((header, fingerprint, body_offset))
+}
+
+/// The fingerprint of the file at `path`, reading only the start of it.
+pub(crate) fn fingerprint(path: &Path) -> io::Result<Fingerprint> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut data = Vec::new();
+    file.take(HEADER_PREFIX).read_to_end(&mut data)?;
+    Ok(Fingerprint::new(len, &data))
+}
+
+/// Every `.flitevox` file below `dir`, sorted.
+pub(crate) fn voice_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "flitevox") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The licensing metadata of one voice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoiceLicense {
+    pub path: PathBuf,
+    pub name: String,
+    pub description: String,
+    pub copyright: String,
+}
+
+/// Why a voice needs a packager's attention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    /// The copyright feature is empty.
+    Missing,
+    /// The copyright feature is `unknown` or `none`.
+    Unknown,
+}
+impl Flag {
+    fn of(copyright: &str) -> Option<Flag> {
+        let copyright = copyright.trim();
+        if copyright.is_empty() {
+            Some(Flag::Missing)
+        } else if copyright.eq_ignore_ascii_case("unknown")
+            || copyright.eq_ignore_ascii_case("none")
+        {
+            Some(Flag::Unknown)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LicenseAudit {
+    /// Every voice, by its copyright text with whitespace collapsed.
+    pub groups: BTreeMap<String, Vec<VoiceLicense>>,
+    /// The voices without usable licensing.
+    pub flagged: Vec<(VoiceLicense, Flag)>,
+    /// The `.flitevox` files whose header could not be read.
+    pub unreadable: Vec<(PathBuf, ReadError)>,
+}
+
+/// Audit every `.flitevox` file below `dir`.
+///
+/// Only a failure to list the directory is an error; unreadable voices end up in
+/// [`LicenseAudit::unreadable`].
+pub fn audit_dir(dir: impl AsRef<Path>) -> io::Result<LicenseAudit> {
+    let mut audit = LicenseAudit::default();
+    for path in voice_files(dir.as_ref())? {
+        let header = match read_header(&path) {
+            Ok((header, _, _)) => header,
+            Err(err) => {
+                audit.unreadable.push((path, err));
+                continue;
+            }
+        };
+        let voice = VoiceLicense {
+            path,
+            name: header.name,
+            description: header.features.description,
+            copyright: header.features.copyright,
+        };
+        if let Some(flag) = Flag::of(&voice.copyright) {
+            audit.flagged.push((voice.clone(), flag));
+        }
+        let key = voice.copyright.split_whitespace().collect::<Vec<_>>();
+        audit.groups.entry(key.join(" ")).or_default().push(voice);
+    }
+    Ok(audit)
+}
+
+#[test]
+fn test_audit_dir() {
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let with_copyright = |copyright: &str| {
+        let key = b"copyright\0";
+        let at = data.windows(key.len()).position(|w| w == key).unwrap() + key.len();
+        let size = u32::from_le_bytes(data[at..at + 4].try_into().unwrap()) as usize;
+        let mut patched = data[..at].to_vec();
+        patched.extend((copyright.len() as u32 + 1).to_le_bytes());
+        patched.extend(copyright.as_bytes());
+        patched.push(0);
+        patched.extend(&data[at + 4 + size..]);
+        patched
+    };
+
+    let dir = std::env::temp_dir().join(format!("serde_cst_audit_{}", std::process::id()));
+    fs::create_dir_all(dir.join("nested")).unwrap();
+    fs::write(dir.join("slt.flitevox"), data).unwrap();
+    fs::write(dir.join("missing.flitevox"), with_copyright("")).unwrap();
+    fs::write(
+        dir.join("nested/bsd.flitevox"),
+        with_copyright("Copyright  (c) CMU,\nBSD license"),
+    )
+    .unwrap();
+    fs::write(dir.join("broken.flitevox"), b"RIFF").unwrap();
+    fs::write(dir.join("README"), b"not a voice").unwrap();
+
+    let audit = audit_dir(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let groups: Vec<(&str, usize)> = audit
+        .groups
+        .iter()
+        .map(|(license, voices)| (license.as_str(), voices.len()))
+        .collect();
+    assert_eq!(
+        groups,
+        [
+            ("", 1),
+            ("Copyright (c) CMU, BSD license", 1),
+            ("unknown", 1)
+        ]
+    );
+    let flagged: Vec<(&Path, Flag)> = audit
+        .flagged
+        .iter()
+        .map(|(voice, flag)| (voice.path.strip_prefix(&dir).unwrap(), *flag))
+        .collect();
+    assert_eq!(
+        flagged,
+        [
+            (Path::new("missing.flitevox"), Flag::Missing),
+            (Path::new("slt.flitevox"), Flag::Unknown),
+        ]
+    );
+    assert_eq!(audit.unreadable.len(), 1);
+    assert!(audit.unreadable[0].0.ends_with("broken.flitevox"));
+    assert_eq!(audit.groups["unknown"][0].name, "cmu_us_slt");
+}
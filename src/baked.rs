@@ -0,0 +1,95 @@
+//! Borrowed, `const`-constructible versions of the [`model`](crate::model) types.
+//!
+//! These are what the modules generated by [`codegen::rust_module`](crate::codegen::rust_module)
+//! are made of. They need neither `alloc` nor any parsing at runtime, so `no_std` projects can
+//! embed a whole voice in their binary.
+
+/// A value held by a tree node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Float(f32),
+    Str(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CartNode {
+    /// Index into [`Cart::features`].
+    pub feature: u8,
+    /// The question asked, as stored by flite: 0 is, 1 in, 2 less, 3 greater, 4 matches and 255
+    /// for a leaf.
+    pub op: u8,
+    pub no_node: u16,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cart {
+    pub nodes: &'static [CartNode],
+    pub features: &'static [&'static str],
+}
+
+/// The frames of one parameter model, stored back to back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParamModel {
+    pub num_channels: usize,
+    /// The length of every frame in bytes.
+    pub frame_len: usize,
+    pub frames: &'static [u8],
+}
+impl ParamModel {
+    pub fn frame(&self, index: usize) -> Option<&'static [u8]> {
+        let start = index.checked_mul(self.frame_len)?;
+        self.frames.get(start..start.checked_add(self.frame_len)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DurStat {
+    pub phone: &'static str,
+    pub mean: f32,
+    pub stddev: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DurModel {
+    pub stats: &'static [DurStat],
+    pub tree: Cart,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhoneState {
+    pub phone: &'static str,
+    pub states: &'static [&'static str],
+}
+
+/// A whole clustergen voice, see [`CgModel`](crate::model::CgModel) for the fields.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CgVoice {
+    pub name: &'static str,
+    pub sample_rate: u32,
+    pub frame_advance: f32,
+    pub f0_mean: f32,
+    pub f0_stddev: f32,
+    pub state_names: &'static [&'static str],
+    pub f0_trees: &'static [&'static [Cart]],
+    pub param_trees: &'static [&'static [Cart]],
+    pub param_models: &'static [ParamModel],
+    pub model_shape: u32,
+    pub model_min: &'static [f32],
+    pub model_range: &'static [f32],
+    pub dur_models: &'static [DurModel],
+    pub phone_states: &'static [PhoneState],
+}
+
+#[test]
+fn test_frame() {
+    let model = ParamModel {
+        num_channels: 1,
+        frame_len: 2,
+        frames: &[1, 2, 3, 4],
+    };
+    assert_eq!(model.frame(1), Some(&[3, 4][..]));
+    assert_eq!(model.frame(2), None);
+    assert_eq!(model.frame(usize::MAX), None);
+}
@@ -0,0 +1,223 @@
+//! Best-effort parsing for auditing voices of unknown quality.
+//!
+//! Instead of failing on the first anomaly, [`from_bytes`] downgrades everything it can recover
+//! from (unknown gender strings, bad dates, missing or unparsable features, a body that ends
+//! early, trailers which don't match) to a [`Warning`], and carries on. Only a file whose header
+//! cannot be read at all is an error.
+
+use serde::de::DeserializeSeed;
+
+use crate::de::Deserializer;
+use crate::diagnostic::Diagnostic;
+use crate::error::Result;
+use crate::header_ref::{
+    DEFAULT_MODEL_SHAPE, DEFAULT_NUM_DUR_MODELS, DEFAULT_NUM_F0_MODELS, DEFAULT_NUM_PARAM_MODELS,
+};
+use crate::trailer::strip_trailers;
+use crate::voice::{Body, BodyDeserializer, TreeDb};
+use crate::{BuildDate, EndOfFeatures, Features, Gender, Header};
+
+/// A recoverable anomaly, and what was done about it.
+pub type Warning = Diagnostic;
+
+#[derive(Debug, PartialEq)]
+pub struct BestEffort {
+    pub header: Header,
+    /// `None` if the body could not be decoded; the last warning says why.
+    pub body: Option<Body>,
+    pub warnings: Vec<Warning>,
+}
+impl BestEffort {
+    /// The complete voice, if the body could be decoded.
+    pub fn into_voice(self) -> Option<TreeDb> {
+        let BestEffort { header, body, .. } = self;
+        body.map(|body| TreeDb { header, body })
+    }
+}
+
+struct Fields<'de> {
+    /// Every feature, and the offset of its key.
+    pairs: Vec<(&'de str, &'de str, usize)>,
+    warnings: Vec<Warning>,
+}
+impl<'de> Fields<'de> {
+    fn warn(&mut self, code: &'static str, field: &str, offset: Option<usize>, message: String) {
+        let warning = Diagnostic::warning(code, field, message);
+        self.warnings.push(match offset {
+            Some(offset) => warning.at(offset),
+            None => warning,
+        });
+    }
+    fn take(&mut self, key: &'static str) -> Option<(&'de str, usize)> {
+        let idx = self.pairs.iter().position(|(k, _, _)| *k == key)?;
+        let (_, value, offset) = self.pairs.remove(idx);
+        if let Some(&(_, _, repeated)) = self.pairs.iter().find(|(k, _, _)| *k == key) {
+            let message = format!("repeated, using the first value {value:?}");
+            self.warn("repeated-feature", key, Some(repeated), message);
+            self.pairs.retain(|(k, _, _)| *k != key);
+        }
+        Some((value, offset))
+    }
+    fn missing(&mut self, key: &'static str, default: &str) {
+        let message = format!("missing, using {default}");
+        self.warn("missing-feature", key, None, message);
+    }
+    fn string(&mut self, key: &'static str) -> String {
+        self.take(key)
+            .map(|(value, _)| value.to_string())
+            .unwrap_or_else(|| {
+                self.missing(key, "\"unknown\"");
+                "unknown".to_string()
+            })
+    }
+    fn number(&mut self, key: &'static str, default: u32) -> u32 {
+        match self.take(key) {
+            Some((value, offset)) => value.parse().unwrap_or_else(|_| {
+                let message = format!("{value:?} is not a number, using {default}");
+                self.warn("bad-number", key, Some(offset), message);
+                default
+            }),
+            None => {
+                self.missing(key, &default.to_string());
+                default
+            }
+        }
+    }
+    fn gender(&mut self) -> Gender {
+        match self.take("gender") {
+            Some((value, offset)) => value.parse().unwrap_or_else(|_| {
+                let message = format!("unknown gender {value:?}, using unknown");
+                self.warn("unknown-gender", "gender", Some(offset), message);
+                Gender::Unknown
+            }),
+            None => {
+                self.missing("gender", "unknown");
+                Gender::Unknown
+            }
+        }
+    }
+    fn build_date(&mut self) -> BuildDate {
+        match self.take("build_date") {
+            Some((value, offset)) => match crate::date::parse(value) {
+                Some(date) => date,
+                None => {
+                    let message =
+                        format!("{value:?} is not a date in any known format, using the epoch");
+                    self.warn("bad-date", "build_date", Some(offset), message);
+                    BuildDate::EPOCH
+                }
+            },
+            None => {
+                self.missing("build_date", "the epoch");
+                BuildDate::EPOCH
+            }
+        }
+    }
+}
+
+/// Parse a voice, collecting recoverable anomalies as warnings instead of failing on them.
+pub fn from_bytes(input: &[u8]) -> Result<BestEffort> {
+    let (input, trailers) = strip_trailers(input);
+    let mut de = Deserializer::from_bytes(input);
+    let mut fields = Fields {
+        pairs: Vec::new(),
+        warnings: Vec::new(),
+    };
+    let offset = |de: &Deserializer| input.len() - de.remaining();
+    loop {
+        let key = de.parse_str()?;
+        let at = offset(&de) - key.len() - 5;
+        let value = de.parse_str()?;
+        if key == "end_of_features" {
+            break;
+        }
+        fields.pairs.push((key, value, at));
+    }
+    let features = Features {
+        language: fields.string("language"),
+        country: fields.string("country"),
+        variant: fields.string("variant"),
+        age: fields.number("age", 0),
+        gender: fields.gender(),
+        build_date: fields.build_date(),
+        description: fields.string("description"),
+        eng_shared: fields.number("eng_shared", 0),
+        copyright: fields.string("copyright"),
+        num_dur_models: fields.number("num_dur_models", DEFAULT_NUM_DUR_MODELS),
+        num_param_models: fields.number("num_param_models", DEFAULT_NUM_PARAM_MODELS),
+        model_shape: fields.number("model_shape", DEFAULT_MODEL_SHAPE),
+        num_f0_models: fields.number("num_f0_models", DEFAULT_NUM_F0_MODELS),
+        end_of_features: EndOfFeatures::EndOfFeatures,
+    };
+    let Fields {
+        pairs,
+        mut warnings,
+    } = fields;
+    for (key, value, at) in pairs {
+        let message = format!("unknown feature ignored (value {value:?})");
+        warnings.push(Diagnostic::warning("unknown-feature", key, message).at(at));
+    }
+    let header = Header {
+        version: de.version().unwrap_or_default(),
+        features,
+        name: de.parse_str()?.to_string(),
+    };
+    let body_offset = offset(&de);
+    let body = match (BodyDeserializer { header: &header }).deserialize(&mut de) {
+        Ok(body) => Some(body),
+        Err(e) => {
+            let message = format!("could not be decoded: {e:?}");
+            warnings.push(Diagnostic::error("undecodable-body", "body", message).at(body_offset));
+            None
+        }
+    };
+    if let Err(e) = trailers.verify() {
+        let message = format!("does not match the voice: {e}");
+        warnings.push(Diagnostic::error("checksum-mismatch", "trailer", message).at(input.len()));
+    }
+    Ok(BestEffort {
+        header,
+        body,
+        warnings,
+    })
+}
+
+#[test]
+fn test_best_effort() {
+    let mut data = include_bytes!("../data/cmu_us_slt.flitevox").to_vec();
+    let clean = from_bytes(&data).unwrap();
+    assert!(clean.warnings.is_empty());
+    assert!(clean.body.is_some());
+
+    let mut with_trailer = data.clone();
+    crate::trailer::append_trailer(&mut with_trailer);
+    assert_eq!(from_bytes(&with_trailer).unwrap(), clean);
+    with_trailer[data.len() / 2] ^= 1;
+    let damaged = from_bytes(&with_trailer).unwrap();
+    assert_eq!(damaged.warnings.last().unwrap().code, "checksum-mismatch");
+
+    // same lengths, so the rest of the file stays intact
+    let gender = data.windows(8).position(|w| w == b"unknown\0").unwrap();
+    data[gender..gender + 7].copy_from_slice(b"unknowx");
+    let date = data
+        .windows(16)
+        .position(|w| w == b"2017-09-14_23:37")
+        .unwrap();
+    data[date + 5..date + 7].copy_from_slice(b"99");
+    let parsed = from_bytes(&data).unwrap();
+    assert_eq!(parsed.header.name, "cmu_us_slt");
+    assert_eq!(parsed.header.features.gender, Gender::Unknown);
+    assert_eq!(parsed.header.features.build_date, BuildDate::EPOCH);
+    let fields: Vec<&str> = parsed
+        .warnings
+        .iter()
+        .map(|warning| warning.field.as_str())
+        .filter(|field| *field != "body")
+        .collect();
+    assert_eq!(fields, ["gender", "build_date"]);
+    assert_eq!(parsed.warnings[0].code, "unknown-gender");
+    assert_eq!(
+        &data[parsed.warnings[1].offset.unwrap()..][..15],
+        b"\x0b\0\0\0build_date\0"
+    );
+}
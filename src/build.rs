@@ -0,0 +1,265 @@
+//! Building a voice from scratch.
+//!
+//! [`VoiceBuilder`] starts from an empty voice and takes the models one at a time. The counts
+//! flite reads before the models they count (`num_types`, `num_f0_models`, `me_num` and so on)
+//! follow from what was added, and [`VoiceBuilder::to_bytes`] checks the written voice with
+//! [`validate`](crate::validate::validate), so what it returns is a voice flite can load.
+
+use crate::error::{Error, Result};
+use crate::voice::{Array, Array2d, Body, DurModel, ModelVectors, SpamF0, TreeDb, Trees};
+use crate::{BuildDate, EndOfFeatures, Features, FormatVersion, Gender, Header};
+
+// Model shapes above 1 carry quantization tables; 3 is what festvox writes for them.
+const MODEL_SHAPE_QUANTIZED: u32 = 3;
+
+/// A voice under construction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoiceBuilder {
+    header: Header,
+    body: Body,
+}
+impl VoiceBuilder {
+    /// A voice called `name`, without any models. The features are those festvox gives a voice
+    /// it knows nothing about.
+    pub fn new(name: &str) -> Self {
+        let features = Features {
+            language: "eng".to_string(),
+            country: "USA".to_string(),
+            variant: "none".to_string(),
+            age: 0,
+            gender: Gender::Unknown,
+            build_date: BuildDate::EPOCH,
+            description: "unknown".to_string(),
+            eng_shared: 0,
+            copyright: "unknown".to_string(),
+            num_dur_models: 0,
+            num_param_models: 0,
+            model_shape: 0,
+            num_f0_models: 0,
+            end_of_features: EndOfFeatures::EndOfFeatures,
+        };
+        let body = Body {
+            db_types: Vec::new(),
+            num_types: 0,
+            sample_rate: 16000,
+            f0_mean: 0.0,
+            f0_stddev: 0.0,
+            f0_trees: Vec::new(),
+            param_trees: Vec::new(),
+            spamf0: None,
+            param_models: Vec::new(),
+            model_min: Array::default(),
+            model_range: Array::default(),
+            qtables: Vec::new(),
+            frame_advance: 0.005,
+            dur_models: Vec::new(),
+            phone_states: Vec::new(),
+            do_mlpg: 0,
+            dynwin: Array::default(),
+            dynwinsize: 0,
+            mlsa_alpha: 0.42,
+            mlsa_beta: 0.0,
+            multimodel: 0,
+            mixed_excitation: 0,
+            me_num: 0,
+            me_order: 0,
+            me_h: Array2d::new(),
+            gain: 1.0,
+        };
+        VoiceBuilder {
+            header: Header {
+                version: FormatVersion::CURRENT,
+                features,
+                name: name.to_string(),
+            },
+            body,
+        }
+    }
+    /// Start from the features of another voice. Its model counts are replaced by the ones of
+    /// the models added here.
+    pub fn features(mut self, features: Features) -> Self {
+        self.header.features = features;
+        self
+    }
+    pub fn language(mut self, language: &str) -> Self {
+        self.header.features.language = language.to_string();
+        self
+    }
+    pub fn country(mut self, country: &str) -> Self {
+        self.header.features.country = country.to_string();
+        self
+    }
+    pub fn variant(mut self, variant: &str) -> Self {
+        self.header.features.variant = variant.to_string();
+        self
+    }
+    pub fn age(mut self, age: u32) -> Self {
+        self.header.features.age = age;
+        self
+    }
+    pub fn gender(mut self, gender: Gender) -> Self {
+        self.header.features.gender = gender;
+        self
+    }
+    pub fn build_date(mut self, build_date: BuildDate) -> Self {
+        self.header.features.build_date = build_date;
+        self
+    }
+    pub fn description(mut self, description: &str) -> Self {
+        self.header.features.description = description.to_string();
+        self
+    }
+    pub fn copyright(mut self, copyright: &str) -> Self {
+        self.header.features.copyright = copyright.to_string();
+        self
+    }
+    /// The names of the states every tree set has one tree for, in order.
+    pub fn types(mut self, types: Vec<String>) -> Self {
+        self.body.db_types = types;
+        self
+    }
+    pub fn sample_rate(mut self, sample_rate: i32) -> Self {
+        self.body.sample_rate = sample_rate;
+        self
+    }
+    pub fn f0(mut self, mean: f32, stddev: f32) -> Self {
+        self.body.f0_mean = mean;
+        self.body.f0_stddev = stddev;
+        self
+    }
+    /// Add an f0 model: one tree per type.
+    pub fn f0_model(mut self, trees: Trees) -> Self {
+        self.body.f0_trees.push(trees);
+        self
+    }
+    /// Add a parameter model: one tree per type, and the frames its leaves point into.
+    pub fn param_model(mut self, trees: Trees, vectors: ModelVectors) -> Self {
+        self.body.param_trees.push(trees);
+        self.body.param_models.push(vectors);
+        self
+    }
+    /// The per channel minimum and range of the parameters, as `f32`s.
+    pub fn model_range(mut self, min: Array, range: Array) -> Self {
+        self.body.model_min = min;
+        self.body.model_range = range;
+        self
+    }
+    /// Quantize the parameter models, with for every one of them a table per channel.
+    pub fn quantized(mut self, qtables: Vec<Array2d>) -> Self {
+        self.header.features.model_shape = MODEL_SHAPE_QUANTIZED;
+        self.body.qtables = qtables;
+        self
+    }
+    pub fn spamf0(mut self, spamf0: SpamF0) -> Self {
+        self.body.spamf0 = Some(spamf0);
+        self
+    }
+    /// Seconds between two frames.
+    pub fn frame_advance(mut self, frame_advance: f32) -> Self {
+        self.body.frame_advance = frame_advance;
+        self
+    }
+    pub fn dur_model(mut self, model: DurModel) -> Self {
+        self.body.dur_models.push(model);
+        self
+    }
+    /// For every phone, its name and then its states.
+    pub fn phone_states(mut self, phone_states: Vec<Vec<String>>) -> Self {
+        self.body.phone_states = phone_states;
+        self
+    }
+    /// Smooth the parameters with maximum likelihood parameter generation, over the delta
+    /// window `dynwin` of `dynwinsize` `f32`s.
+    pub fn mlpg(mut self, dynwin: Array, dynwinsize: i32) -> Self {
+        self.body.do_mlpg = 1;
+        self.body.dynwin = dynwin;
+        self.body.dynwinsize = dynwinsize;
+        self
+    }
+    pub fn mlsa(mut self, alpha: f32, beta: f32) -> Self {
+        self.body.mlsa_alpha = alpha;
+        self.body.mlsa_beta = beta;
+        self
+    }
+    pub fn multimodel(mut self, multimodel: bool) -> Self {
+        self.body.multimodel = i32::from(multimodel);
+        self
+    }
+    /// Use mixed excitation, with one filter of `f64`s per band in `me_h`.
+    pub fn mixed_excitation(mut self, me_h: Array2d, me_order: i32) -> Self {
+        self.body.mixed_excitation = 1;
+        self.body.me_order = me_order;
+        self.body.me_h = me_h;
+        self
+    }
+    pub fn gain(mut self, gain: f32) -> Self {
+        self.body.gain = gain;
+        self
+    }
+    /// The voice, with the counts filled in from what was added.
+    pub fn build(self) -> TreeDb {
+        let VoiceBuilder {
+            mut header,
+            mut body,
+        } = self;
+        let features = &mut header.features;
+        // a voice too big for its counts can't be written anyway
+        features.num_f0_models = body.f0_trees.len().try_into().unwrap_or(u32::MAX);
+        features.num_param_models = body.param_trees.len().try_into().unwrap_or(u32::MAX);
+        features.num_dur_models = body.dur_models.len().try_into().unwrap_or(u32::MAX);
+        body.num_types = body.db_types.len().try_into().unwrap_or(i32::MAX);
+        body.me_num = body.me_h.len().try_into().unwrap_or(i32::MAX);
+        TreeDb { header, body }
+    }
+    /// Write the voice, failing with the first problem which would keep flite from loading it.
+    pub fn to_bytes(self) -> Result<Vec<u8>> {
+        let data = crate::ser::to_bytes(&self.build())?;
+        let report = crate::validate::validate(&data)?;
+        let first = report.errors().next().cloned();
+        match first {
+            Some(diagnostic) => Err(Error::Invalid(Box::new(diagnostic))),
+            None => Ok(data),
+        }
+    }
+}
+
+#[test]
+fn test_voice_builder() {
+    use crate::de::from_bytes;
+    use crate::voice::{CstVal, DurStat, Tree, TreeFeatures, TreeNode};
+
+    // a single leaf, and no features to ask about
+    let leaf = |value: f32| {
+        let node = TreeNode(255, 255, 0, CstVal::Float(value));
+        Tree(vec![node], TreeFeatures(Vec::new()))
+    };
+    let frames: Array2d = [[0u8; 8]].into_iter().collect();
+    let builder = VoiceBuilder::new("test_voice")
+        .gender(Gender::Female)
+        .types(vec!["pau_1".to_string()])
+        .f0(180.0, 20.0)
+        .f0_model(Trees(vec![leaf(180.0)]))
+        .param_model(Trees(vec![leaf(0.0)]), ModelVectors(2, 1, frames))
+        .model_range(Array(vec![0; 8]), Array(vec![0; 8]))
+        .dur_model(DurModel(
+            vec![DurStat(0.1, 0.05, "pau".to_string())],
+            leaf(0.0),
+        ))
+        .phone_states(vec![vec!["pau".to_string(), "pau_1".to_string()]]);
+
+    let voice = builder.clone().build();
+    assert_eq!(voice.header.features.num_f0_models, 1);
+    assert_eq!(voice.header.features.num_param_models, 1);
+    assert_eq!(voice.body.num_types, 1);
+    let data = builder.clone().to_bytes().unwrap();
+    assert_eq!(from_bytes::<TreeDb>(&data).unwrap(), voice);
+    assert!(crate::validate::validate(&data).unwrap().is_valid());
+
+    // a type without its f0 tree
+    let builder = builder.types(vec!["pau_1".to_string(), "pau_2".to_string()]);
+    let Err(Error::Invalid(diagnostic)) = builder.to_bytes() else {
+        panic!("the missing tree was not found");
+    };
+    assert_eq!(diagnostic.code, "count-mismatch");
+    assert_eq!(diagnostic.field, "f0_trees");
+}
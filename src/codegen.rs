@@ -0,0 +1,878 @@
+//! Generate source code which embeds a voice, the way flite bakes its voices into C arrays.
+
+use core::fmt::{self, Write};
+
+use crate::diagnostic::Diagnostic;
+use crate::error::{Error, Result};
+use crate::model::{Cart, CgModel, Value};
+
+// Bytes per line of the generated frame arrays.
+const BYTES_PER_LINE: usize = 24;
+
+fn rust_f32(out: &mut String, value: f32) -> fmt::Result {
+    if value.is_nan() {
+        out.write_str("f32::NAN")
+    } else if value.is_infinite() {
+        out.write_str(if value > 0.0 {
+            "f32::INFINITY"
+        } else {
+            "f32::NEG_INFINITY"
+        })
+    } else {
+        // Debug always prints a decimal point or exponent, so the literal stays a float
+        write!(out, "{value:?}")
+    }
+}
+
+fn rust_strs(out: &mut String, strs: &[String]) -> fmt::Result {
+    out.write_char('[')?;
+    for s in strs {
+        write!(out, "{s:?}, ")?;
+    }
+    out.write_char(']')
+}
+
+fn rust_f32s(out: &mut String, values: &[f32]) -> fmt::Result {
+    out.write_char('[')?;
+    for value in values {
+        rust_f32(out, *value)?;
+        out.write_str(", ")?;
+    }
+    out.write_char(']')
+}
+
+fn rust_cart(out: &mut String, cart: &Cart) -> fmt::Result {
+    out.write_str("Cart { nodes: &[")?;
+    for node in &cart.nodes {
+        write!(
+            out,
+            "CartNode {{ feature: {}, op: {}, no_node: {}, value: ",
+            node.feature,
+            u8::from(node.op),
+            node.no_node
+        )?;
+        match &node.value {
+            Value::Int(i) => write!(out, "Value::Int({i})")?,
+            Value::Float(f) => {
+                out.write_str("Value::Float(")?;
+                rust_f32(out, *f)?;
+                out.write_char(')')?;
+            }
+            Value::Str(s) => write!(out, "Value::Str({s:?})")?,
+        }
+        out.write_str(" }, ")?;
+    }
+    out.write_str("], features: &")?;
+    rust_strs(out, &cart.features)?;
+    out.write_str(" }")
+}
+
+fn rust_carts(out: &mut String, models: &[Vec<Cart>]) -> fmt::Result {
+    out.write_str("&[")?;
+    for carts in models {
+        out.write_str("\n        &[")?;
+        for cart in carts {
+            out.write_str("\n            ")?;
+            rust_cart(out, cart)?;
+            out.write_char(',')?;
+        }
+        out.write_str("\n        ],")?;
+    }
+    out.write_str("\n    ]")
+}
+
+/// Fail unless every parameter model has frames of one length.
+fn check_frames(model: &CgModel) -> Result<()> {
+    let ragged = model
+        .param_models
+        .iter()
+        .position(|p| p.frames.row_len().is_none());
+    match ragged {
+        Some(i) => {
+            let message = format!("the frames of parameter model {i} differ in length");
+            let field = format!("param_models[{i}]");
+            let diagnostic = Diagnostic::error("ragged-frames", &field, message);
+            Err(Error::Invalid(Box::new(diagnostic)))
+        }
+        None => Ok(()),
+    }
+}
+
+fn rust_module_inner(out: &mut String, model: &CgModel) -> fmt::Result {
+    writeln!(
+        out,
+        "// Generated by serde_cst from the voice {:?}. Do not edit.",
+        model.name
+    )?;
+    writeln!(out, "use serde_cst::baked::*;")?;
+    // the frames are by far the largest part, keep them out of the voice literal
+    for (i, params) in model.param_models.iter().enumerate() {
+        let bytes = params.frames.as_bytes();
+        write!(out, "\nstatic FRAMES_{i}: [u8; {}] = [", bytes.len())?;
+        for (j, byte) in bytes.iter().enumerate() {
+            if j % BYTES_PER_LINE == 0 {
+                out.write_str("\n    ")?;
+            }
+            write!(out, "{byte}, ")?;
+        }
+        out.write_str("\n];\n")?;
+    }
+
+    out.write_str("\npub static VOICE: CgVoice = CgVoice {\n")?;
+    writeln!(out, "    name: {:?},", model.name)?;
+    writeln!(out, "    sample_rate: {},", model.sample_rate)?;
+    for (field, value) in [
+        ("frame_advance", model.frame_advance),
+        ("f0_mean", model.f0_mean),
+        ("f0_stddev", model.f0_stddev),
+    ] {
+        write!(out, "    {field}: ")?;
+        rust_f32(out, value)?;
+        out.write_str(",\n")?;
+    }
+    out.write_str("    state_names: &")?;
+    rust_strs(out, &model.state_names)?;
+    out.write_str(",\n    f0_trees: ")?;
+    rust_carts(out, &model.f0_trees)?;
+    out.write_str(",\n    param_trees: ")?;
+    rust_carts(out, &model.param_trees)?;
+    out.write_str(",\n    param_models: &[")?;
+    for (i, params) in model.param_models.iter().enumerate() {
+        let frame_len = params.frames.row_len().unwrap_or(0);
+        write!(
+            out,
+            "\n        ParamModel {{ num_channels: {}, frame_len: {frame_len}, frames: &FRAMES_{i} }},",
+            params.num_channels
+        )?;
+    }
+    out.write_str("\n    ],\n")?;
+    writeln!(out, "    model_shape: {},", model.quantization.model_shape)?;
+    out.write_str("    model_min: &")?;
+    rust_f32s(out, &model.quantization.model_min)?;
+    out.write_str(",\n    model_range: &")?;
+    rust_f32s(out, &model.quantization.model_range)?;
+    out.write_str(",\n    dur_models: &[")?;
+    for dur in &model.dur_models {
+        out.write_str("\n        DurModel {\n            stats: &[")?;
+        for stat in &dur.stats {
+            write!(
+                out,
+                "\n                DurStat {{ phone: {:?}, mean: ",
+                stat.phone
+            )?;
+            rust_f32(out, stat.mean)?;
+            out.write_str(", stddev: ")?;
+            rust_f32(out, stat.stddev)?;
+            out.write_str(" },")?;
+        }
+        out.write_str("\n            ],\n            tree: ")?;
+        rust_cart(out, &dur.tree)?;
+        out.write_str(",\n        },")?;
+    }
+    out.write_str("\n    ],\n    phone_states: &[")?;
+    for phone in &model.phone_states.phones {
+        write!(
+            out,
+            "\n        PhoneState {{ phone: {:?}, states: &",
+            phone.phone
+        )?;
+        rust_strs(out, &phone.states)?;
+        out.write_str(" },")?;
+    }
+    out.write_str("\n    ],\n};\n")
+}
+
+/// Generate a Rust module holding `model` as a `pub static VOICE` of [`baked`](crate::baked)
+/// types.
+///
+/// The module expects this crate to be available as `serde_cst`; it does not need the `alloc`
+/// feature. Frames become one `static` byte array per parameter model, read with a single
+/// `frame_len`, so a model whose frames differ in length is an error.
+pub fn rust_module(model: &CgModel) -> Result<String> {
+    check_frames(model)?;
+    let mut out = String::new();
+    rust_module_inner(&mut out, model).expect("writing to a String cannot fail");
+    Ok(out)
+}
+
+fn c_f32(value: f32) -> String {
+    if value.is_nan() {
+        "NAN".to_string()
+    } else if value.is_infinite() {
+        let sign = if value < 0.0 { "-" } else { "" };
+        format!("{sign}INFINITY")
+    } else {
+        format!("{value:?}f")
+    }
+}
+
+fn c_f64(value: f64) -> String {
+    if value.is_nan() {
+        "NAN".to_string()
+    } else if value.is_infinite() {
+        let sign = if value < 0.0 { "-" } else { "" };
+        format!("{sign}INFINITY")
+    } else {
+        format!("{value:?}")
+    }
+}
+
+/// A C string literal. Anything but printable ASCII becomes an octal escape, which unlike a hex
+/// escape cannot swallow the characters after it.
+fn c_str(s: &str) -> String {
+    let mut out = String::from("\"");
+    for byte in s.bytes() {
+        match byte {
+            b'"' | b'\\' => {
+                out.push('\\');
+                out.push(char::from(byte));
+            }
+            b' '..=b'~' => out.push(char::from(byte)),
+            _ => out.push_str(&format!("\\{byte:03o}")),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// A C identifier made from a voice name, like flite's `cmu_us_slt`.
+fn c_ident(name: &str) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        ident.insert(0, '_');
+    }
+    ident
+}
+
+// Declare `name` as an array of `ty`, without a space between a pointer and the name.
+fn c_array(ty: &str, name: &str) -> String {
+    if ty.ends_with('*') {
+        format!("{ty}{name}[]")
+    } else {
+        format!("{ty} {name}[]")
+    }
+}
+
+struct CSource<'a> {
+    out: String,
+    prefix: &'a str,
+}
+impl CSource<'_> {
+    /// A null terminated array of strings.
+    fn strs<'s>(&mut self, name: &str, strs: impl IntoIterator<Item = &'s String>) -> fmt::Result {
+        write!(self.out, "static const char * const {name}[] = {{")?;
+        for s in strs {
+            write!(self.out, " {},", c_str(s))?;
+        }
+        self.out.write_str(" NULL };\n")
+    }
+    fn numbers(
+        &mut self,
+        ty: &str,
+        name: &str,
+        values: impl IntoIterator<Item = String>,
+    ) -> fmt::Result {
+        write!(self.out, "static {} = {{", c_array(ty, name))?;
+        for value in values {
+            write!(self.out, " {value},")?;
+        }
+        self.out.write_str(" };\n")
+    }
+    /// A null terminated array of pointers to `names`.
+    fn pointers(
+        &mut self,
+        ty: &str,
+        name: &str,
+        names: impl IntoIterator<Item = String>,
+    ) -> fmt::Result {
+        write!(self.out, "static {} = {{", c_array(ty, name))?;
+        for value in names {
+            write!(self.out, " {value},")?;
+        }
+        self.out.write_str(" NULL };\n")
+    }
+    fn cart(&mut self, name: &str, cart: &Cart) -> fmt::Result {
+        for (i, node) in cart.nodes.iter().enumerate() {
+            let (kind, value) = match &node.value {
+                Value::Int(v) => ("INT", v.to_string()),
+                Value::Float(v) => ("FLOAT", c_f32(*v)),
+                Value::Str(s) => ("STRING", c_str(s)),
+            };
+            writeln!(
+                self.out,
+                "DEF_STATIC_CONST_VAL_{kind}({name}_val_{i}, {value});"
+            )?;
+        }
+        writeln!(self.out, "static const cst_cart_node {name}_nodes[] = {{")?;
+        for (i, node) in cart.nodes.iter().enumerate() {
+            writeln!(
+                self.out,
+                "    {{ {}, {}, {}, (const cst_val *)&{name}_val_{i} }},",
+                node.feature,
+                u8::from(node.op),
+                node.no_node
+            )?;
+        }
+        self.out.write_str("    { 255, 0, 0, 0 }\n};\n")?;
+        self.strs(&format!("{name}_feats"), &cart.features)?;
+        writeln!(
+            self.out,
+            "static const cst_cart {name} = {{ {name}_nodes, {name}_feats }};"
+        )
+    }
+    /// Trees per model, as `const cst_cart ***`.
+    fn carts(&mut self, section: &str, models: &[Vec<Cart>]) -> fmt::Result {
+        let prefix = self.prefix;
+        for (i, carts) in models.iter().enumerate() {
+            for (j, cart) in carts.iter().enumerate() {
+                self.cart(&format!("{prefix}_{section}_{i}_{j}"), cart)?;
+            }
+            self.pointers(
+                "const cst_cart *",
+                &format!("{prefix}_{section}_{i}"),
+                (0..carts.len()).map(|j| format!("&{prefix}_{section}_{i}_{j}")),
+            )?;
+        }
+        self.pointers(
+            "const cst_cart **",
+            &format!("{prefix}_{section}"),
+            (0..models.len()).map(|i| format!("{prefix}_{section}_{i}")),
+        )
+    }
+    /// The frames of every parameter model as one flat array, and a table pointing into it.
+    fn model_vectors(&mut self, model: &CgModel) -> fmt::Result {
+        let prefix = self.prefix;
+        for (i, params) in model.param_models.iter().enumerate() {
+            let frame_len = params.frames.row_len().unwrap_or(0);
+            // quantized shapes store byte indices, which flite reads through the same pointers
+            let shorts = frame_len == params.num_channels * 2;
+            let ty = if shorts { "short" } else { "char" };
+            write!(
+                self.out,
+                "static const unsigned {ty} {prefix}_frames_{i}[] = {{"
+            )?;
+            let bytes = params.frames.as_bytes();
+            let cells: Vec<u16> = if shorts {
+                bytes
+                    .chunks_exact(2)
+                    .map(|cell| u16::from_le_bytes([cell[0], cell[1]]))
+                    .collect()
+            } else {
+                bytes.iter().map(|&b| b.into()).collect()
+            };
+            for (j, cell) in cells.iter().enumerate() {
+                if j % BYTES_PER_LINE == 0 {
+                    self.out.write_str("\n   ")?;
+                }
+                write!(self.out, " {cell},")?;
+            }
+            self.out.write_str("\n};\n")?;
+            let mut offset = 0;
+            let rows: Vec<String> = params
+                .frames
+                .rows()
+                .map(|row| {
+                    let start = offset;
+                    offset += if shorts { row.0.len() / 2 } else { row.0.len() };
+                    if shorts {
+                        format!("\n    {prefix}_frames_{i} + {start}")
+                    } else {
+                        format!("\n    (const unsigned short *)({prefix}_frames_{i} + {start})")
+                    }
+                })
+                .collect();
+            self.pointers(
+                "const unsigned short *",
+                &format!("{prefix}_model_vectors_{i}"),
+                rows,
+            )?;
+        }
+        self.pointers(
+            "const unsigned short **",
+            &format!("{prefix}_model_vectors"),
+            (0..model.param_models.len()).map(|i| format!("{prefix}_model_vectors_{i}")),
+        )?;
+        self.numbers(
+            "int",
+            &format!("{prefix}_num_channels"),
+            model
+                .param_models
+                .iter()
+                .map(|p| p.num_channels.to_string()),
+        )?;
+        self.numbers(
+            "int",
+            &format!("{prefix}_num_frames"),
+            model
+                .param_models
+                .iter()
+                .map(|p| p.frames.len().to_string()),
+        )
+    }
+}
+
+fn c_source_inner(c: &mut CSource, model: &CgModel) -> fmt::Result {
+    let prefix = c.prefix;
+    c.out
+        .write_str("/* Generated by serde_cst. Do not edit. */\n")?;
+    c.out
+        .write_str("#include <math.h>\n#include \"cst_cg.h\"\n\n")?;
+    c.strs(&format!("{prefix}_types"), &model.state_names)?;
+    c.carts("f0_trees", &model.f0_trees)?;
+    c.carts("param_trees", &model.param_trees)?;
+    c.model_vectors(model)?;
+
+    let quantization = &model.quantization;
+    c.numbers(
+        "const float",
+        &format!("{prefix}_model_min"),
+        quantization.model_min.iter().map(|v| c_f32(*v)),
+    )?;
+    c.numbers(
+        "const float",
+        &format!("{prefix}_model_range"),
+        quantization.model_range.iter().map(|v| c_f32(*v)),
+    )?;
+    let qtable = if quantization.qtables.is_empty() {
+        "NULL".to_string()
+    } else {
+        for (i, tables) in quantization.qtables.iter().enumerate() {
+            for (j, table) in tables.iter().enumerate() {
+                c.numbers(
+                    "const float",
+                    &format!("{prefix}_qtable_{i}_{j}"),
+                    table.iter().map(|v| c_f32(*v)),
+                )?;
+            }
+            c.pointers(
+                "const float *",
+                &format!("{prefix}_qtable_{i}"),
+                (0..tables.len()).map(|j| format!("{prefix}_qtable_{i}_{j}")),
+            )?;
+        }
+        c.pointers(
+            "const float **",
+            &format!("{prefix}_qtable"),
+            (0..quantization.qtables.len()).map(|i| format!("{prefix}_qtable_{i}")),
+        )?;
+        format!("{prefix}_qtable")
+    };
+
+    for (i, dur) in model.dur_models.iter().enumerate() {
+        for (j, stat) in dur.stats.iter().enumerate() {
+            writeln!(
+                c.out,
+                "static const dur_stat {prefix}_dur_stat_{i}_{j} = {{ {}, {}, {} }};",
+                c_str(&stat.phone),
+                c_f32(stat.mean),
+                c_f32(stat.stddev)
+            )?;
+        }
+        c.pointers(
+            "const dur_stat *",
+            &format!("{prefix}_dur_stats_{i}"),
+            (0..dur.stats.len()).map(|j| format!("&{prefix}_dur_stat_{i}_{j}")),
+        )?;
+        c.cart(&format!("{prefix}_dur_cart_{i}"), &dur.tree)?;
+    }
+    c.pointers(
+        "const dur_stat **",
+        &format!("{prefix}_dur_stats"),
+        (0..model.dur_models.len()).map(|i| format!("{prefix}_dur_stats_{i}")),
+    )?;
+    c.pointers(
+        "const cst_cart *",
+        &format!("{prefix}_dur_cart"),
+        (0..model.dur_models.len()).map(|i| format!("&{prefix}_dur_cart_{i}")),
+    )?;
+
+    // flite lists the phone first, then its states
+    for (i, phone) in model.phone_states.phones.iter().enumerate() {
+        c.strs(
+            &format!("{prefix}_phone_states_{i}"),
+            core::iter::once(&phone.phone).chain(&phone.states),
+        )?;
+    }
+    c.pointers(
+        "const char * const * const",
+        &format!("{prefix}_phone_states"),
+        (0..model.phone_states.phones.len()).map(|i| format!("{prefix}_phone_states_{i}")),
+    )?;
+
+    let synthesis = &model.synthesis;
+    let dynwin = if synthesis.dynwin.is_empty() {
+        "NULL".to_string()
+    } else {
+        c.numbers(
+            "float",
+            &format!("{prefix}_dynwin"),
+            synthesis.dynwin.iter().map(|v| c_f32(*v)),
+        )?;
+        format!("{prefix}_dynwin")
+    };
+    let me_h = if synthesis.me_filters.is_empty() {
+        "NULL".to_string()
+    } else {
+        for (i, filter) in synthesis.me_filters.iter().enumerate() {
+            c.numbers(
+                "const double",
+                &format!("{prefix}_me_filter_{i}"),
+                filter.iter().map(|v| c_f64(*v)),
+            )?;
+        }
+        c.pointers(
+            "const double * const",
+            &format!("{prefix}_me_h"),
+            (0..synthesis.me_filters.len()).map(|i| format!("{prefix}_me_filter_{i}")),
+        )?;
+        format!("{prefix}_me_h")
+    };
+
+    // designated initializers keep this independent of the field order of the flite release
+    writeln!(c.out, "\ncst_cg_db {prefix}_cg_db = {{")?;
+    let fields = [
+        ("name", c_str(&model.name)),
+        ("types", format!("{prefix}_types")),
+        ("num_types", model.state_names.len().to_string()),
+        ("sample_rate", model.sample_rate.to_string()),
+        ("f0_mean", c_f32(model.f0_mean)),
+        ("f0_stddev", c_f32(model.f0_stddev)),
+        ("num_f0_models", model.f0_trees.len().to_string()),
+        ("f0_trees", format!("{prefix}_f0_trees")),
+        ("num_param_models", model.param_models.len().to_string()),
+        ("param_trees", format!("{prefix}_param_trees")),
+        ("num_channels", format!("{prefix}_num_channels")),
+        ("num_frames", format!("{prefix}_num_frames")),
+        ("model_vectors", format!("{prefix}_model_vectors")),
+        ("model_min", format!("{prefix}_model_min")),
+        ("model_range", format!("{prefix}_model_range")),
+        ("qtable", qtable),
+        ("model_shape", quantization.model_shape.to_string()),
+        ("frame_advance", c_f32(model.frame_advance)),
+        ("num_dur_models", model.dur_models.len().to_string()),
+        ("dur_stats", format!("{prefix}_dur_stats")),
+        ("dur_cart", format!("{prefix}_dur_cart")),
+        ("phone_states", format!("{prefix}_phone_states")),
+        ("do_mlpg", u8::from(synthesis.do_mlpg).to_string()),
+        ("dynwin", dynwin),
+        ("dynwinsize", synthesis.dynwin.len().to_string()),
+        ("mlsa_alpha", c_f32(synthesis.mlsa_alpha)),
+        ("mlsa_beta", c_f32(synthesis.mlsa_beta)),
+        ("multimodel", u8::from(synthesis.multimodel).to_string()),
+        (
+            "mixed_excitation",
+            u8::from(synthesis.mixed_excitation).to_string(),
+        ),
+        ("ME_num", synthesis.me_filters.len().to_string()),
+        (
+            "ME_order",
+            synthesis.me_filters.first().map_or(0, Vec::len).to_string(),
+        ),
+        ("me_h", me_h),
+        ("gain", c_f32(synthesis.gain)),
+    ];
+    for (field, value) in fields {
+        writeln!(c.out, "    .{field} = {value},")?;
+    }
+    c.out.write_str("};\n")
+}
+
+/// Generate a C file defining `<name>_cg_db`, the `cst_cg_db` of `model`, so it can be linked
+/// into a flite build in place of a voice flite baked itself.
+///
+/// The file includes flite's `cst_cg.h`. Voices using spamf0 lose those models. flite steps
+/// through the frames of a model by one width, so frames differing in length are an error.
+pub fn c_source(model: &CgModel) -> Result<String> {
+    check_frames(model)?;
+    let prefix = c_ident(&model.name);
+    let mut c = CSource {
+        out: String::new(),
+        prefix: &prefix,
+    };
+    c_source_inner(&mut c, model).expect("writing to a String cannot fail");
+    Ok(c.out)
+}
+
+#[cfg(test)]
+fn test_model() -> CgModel {
+    use crate::model::{
+        CartNode, CartOp, DurModel, DurStat, ParamModel, PhoneState, PhoneStates, Quantization,
+        Synthesis,
+    };
+    let cart = Cart {
+        nodes: vec![
+            CartNode {
+                feature: 0,
+                op: CartOp::Is,
+                no_node: 2,
+                value: Value::Str("n".to_string()),
+            },
+            CartNode {
+                feature: 255,
+                op: CartOp::Leaf,
+                no_node: 0,
+                value: Value::Float(0.5),
+            },
+            CartNode {
+                feature: 255,
+                op: CartOp::Leaf,
+                no_node: 0,
+                value: Value::Int(-1),
+            },
+        ],
+        features: vec!["name".to_string()],
+    };
+    CgModel {
+        name: "test".to_string(),
+        sample_rate: 16000,
+        frame_advance: 0.005,
+        f0_mean: 172.0,
+        f0_stddev: f32::NAN,
+        state_names: vec!["n_1".to_string()],
+        f0_trees: vec![vec![cart.clone()]],
+        param_trees: vec![vec![cart.clone()]],
+        param_models: vec![ParamModel {
+            num_channels: 1,
+            frames: [[1, 0], [0xff, 0xff]].into_iter().collect(),
+        }],
+        quantization: Quantization {
+            model_shape: 1,
+            model_min: vec![-1.0],
+            model_range: vec![2.0],
+            qtables: Vec::new(),
+        },
+        dur_models: vec![DurModel {
+            stats: vec![DurStat {
+                phone: "n".to_string(),
+                mean: 0.1,
+                stddev: 0.02,
+            }],
+            tree: cart,
+        }],
+        phone_states: PhoneStates {
+            phones: vec![PhoneState {
+                phone: "n".to_string(),
+                states: vec!["n_1".to_string()],
+            }],
+        },
+        synthesis: Synthesis {
+            do_mlpg: true,
+            dynwin: vec![-0.5, 0.0, 0.5],
+            mlsa_alpha: 0.42,
+            mlsa_beta: 0.4,
+            multimodel: false,
+            mixed_excitation: true,
+            me_filters: vec![vec![0.25, -0.5]],
+            gain: 1.5,
+        },
+    }
+}
+
+#[test]
+fn test_rust_module() {
+    let module = rust_module(&test_model()).unwrap();
+    assert!(module.contains("static FRAMES_0: [u8; 4] = [\n    1, 0, 255, 255, \n];"));
+    assert!(module.contains("name: \"test\","));
+    assert!(module.contains("frame_advance: 0.005,"));
+    assert!(module.contains("f0_mean: 172.0,"));
+    assert!(module.contains("f0_stddev: f32::NAN,"));
+    assert!(module.contains(
+        "CartNode { feature: 0, op: 0, no_node: 2, value: Value::Str(\"n\") }, \
+         CartNode { feature: 255, op: 255, no_node: 0, value: Value::Float(0.5) }, \
+         CartNode { feature: 255, op: 255, no_node: 0, value: Value::Int(-1) }, \
+         ], features: &[\"name\", ] }"
+    ));
+    assert!(module.contains("ParamModel { num_channels: 1, frame_len: 2, frames: &FRAMES_0 }"));
+    assert!(module.contains("DurStat { phone: \"n\", mean: 0.1, stddev: 0.02 }"));
+    assert!(module.contains("PhoneState { phone: \"n\", states: &[\"n_1\", ] }"));
+}
+
+#[test]
+fn test_c_source() {
+    assert_eq!(c_str("a\"b\\c\n\u{e9}"), "\"a\\\"b\\\\c\\012\\303\\251\"");
+    assert_eq!(c_ident("cmu_us_slt"), "cmu_us_slt");
+    assert_eq!(c_ident("1 voice"), "_1_voice");
+
+    let source = c_source(&test_model()).unwrap();
+    assert!(source.contains("DEF_STATIC_CONST_VAL_STRING(test_f0_trees_0_0_val_0, \"n\");"));
+    assert!(source.contains("DEF_STATIC_CONST_VAL_FLOAT(test_f0_trees_0_0_val_1, 0.5f);"));
+    assert!(source.contains("DEF_STATIC_CONST_VAL_INT(test_f0_trees_0_0_val_2, -1);"));
+    assert!(source.contains("    { 0, 0, 2, (const cst_val *)&test_f0_trees_0_0_val_0 },\n"));
+    assert!(source
+        .contains("static const cst_cart **test_param_trees[] = { test_param_trees_0, NULL };"));
+    assert!(source.contains("static const unsigned short test_frames_0[] = {\n    1, 65535,\n};"));
+    assert!(source
+        .contains("static const char * const test_phone_states_0[] = { \"n\", \"n_1\", NULL };"));
+    assert!(source.contains("static const dur_stat test_dur_stat_0_0 = { \"n\", 0.1f, 0.02f };"));
+    assert!(source.contains("static const double test_me_filter_0[] = { 0.25, -0.5, };"));
+    assert!(source.contains("\ncst_cg_db test_cg_db = {\n    .name = \"test\",\n"));
+    for field in [
+        ".f0_stddev = NAN,",
+        ".qtable = NULL,",
+        ".dynwinsize = 3,",
+        ".ME_num = 1,",
+        ".ME_order = 2,",
+        ".gain = 1.5f,",
+    ] {
+        assert!(source.contains(field), "{field}");
+    }
+}
+
+// The declarations of flite's headers which the generated file uses, so it can be compiled
+// without a flite checkout.
+#[cfg(all(test, feature = "std"))]
+const CST_CG_H: &str = r#"
+#include <stddef.h>
+
+typedef struct cst_val_struct {
+    int type;
+    int ref_count;
+    union { double fval; long long ival; void *vval; } v;
+} cst_val;
+#define DEF_STATIC_CONST_VAL_INT(N, I) \
+    static const cst_val N = { .type = 1, .ref_count = -1, .v = { .ival = I } }
+#define DEF_STATIC_CONST_VAL_FLOAT(N, F) \
+    static const cst_val N = { .type = 3, .ref_count = -1, .v = { .fval = F } }
+#define DEF_STATIC_CONST_VAL_STRING(N, S) \
+    static const cst_val N = { .type = 5, .ref_count = -1, .v = { .vval = (void *)S } }
+
+typedef struct cst_cart_node_struct {
+    unsigned char feat;
+    unsigned char op;
+    unsigned short no_node;
+    const cst_val *val;
+} cst_cart_node;
+typedef struct cst_cart_struct {
+    const cst_cart_node *rule_table;
+    const char * const *feat_table;
+} cst_cart;
+typedef struct dur_stat_struct {
+    char *phone;
+    float mean;
+    float stddev;
+} dur_stat;
+
+typedef struct cst_cg_db_struct {
+    const char *name;
+    const char * const *types;
+    int num_types;
+    int sample_rate;
+    float f0_mean, f0_stddev;
+    int num_f0_models;
+    const cst_cart ***f0_trees;
+    int num_param_models;
+    const cst_cart ***param_trees;
+    const cst_cart *spamf0_accent_tree;
+    const cst_cart *spamf0_phrase_tree;
+    int *num_channels;
+    int *num_frames;
+    const unsigned short ***model_vectors;
+    int num_channels_spamf0_accent;
+    int num_frames_spamf0_accent;
+    const float * const *spamf0_accent_vectors;
+    const float *model_min;
+    const float *model_range;
+    const float ***qtable;
+    int model_shape;
+    float frame_advance;
+    int num_dur_models;
+    const dur_stat ***dur_stats;
+    const cst_cart **dur_cart;
+    const char * const * const *phone_states;
+    int do_mlpg;
+    float *dynwin;
+    int dynwinsize;
+    float mlsa_alpha;
+    float mlsa_beta;
+    int multimodel;
+    int mixed_excitation;
+    int ME_num;
+    int ME_order;
+    const double * const *me_h;
+    int spamf0;
+    float gain;
+    int freeable;
+} cst_cg_db;
+"#;
+
+#[cfg(feature = "std")]
+#[test]
+fn test_c_source_compiles() {
+    use std::process::Command;
+
+    let dir = std::env::temp_dir().join(format!("serde_cst_codegen_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("cst_cg.h"), CST_CG_H).unwrap();
+    let mut model = test_model();
+    model.quantization.qtables = vec![vec![vec![0.5, 1.0]]];
+    std::fs::write(dir.join("voice.c"), c_source(&model).unwrap()).unwrap();
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let output = Command::new(&cc)
+        .args(["-std=c99", "-Wall", "-Werror", "-fsyntax-only", "-I"])
+        .arg(&dir)
+        .arg(dir.join("voice.c"))
+        .output();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let Ok(output) = output else {
+        eprintln!("skipped: {cc} is not available");
+        return;
+    };
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_ragged_frames() {
+    let mut model = test_model();
+    model.param_models[0].frames = [&[1, 0][..], &[0]].into_iter().collect();
+    let Err(Error::Invalid(diagnostic)) = rust_module(&model) else {
+        panic!("ragged frames were accepted");
+    };
+    assert_eq!(diagnostic.code, "ragged-frames");
+    assert_eq!(diagnostic.field, "param_models[0]");
+    assert!(c_source(&model).is_err());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_rust_module_compiles() {
+    use std::process::Command;
+
+    let dir = std::env::temp_dir().join(format!("serde_cst_rust_module_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // the generated module only needs `baked`, so a crate of just that module stands in for this one
+    let baked = concat!(env!("CARGO_MANIFEST_DIR"), "/src/baked.rs");
+    let root = format!("#![no_std]\n#[path = {baked:?}]\npub mod baked;\n");
+    std::fs::write(dir.join("serde_cst.rs"), root).unwrap();
+    std::fs::write(dir.join("voice.rs"), rust_module(&test_model()).unwrap()).unwrap();
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc = |args: &[&str]| {
+        Command::new(&rustc)
+            .args(["--edition=2021", "--crate-type=rlib", "-Dwarnings"])
+            .args(args)
+            .current_dir(&dir)
+            .output()
+    };
+    let output = rustc(&["serde_cst.rs"]).and_then(|output| {
+        if !output.status.success() {
+            return Ok(output);
+        }
+        rustc(&["--extern", "serde_cst=libserde_cst.rlib", "voice.rs"])
+    });
+    std::fs::remove_dir_all(&dir).unwrap();
+    let Ok(output) = output else {
+        eprintln!("skipped: rustc is not available");
+        return;
+    };
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
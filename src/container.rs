@@ -0,0 +1,58 @@
+//! The magics CST dumps start with.
+//!
+//! Every dump is a magic, the endianness marker, and then entries in the same length-prefixed
+//! encoding. flite defines a single magic, that of clustergen voices; [`Container::Magic`] lets
+//! the [`Deserializer`](crate::de::Deserializer) and the [`Serializer`](crate::ser::Serializer)
+//! read and write dumps with any other, like compiled lexicons.
+
+use crate::{FormatVersion, FLITEVOX_MAGIC};
+
+/// flite has no file format for lexicons, which it compiles into C; this is the magic lexicon
+/// dumps written by this crate start with, including its null terminator.
+pub const LEXDATA_MAGIC: &[u8] = b"CMU_FLITE_LEXDATA-v1.0\0";
+
+/// What a dump starts with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Container {
+    /// A clustergen voice, starting with the magic of any [`FormatVersion`] this crate reads.
+    #[default]
+    Voxdata,
+    /// A dump starting with exactly these bytes.
+    Magic(&'static [u8]),
+}
+impl Container {
+    /// A [`Lexicon`](crate::lexicon::Lexicon) dump.
+    pub const LEXICON: Container = Container::Magic(LEXDATA_MAGIC);
+
+    /// The bytes the magic takes.
+    pub const fn magic_len(self) -> usize {
+        match self {
+            Container::Voxdata => FLITEVOX_MAGIC.len(),
+            Container::Magic(magic) => magic.len(),
+        }
+    }
+    /// The magic a new dump starts with.
+    pub const fn magic(self) -> &'static [u8] {
+        match self {
+            Container::Voxdata => FLITEVOX_MAGIC,
+            Container::Magic(magic) => magic,
+        }
+    }
+    /// Whether `input` starts with the magic.
+    pub fn detect(self, input: &[u8]) -> bool {
+        match self {
+            Container::Voxdata => FormatVersion::detect(input).is_some(),
+            Container::Magic(magic) => input.starts_with(magic),
+        }
+    }
+}
+
+#[test]
+fn test_container() {
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    assert!(Container::Voxdata.detect(data));
+    assert!(Container::Voxdata.detect(b"CMU_FLITE_CG_VOXDATA-v1.5\0\x01\0\0\0"));
+    assert!(!Container::LEXICON.detect(data));
+    assert!(Container::LEXICON.detect(b"CMU_FLITE_LEXDATA-v1.0\0\x01\0\0\0"));
+    assert_eq!(Container::LEXICON.magic_len(), 23);
+}
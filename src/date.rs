@@ -1,35 +1,398 @@
-use chrono::{DateTime, NaiveDateTime, Utc};
-use serde::{self, Deserialize, Deserializer, Serializer};
-
-const FORMAT: &'static str = "%Y-%m-%d_%H:%M";
-
-// The signature of a serialize_with function must follow the pattern:
-//
-//    fn serialize<S>(&T, S) -> Result<S::Ok, S::Error>
-//    where
-//        S: Serializer
-//
-// although it may also be generic over the input types T.
-pub fn serialize<S>(date: &NaiveDateTime, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    let s = format!("{}", date.format(FORMAT));
-    serializer.serialize_str(&s)
-}
-
-// The signature of a deserialize_with function must follow the pattern:
-//
-//    fn deserialize<'de, D>(D) -> Result<T, D::Error>
-//    where
-//        D: Deserializer<'de>
-//
-// although it may also be generic over the output types T.
-pub fn deserialize<'de, D>(deserializer: D) -> Result<NaiveDateTime, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s = String::deserialize(deserializer)?;
-    let dt = NaiveDateTime::parse_from_str(&s, FORMAT).map_err(serde::de::Error::custom)?;
-    Ok(dt)
+//! The `build_date` feature of a voice.
+//!
+//! [`BuildDate`] parses the date festvox writes without any date crate, so reading a header
+//! doesn't pull one in. With the `chrono` or `time` features it converts to and from their
+//! types.
+
+use core::fmt;
+use core::str::FromStr;
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+/// The layouts of `build_date` found in real voices. Every field is zero padded to two digits,
+/// or four for the year.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DateFormat {
+    /// `2017-09-14_23:37`, what flite writes.
+    Flite,
+    /// `2017-09-14_23:37:05`
+    FliteSeconds,
+    /// `2017-09-14 23:37`
+    Space,
+    /// `2017-09-14 23:37:05`
+    SpaceSeconds,
+    /// `14-09-2017_23:37`
+    DayFirst,
+    /// `2017-09-14`, taken as midnight.
+    DateOnly,
+    /// `unknown`, taken as the epoch.
+    Unknown,
+}
+
+/// When a voice was built, to the second, and the layout it is written in. Years range from 0
+/// to 9999, as they are written with four digits.
+///
+/// A parsed date keeps the layout it was read in, so writing it gives back the same text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BuildDate {
+    year: u16,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+    format: DateFormat,
+}
+
+const fn is_leap_year(year: u16) -> bool {
+    year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400))
+}
+
+const fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl BuildDate {
+    /// 1970-01-01 at midnight, what voices which don't know their build date are given.
+    pub const EPOCH: BuildDate = BuildDate {
+        year: 1970,
+        month: 1,
+        day: 1,
+        hour: 0,
+        minute: 0,
+        second: 0,
+        format: DateFormat::Flite,
+    };
+    /// `unknown`, what festvox writes when it doesn't know the date. It is [`BuildDate::EPOCH`]
+    /// written differently.
+    pub const UNKNOWN: BuildDate = BuildDate::EPOCH.with_format(DateFormat::Unknown);
+
+    /// Returns `None` if this is no date and time of the day. The date is written the way flite
+    /// writes it, [`DateFormat::Flite`], or [`DateFormat::FliteSeconds`] if `second` isn't 0.
+    pub const fn new(
+        year: u16,
+        month: u8,
+        day: u8,
+        hour: u8,
+        minute: u8,
+        second: u8,
+    ) -> Option<BuildDate> {
+        if year > 9999
+            || month == 0
+            || month > 12
+            || day == 0
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 59
+        {
+            return None;
+        }
+        let format = if second == 0 {
+            DateFormat::Flite
+        } else {
+            DateFormat::FliteSeconds
+        };
+        Some(BuildDate {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            format,
+        })
+    }
+    /// The same date, written in `format`. Layouts without seconds, or without a time, leave
+    /// those out when writing.
+    pub const fn with_format(self, format: DateFormat) -> BuildDate {
+        BuildDate { format, ..self }
+    }
+    pub const fn format(&self) -> DateFormat {
+        self.format
+    }
+    pub const fn year(&self) -> u16 {
+        self.year
+    }
+    pub const fn month(&self) -> u8 {
+        self.month
+    }
+    pub const fn day(&self) -> u8 {
+        self.day
+    }
+    pub const fn hour(&self) -> u8 {
+        self.hour
+    }
+    pub const fn minute(&self) -> u8 {
+        self.minute
+    }
+    pub const fn second(&self) -> u8 {
+        self.second
+    }
+}
+impl Default for BuildDate {
+    fn default() -> BuildDate {
+        BuildDate::EPOCH
+    }
+}
+
+// A field of exactly `digits` digits.
+fn number(field: &str, digits: usize) -> Option<u16> {
+    if field.len() != digits || !field.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    field.parse().ok()
+}
+
+// `N` fields separated by `sep`.
+fn fields<const N: usize>(s: &str, sep: char) -> Option<[&str; N]> {
+    let mut fields = [""; N];
+    let mut parts = s.split(sep);
+    for field in &mut fields {
+        *field = parts.next()?;
+    }
+    parts.next().is_none().then_some(fields)
+}
+
+/// Parse a `build_date`, trying every known layout. The date keeps the layout which matched,
+/// see [`BuildDate::format`].
+pub fn parse(s: &str) -> Option<BuildDate> {
+    if s == "unknown" {
+        return Some(BuildDate::UNKNOWN);
+    }
+    let (date, time) = match s.find(['_', ' ']) {
+        Some(at) => (&s[..at], Some((s.as_bytes()[at] == b'_', &s[at + 1..]))),
+        None => (s, None),
+    };
+    let [a, b, c] = fields::<3>(date, '-')?;
+    let day_first = number(a, 2).is_some();
+    let (year, month, day) = if day_first { (c, b, a) } else { (a, b, c) };
+    let (year, month, day) = (number(year, 4)?, number(month, 2)?, number(day, 2)?);
+    let (time, format) = match time {
+        None if !day_first => ("00:00:00", DateFormat::DateOnly),
+        Some((true, time)) if day_first => (time, DateFormat::DayFirst),
+        Some((underscore, time)) if !day_first => {
+            let seconds = time.matches(':').count() == 2;
+            let format = match (underscore, seconds) {
+                (true, false) => DateFormat::Flite,
+                (true, true) => DateFormat::FliteSeconds,
+                (false, false) => DateFormat::Space,
+                (false, true) => DateFormat::SpaceSeconds,
+            };
+            (time, format)
+        }
+        _ => return None,
+    };
+    let (hour, minute, second) = match format {
+        DateFormat::FliteSeconds | DateFormat::SpaceSeconds | DateFormat::DateOnly => {
+            let [hour, minute, second] = fields::<3>(time, ':')?;
+            (hour, minute, second)
+        }
+        _ => {
+            let [hour, minute] = fields::<2>(time, ':')?;
+            (hour, minute, "00")
+        }
+    };
+    let [hour, minute, second] = [hour, minute, second].map(|field| number(field, 2));
+    let date = BuildDate::new(
+        year,
+        u8::try_from(month).ok()?,
+        u8::try_from(day).ok()?,
+        u8::try_from(hour?).ok()?,
+        u8::try_from(minute?).ok()?,
+        u8::try_from(second?).ok()?,
+    )?;
+    Some(date.with_format(format))
+}
+
+impl FromStr for BuildDate {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s).ok_or("build_date is in no known format")
+    }
+}
+
+/// Writes the date in its [format](BuildDate::format).
+impl fmt::Display for BuildDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let BuildDate {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            format,
+        } = *self;
+        match format {
+            DateFormat::Flite => write!(f, "{year:04}-{month:02}-{day:02}_{hour:02}:{minute:02}"),
+            DateFormat::FliteSeconds => write!(
+                f,
+                "{year:04}-{month:02}-{day:02}_{hour:02}:{minute:02}:{second:02}"
+            ),
+            DateFormat::Space => write!(f, "{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}"),
+            DateFormat::SpaceSeconds => write!(
+                f,
+                "{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02}"
+            ),
+            DateFormat::DayFirst => {
+                write!(f, "{day:02}-{month:02}-{year:04}_{hour:02}:{minute:02}")
+            }
+            DateFormat::DateOnly => write!(f, "{year:04}-{month:02}-{day:02}"),
+            DateFormat::Unknown => f.write_str("unknown"),
+        }
+    }
+}
+
+impl Serialize for BuildDate {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+struct BuildDateVisitor;
+impl Visitor<'_> for BuildDateVisitor {
+    type Value = BuildDate;
+    fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("A build date")
+    }
+    fn visit_str<E>(self, s: &str) -> Result<BuildDate, E>
+    where
+        E: de::Error,
+    {
+        parse(s).ok_or_else(|| E::custom(format_args!("build_date {s:?} is in no known format")))
+    }
+}
+impl<'de> Deserialize<'de> for BuildDate {
+    fn deserialize<D>(deserializer: D) -> Result<BuildDate, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(BuildDateVisitor)
+    }
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_impls {
+    use super::BuildDate;
+    use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+
+    impl From<BuildDate> for NaiveDateTime {
+        fn from(date: BuildDate) -> NaiveDateTime {
+            NaiveDate::from_ymd_opt(date.year.into(), date.month.into(), date.day.into())
+                .and_then(|day| {
+                    day.and_hms_opt(date.hour.into(), date.minute.into(), date.second.into())
+                })
+                .expect("a BuildDate is a valid date")
+        }
+    }
+    impl TryFrom<NaiveDateTime> for BuildDate {
+        type Error = &'static str;
+        /// Fails for years before 0 or after 9999. Fractions of a second are dropped.
+        fn try_from(date: NaiveDateTime) -> Result<BuildDate, Self::Error> {
+            let year = u16::try_from(date.year()).map_err(|_| "year out of range")?;
+            let (month, day) = (date.month() as u8, date.day() as u8);
+            let (hour, minute) = (date.hour() as u8, date.minute() as u8);
+            BuildDate::new(year, month, day, hour, minute, date.second() as u8)
+                .ok_or("year out of range")
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+mod time_impls {
+    use super::BuildDate;
+    use time::{Date, Month, PrimitiveDateTime, Time};
+
+    impl From<BuildDate> for PrimitiveDateTime {
+        fn from(date: BuildDate) -> PrimitiveDateTime {
+            Month::try_from(date.month)
+                .ok()
+                .and_then(|month| Date::from_calendar_date(date.year.into(), month, date.day).ok())
+                .zip(Time::from_hms(date.hour, date.minute, date.second).ok())
+                .map(|(day, time)| PrimitiveDateTime::new(day, time))
+                .expect("a BuildDate is a valid date")
+        }
+    }
+    impl TryFrom<PrimitiveDateTime> for BuildDate {
+        type Error = &'static str;
+        /// Fails for years before 0. Fractions of a second are dropped.
+        fn try_from(date: PrimitiveDateTime) -> Result<BuildDate, Self::Error> {
+            let year = u16::try_from(date.year()).map_err(|_| "year out of range")?;
+            let month = u8::from(date.month());
+            let (hour, minute, second) = (date.hour(), date.minute(), date.second());
+            BuildDate::new(year, month, date.day(), hour, minute, second).ok_or("year out of range")
+        }
+    }
+}
+
+#[test]
+fn test_parse() {
+    let minutes = BuildDate::new(2017, 9, 14, 23, 37, 0).unwrap();
+    let seconds = BuildDate::new(2017, 9, 14, 23, 37, 5).unwrap();
+    let midnight = BuildDate::new(2017, 9, 14, 0, 0, 0).unwrap();
+    assert_eq!(parse("2017-09-14_23:37"), Some(minutes));
+    assert_eq!(minutes.format(), DateFormat::Flite);
+    assert_eq!(parse("2017-09-14_23:37:05"), Some(seconds));
+    assert_eq!(seconds.format(), DateFormat::FliteSeconds);
+    assert_eq!(
+        parse("2017-09-14 23:37"),
+        Some(minutes.with_format(DateFormat::Space))
+    );
+    assert_eq!(
+        parse("2017-09-14 23:37:05"),
+        Some(seconds.with_format(DateFormat::SpaceSeconds))
+    );
+    assert_eq!(
+        parse("14-09-2017_23:37"),
+        Some(minutes.with_format(DateFormat::DayFirst))
+    );
+    assert_eq!(
+        parse("2017-09-14"),
+        Some(midnight.with_format(DateFormat::DateOnly))
+    );
+    assert_eq!(parse("unknown"), Some(BuildDate::UNKNOWN));
+    assert_eq!(BuildDate::UNKNOWN.year(), 1970);
+    assert_eq!(parse("2017-99-14_23:37"), None);
+    assert_eq!(parse("2017-02-29_23:37"), None);
+    assert_eq!(parse("2017-09-14_23:37:05:00"), None);
+    // only the padded spelling, so every date is written back as it was read
+    assert_eq!(parse("2017-9-14_23:37"), None);
+    assert_eq!(parse(" unknown"), None);
+    assert_eq!(parse("last tuesday"), None);
+    for text in [
+        "2017-09-14_23:37",
+        "2017-09-14_23:37:05",
+        "2017-09-14 23:37",
+        "2017-09-14 23:37:05",
+        "14-09-2017_23:37",
+        "2017-09-14",
+        "unknown",
+    ] {
+        assert_eq!(text.parse::<BuildDate>().unwrap().to_string(), text);
+    }
+
+    #[cfg(feature = "chrono")]
+    {
+        let date = chrono::NaiveDate::from_ymd_opt(2017, 9, 14).unwrap();
+        let chrono = date.and_hms_opt(23, 37, 5).unwrap();
+        assert_eq!(chrono::NaiveDateTime::from(seconds), chrono);
+        assert_eq!(BuildDate::try_from(chrono), Ok(seconds));
+    }
+    #[cfg(feature = "time")]
+    {
+        let date = time::Date::from_calendar_date(2017, time::Month::September, 14).unwrap();
+        let time = date.with_hms(23, 37, 5).unwrap();
+        assert_eq!(time::PrimitiveDateTime::from(seconds), time);
+        assert_eq!(BuildDate::try_from(time), Ok(seconds));
+    }
 }
@@ -1,19 +1,99 @@
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+use core::marker::PhantomData;
 use core::ops::{AddAssign, MulAssign};
 use core::str::FromStr;
 
-use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::de::value::StrDeserializer;
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+    Visitor,
+};
 use serde::Deserialize;
 
-use crate::error::{Error, Result};
-use crate::Gender;
+use crate::container::Container;
+#[cfg(feature = "alloc")]
+use crate::decode::StringDecoder;
+use crate::error::{Error, Located, Result};
+use crate::header_ref::FEATURE_KEYS;
+#[cfg(feature = "std")]
+use crate::input::IoInput;
+use crate::input::{Bytes, Input};
+use crate::integrity::Algorithm;
+use crate::version::VERSION;
 #[cfg(feature = "alloc")]
 use crate::Header;
+use crate::{FormatVersion, Gender, FLITEVOX_MAGIC};
 
-pub struct Deserializer<'de> {
-    // This string starts with the input data and characters are truncated off
-    // the beginning as data is parsed.
-    input: &'de [u8],
+/// Reads the CST binary layout from an [`Input`]: a byte slice unless built with
+/// [`Deserializer::from_reader`].
+pub struct Deserializer<'de, R = &'de [u8]> {
+    // Bytes are consumed from the front of the input as data is parsed.
+    input: R,
+    container: Container,
     byteswapped: Option<bool>,
+    version: Option<FormatVersion>,
+    // Set while `deserialize_any` walks a run of key/value pairs, so that nested values are
+    // read as plain strings instead of starting yet another map.
+    in_pairs: bool,
+    // Set while a `VALUE` document is read, so that `deserialize_any` guesses the type of every
+    // entry from its bytes.
+    dynamic: bool,
+    options: DeserializerOptions,
+    // Set inside a `PACKED` tuple struct.
+    packed: bool,
+    // Bytes consumed so far, and the struct field being read, for locating errors.
+    offset: usize,
+    field: Option<&'static str>,
+    #[cfg(feature = "alloc")]
+    decoder: Option<&'de dyn StringDecoder>,
+    lifetime: PhantomData<&'de ()>,
+}
+
+/// The byte order of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    Little,
+    Big,
+}
+
+/// The trade-offs a [`Deserializer`] makes on input which is not quite what it expects. The
+/// default accepts every voice flite does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeserializerOptions {
+    /// Fail in [`Deserializer::end`] if input is left after the value.
+    pub deny_trailing_bytes: bool,
+    /// The longest string or array a size prefix may declare, in bytes.
+    pub max_string_len: usize,
+    /// The most elements a sequence may declare.
+    pub max_seq_len: usize,
+    /// Read a string which names none of the variants of an enum as its `Other` variant, if it
+    /// has a unit variant of that name, instead of failing.
+    pub unknown_variants_as_other: bool,
+    /// Read in this byte order whatever the endianness marker says, for files whose marker is
+    /// broken.
+    pub byte_order: Option<ByteOrder>,
+    /// See [`Deserializer::tolerate_numeric_strings`].
+    pub numeric_strings: bool,
+    /// Compute the digest of every section with this algorithm, which
+    /// [`integrity::from_bytes_with`](crate::integrity::from_bytes_with) hands back with the value.
+    pub digests: Option<Algorithm>,
+}
+impl Default for DeserializerOptions {
+    fn default() -> Self {
+        DeserializerOptions {
+            deny_trailing_bytes: false,
+            max_string_len: usize::MAX,
+            max_seq_len: usize::MAX,
+            unknown_variants_as_other: false,
+            byte_order: None,
+            numeric_strings: false,
+            digests: None,
+        }
+    }
 }
 
 impl<'de> Deserializer<'de> {
@@ -25,41 +105,243 @@ impl<'de> Deserializer<'de> {
     // `serde_json::from_str(...)` while advanced use cases that require a
     // deserializer can make one with `serde_json::Deserializer::from_str(...)`.
     pub fn from_bytes(input: &'de [u8]) -> Self {
+        Deserializer::new(input)
+    }
+    pub fn from_bytes_with(input: &'de [u8], options: DeserializerOptions) -> Self {
+        Deserializer::new(input).with_options(options)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de, R: std::io::Read> Deserializer<'de, IoInput<R>> {
+    /// Read from `reader` in chunks instead of needing the whole file in memory. Strings are
+    /// copied out of the buffer, so only owned types (`String` rather than `&str`) can be
+    /// deserialized.
+    pub fn from_reader(reader: R) -> Self {
+        Deserializer::new(IoInput::new(reader))
+    }
+}
+
+impl<'de, R> Deserializer<'de, R> {
+    fn new(input: R) -> Self {
         Deserializer {
             input,
+            container: Container::Voxdata,
             byteswapped: None,
+            version: None,
+            in_pairs: false,
+            dynamic: false,
+            options: DeserializerOptions::default(),
+            packed: false,
+            offset: 0,
+            field: None,
+            #[cfg(feature = "alloc")]
+            decoder: None,
+            lifetime: PhantomData,
+        }
+    }
+    /// Also accept numeric values stored as decimal strings in place of their binary cell, as
+    /// some older voice dumps do for f0 statistics and counts. Off by default: a small binary
+    /// number followed by digit bytes can look like such a string.
+    pub fn tolerate_numeric_strings(mut self, tolerate: bool) -> Self {
+        self.options.numeric_strings = tolerate;
+        self
+    }
+    /// Reject any string, array or sequence whose length prefix declares more than `limit` bytes
+    /// or elements, before anything is read or reserved for it. A slice is never read past its
+    /// end, whatever its sizes say, but a reader is buffered up to the declared size of a string:
+    /// untrusted files read with [`Deserializer::from_reader`] should set a limit.
+    pub fn max_allocation(mut self, limit: usize) -> Self {
+        self.options.max_string_len = limit;
+        self.options.max_seq_len = limit;
+        self
+    }
+    pub fn with_options(mut self, options: DeserializerOptions) -> Self {
+        self.options = options;
+        self
+    }
+    /// Fail with [`Error::TrailingBytes`] if input is left and the options deny trailing bytes.
+    /// Call it once the value has been deserialized.
+    pub fn end(&mut self) -> Result<()>
+    where
+        R: Input<'de>,
+    {
+        if self.options.deny_trailing_bytes && !self.at_end()? {
+            return Err(Error::TrailingBytes);
         }
+        Ok(())
+    }
+    /// Read a dump which starts with the magic of `container` instead of that of a voice.
+    pub fn with_container(mut self, container: Container) -> Self {
+        self.container = container;
+        self
+    }
+    /// Decode strings with `decoder` instead of requiring UTF-8. Strings which the decoder has
+    /// to convert are handed out as owned `String`s, so borrowed `&str` fields only work for
+    /// text which is valid UTF-8 as is.
+    #[cfg(feature = "alloc")]
+    pub fn with_decoder(mut self, decoder: &'de dyn StringDecoder) -> Self {
+        self.decoder = Some(decoder);
+        self
+    }
+    /// Whether the input was written on a host of the other byte order, once the header has
+    /// been read.
+    pub fn is_byteswapped(&self) -> Option<bool> {
+        self.byteswapped
+    }
+    /// `error`, with where it happened. `context` are the bytes from `context_start` on.
+    fn locate(&self, error: Error, context: &[u8], context_start: usize) -> Error {
+        if let Error::At(_) = error {
+            return error;
+        }
+        Error::At(Box::new(Located {
+            error,
+            offset: self.offset,
+            field: self.field,
+            context: context.to_vec(),
+            context_start,
+        }))
+    }
+    /// The version of the magic, once the header has been read.
+    pub fn version(&self) -> Option<FormatVersion> {
+        self.version
+    }
+    /// The number of bytes read so far, including the magic.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+    /// The field of the struct being read, if any.
+    pub fn field(&self) -> Option<&'static str> {
+        self.field
     }
 }
 
-const CST_FLITE_HEADER: &str = "CMU_FLITE_CG_VOXDATA-v2.0";
-const CST_LITTLE_ENDIAN_BYTE_VALUE: usize = 1;
+/// The name of tuple structs which are packed like a C struct, as the nodes of trees are: their
+/// `u8` and `u16` fields take one and two bytes instead of a whole cell.
+pub(crate) const PACKED: &str = "$serde_cst::Packed";
+/// The name of the newtype a [`Value`](crate::value::Value) is read as: at the top it reads every
+/// entry up to the end of the input, and inside of it the type of each entry is guessed.
+pub(crate) const VALUE: &str = "$serde_cst::Value";
+/// The name of enums whose variant is given by an integer type tag, as flite tags its values,
+/// instead of by a string: `#[serde(rename = "$serde_cst::Tagged")]`. Every variant is renamed
+/// to its tag in decimal, like `#[serde(rename = "3")]`, and its fields follow the tag as a
+/// tuple. The tag is a `u16`, which takes two bytes in a `PACKED` struct and a cell elsewhere.
+///
+/// A variant renamed [`ANY_TAG`] takes every other tag, and reads it again as its first field.
+pub const TAGGED: &str = "$serde_cst::Tagged";
+/// The name of the variant of a [`TAGGED`] enum which takes the tags no other variant has.
+pub const ANY_TAG: &str = "_";
+
+const CST_LITTLE_ENDIAN_BYTE_VALUE: u32 = 1;
+// The variant unknown enum strings are read as, if the options allow it.
+const OTHER: &str = "Other";
+// The magic, its null terminator and the endianness marker.
+const CST_MIN_LEN: usize = FLITEVOX_MAGIC.len() + 4;
+// The key (and value) of the last pair of a feature block.
+const END_OF_FEATURES: &str = "end_of_features";
+// The most fields a feature block is read into, whose repeats can be told apart.
+const MAX_FEATURES: usize = 256;
+// The bytes of context a located error carries: the line of a hexdump it is on.
+const CONTEXT: usize = 16;
+// The longest decimal string accepted in place of a number, with its null terminator.
+const MAX_NUMERIC_STRING: usize = 32;
+// The longest entry taken for a string when the type of an entry is guessed, with its null
+// terminator.
+const MAX_DYNAMIC_STRING: usize = 4096;
+// Cells whose integer is smaller than this are taken for one by a `VALUE` document, larger ones
+// for a float if they make a reasonable one: 1.0 is 0x3f800000.
+const MAX_DYNAMIC_INT: u32 = 1 << 24;
+
+impl<'de> Deserializer<'de> {
+    #[cfg(feature = "alloc")]
+    pub(crate) fn parse_str(&mut self) -> Result<&'de str> {
+        match self.parse_str_bytes()? {
+            Bytes::Borrowed(bytes) => Ok(core::str::from_utf8(bytes)?),
+            // a slice always lends its bytes
+            Bytes::Copied(_) => unreachable!(),
+        }
+    }
+    /// Read from the middle of a file whose header has already been read.
+    pub(crate) fn resume(input: &'de [u8], byteswapped: bool) -> Self {
+        let mut de = Deserializer::new(input);
+        de.byteswapped = Some(byteswapped);
+        de
+    }
+    /// `error`, with where in `input`, which this deserializer reads, it happened.
+    pub(crate) fn locate_in(&self, error: Error, input: &[u8]) -> Error {
+        let start = self.offset / CONTEXT * CONTEXT;
+        let context = input.get(start..).unwrap_or_default();
+        self.locate(error, &context[..context.len().min(CONTEXT)], start)
+    }
+    /// The number of bytes not consumed yet.
+    #[cfg(any(test, feature = "alloc"))]
+    pub(crate) fn remaining(&self) -> usize {
+        self.input.len()
+    }
+    /// Consume the next entry as it is stored: a string by its size, and anything else as a
+    /// single cell. Returns how many bytes it took, or `None` at the end of the input.
+    pub(crate) fn next_raw(&mut self) -> Result<Option<usize>> {
+        self.validate_header()?;
+        if self.at_end()? {
+            return Ok(None);
+        }
+        let len = self.next_text_size()?.map_or(4, |size| 4 + size);
+        self.take(len)?;
+        Ok(Some(len))
+    }
+    /// A deserializer reading on from here, counting offsets from the same start.
+    pub(crate) fn fork(&mut self) -> Result<Deserializer<'de>> {
+        self.validate_header()?;
+        let mut de = Deserializer::resume(self.input, self.byteswapped == Some(true));
+        de.container = self.container;
+        de.version = self.version;
+        de.options = self.options;
+        de.offset = self.offset;
+        #[cfg(feature = "alloc")]
+        {
+            de.decoder = self.decoder;
+        }
+        Ok(de)
+    }
+}
 
 // SERDE IS NOT A PARSING LIBRARY. This impl block defines a few basic parsing
 // functions from scratch. More complicated formats may wish to use a dedicated
 // parsing library to help implement their Serde deserializer.
-impl<'de> Deserializer<'de> {
-    fn validate_header(&mut self) -> Result<()> {
+impl<'de, R: Input<'de>> Deserializer<'de, R> {
+    pub(crate) fn validate_header(&mut self) -> Result<()> {
         if self.byteswapped.is_some() {
             return Ok(());
         }
-        if !self.input.starts_with(CST_FLITE_HEADER.as_bytes()) {
+        let min_len = self.container.magic_len() + 4;
+        let start = self.input.peek(min_len)?;
+        if start.len() < min_len {
+            return Err(Error::TooShort(start.len(), min_len));
+        }
+        if !self.container.detect(start) {
             return Err(Error::InvalidHeader);
         }
-        self.input = &self.input[CST_FLITE_HEADER.as_bytes().len() + 1..];
-        self.byteswapped = Some(self.get_size_of_next()? != CST_LITTLE_ENDIAN_BYTE_VALUE);
+        self.version = FormatVersion::detect(start);
+        self.take(self.container.magic_len())?;
+        let marker = u32::from_le_bytes(self.read_bytes::<4, 4>()?);
+        self.byteswapped = Some(match self.options.byte_order {
+            Some(ByteOrder::Little) => false,
+            Some(ByteOrder::Big) => true,
+            None => marker != CST_LITTLE_ENDIAN_BYTE_VALUE,
+        });
         Ok(())
     }
     fn get_size_of_next(&mut self) -> Result<usize> {
-        let bytes = self.input.get(0..4).ok_or(Error::Eof)?;
-        #[cfg(target_pointer_width = "64")]
-        let result = usize::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3], 0, 0, 0, 0]);
-        #[cfg(target_pointer_width = "32")]
-        let result = usize::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
-        #[cfg(target_pointer_width = "16")]
-        compile_error!("This crate is not compatible with 16-bit architectures.");
-        self.input = &self.input[4..];
-        Ok(result)
+        let size = u32::from_le_bytes(self.read_bytes::<4, 4>()?);
+        usize::try_from(size).map_err(|_| Error::SizeOverflow(size))
+    }
+    /// A size prefix declaring at most `limit` bytes or elements.
+    fn get_len_of_next(&mut self, limit: usize) -> Result<usize> {
+        let size = self.get_size_of_next()?;
+        if size > limit {
+            return Err(Error::TooLarge(size, limit));
+        }
+        Ok(size)
     }
     fn parse_bool_unchecked_header(&mut self) -> Result<bool> {
         let required_size = 1;
@@ -68,49 +350,258 @@ impl<'de> Deserializer<'de> {
             return Err(Error::ExpectedSize(size, 1));
         }
         // must use +1 to get rid of null byte
-        let b = self.input.get(0..required_size + 1).ok_or(Error::Eof)?[0] != 0;
-        // account for null byte: 2 instead of 1
-        self.input = &self.input[2..];
+        let b = self.take(required_size + 1)?[0] != 0;
         Ok(b)
     }
     fn parse_bool(&mut self) -> Result<bool> {
         self.validate_header()?;
         self.parse_bool_unchecked_header()
     }
-    fn parse_str(&mut self) -> Result<&'de str> {
+    /// Read a string with the decoder, if one is set.
+    #[cfg(feature = "alloc")]
+    fn parse_text(&mut self) -> Result<Cow<'_, str>> {
+        let decoder = self.decoder;
+        let bytes = self.parse_str_bytes()?.get();
+        match decoder {
+            Some(decoder) => decoder.decode(bytes),
+            None => Ok(Cow::Borrowed(core::str::from_utf8(bytes)?)),
+        }
+    }
+    #[cfg(not(feature = "alloc"))]
+    fn parse_text(&mut self) -> Result<&str> {
+        Ok(core::str::from_utf8(self.parse_str_bytes()?.get())?)
+    }
+    /// Hand the next string to `visitor`, borrowed when the input lends it and the decoder did
+    /// not have to convert it.
+    fn visit_text<V: Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value> {
+        #[cfg(feature = "alloc")]
+        if let Some(decoder) = self.decoder {
+            return match self.parse_str_bytes()? {
+                Bytes::Borrowed(bytes) => match decoder.decode(bytes)? {
+                    Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+                    Cow::Owned(s) => visitor.visit_string(s),
+                },
+                Bytes::Copied(bytes) => match decoder.decode(bytes)? {
+                    Cow::Borrowed(s) => visitor.visit_str(s),
+                    Cow::Owned(s) => visitor.visit_string(s),
+                },
+            };
+        }
+        match self.parse_str_bytes()? {
+            Bytes::Borrowed(bytes) => visitor.visit_borrowed_str(core::str::from_utf8(bytes)?),
+            Bytes::Copied(bytes) => visitor.visit_str(core::str::from_utf8(bytes)?),
+        }
+    }
+    /// The bytes of a string, without its null terminator.
+    fn parse_str_bytes(&mut self) -> Result<Bytes<'de, '_>> {
         self.validate_header()?;
-        let size = self.get_size_of_next()?;
+        let size = self.get_len_of_next(self.options.max_string_len)?;
         #[cfg(feature = "debug")]
         println!("SIZE: {:?}", size);
         #[cfg(feature = "debug")]
-        println!("BUFs: {:x?}", &self.input[..size]);
-        let bytes = &self.input.get(0..size).ok_or(Error::Eof)?;
-        if bytes[size - 1] != 0 {
+        println!("BUFs: {:x?}", self.peek(size));
+        if size == 0 {
+            return Err(Error::WrongLength(size));
+        }
+        if self.input.peek(size)?.len() < size {
+            return Err(Error::Eof);
+        }
+        if self.input.peek(size)?[size - 1] != 0 {
             return Err(Error::WrongLength(size));
         }
-        let s = core::str::from_utf8(&bytes[..size - 1])?;
-        self.input = &self.input[size..];
-        Ok(s)
+        Ok(match self.take(size)? {
+            Bytes::Borrowed(bytes) => Bytes::Borrowed(&bytes[..size - 1]),
+            Bytes::Copied(bytes) => Bytes::Copied(&bytes[..size - 1]),
+        })
+    }
+    /// With numeric strings tolerated, read a number stored as a decimal string. Nothing is
+    /// consumed unless the next value is such a string and parses as a `T`.
+    fn numeric_string<T: FromStr>(&mut self) -> Option<T> {
+        if !self.options.numeric_strings || self.packed {
+            return None;
+        }
+        let swapped = self.byteswapped == Some(true);
+        let head = self.input.peek(4 + MAX_NUMERIC_STRING).ok()?;
+        let mut cell: [u8; 4] = head.get(..4)?.try_into().ok()?;
+        if swapped {
+            cell.reverse();
+        }
+        let size = u32::from_le_bytes(cell) as usize;
+        // at least a digit and the null terminator
+        if !(2..=MAX_NUMERIC_STRING).contains(&size) {
+            return None;
+        }
+        let (&0, text) = head.get(4..4 + size)?.split_last()? else {
+            return None;
+        };
+        let value = core::str::from_utf8(text).ok()?.trim().parse().ok()?;
+        self.take(4 + size).ok()?;
+        Some(value)
+    }
+    /// Whether all of the input has been consumed.
+    fn at_end(&mut self) -> Result<bool> {
+        Ok(self.input.peek(1)?.is_empty())
+    }
+    /// Whether a voice's feature block starts here, right after the magic.
+    fn at_feature_block(&mut self) -> Result<bool> {
+        let start = self.offset == CST_MIN_LEN && self.container == Container::Voxdata;
+        Ok(start && self.next_str_in(FEATURE_KEYS)?.is_some())
+    }
+    /// Guess the type of the next entry of a `VALUE` document from its bytes. A feature block
+    /// right after the magic is a map, a size followed by as many bytes ending in the only null
+    /// byte is a string, and any other cell is a number.
+    fn visit_entry<V: Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value> {
+        if self.at_feature_block()? {
+            return visitor.visit_map(StructValues::new(self, &[END_OF_FEATURES]));
+        }
+        let mut cell: [u8; 4] = match self.input.peek(4)?.try_into() {
+            Ok(cell) => cell,
+            Err(_) => return Err(Error::Eof),
+        };
+        if self.byteswapped == Some(true) {
+            cell.reverse();
+        }
+        // a bool is a one byte string whose byte is not its terminator; false can't be told apart
+        // from an empty string, and reads as one
+        if u32::from_le_bytes(cell) == 1 && self.input.peek(6)?.get(4..6) == Some(&[1, 0]) {
+            return visitor.visit_bool(self.parse_bool()?);
+        }
+        if self.next_text_size()?.is_some() {
+            return self.visit_text(visitor);
+        }
+        let val = i32::from_le_bytes(self.read_bytes::<4, 4>()?);
+        let float = f32::from_bits(val as u32).abs();
+        if val.unsigned_abs() >= MAX_DYNAMIC_INT && (1e-30..1e30).contains(&float) {
+            return visitor.visit_f32(f32::from_bits(val as u32));
+        }
+        visitor.visit_i32(val)
+    }
+    /// The size of the next entry if it looks like a string: a size followed by as many bytes,
+    /// ending in the only null byte.
+    fn next_text_size(&mut self) -> Result<Option<usize>> {
+        let mut cell: [u8; 4] = match self.input.peek(4)?.try_into() {
+            Ok(cell) => cell,
+            Err(_) => return Ok(None),
+        };
+        if self.byteswapped == Some(true) {
+            cell.reverse();
+        }
+        let size = u32::from_le_bytes(cell) as usize;
+        if !(1..=MAX_DYNAMIC_STRING).contains(&size) {
+            return Ok(None);
+        }
+        let text = self.input.peek(4 + size)?.get(4..);
+        let text = text.and_then(|text| text.strip_suffix(b"\0"));
+        let is_text =
+            text.is_some_and(|text| !text.contains(&0) && core::str::from_utf8(text).is_ok());
+        Ok(is_text.then_some(size))
+    }
+    /// Jump over the next entry without looking at its contents: a string (every value inside a
+    /// run of pairs is one) by its size, a feature block up to its `end_of_features` pair, and
+    /// anything else as a single cell.
+    fn skip_entry(&mut self) -> Result<()> {
+        self.validate_header()?;
+        if self.in_pairs {
+            let size = self.get_len_of_next(self.options.max_string_len)?;
+            self.take(size)?;
+            return Ok(());
+        }
+        if self.at_feature_block()? {
+            let in_pairs = core::mem::replace(&mut self.in_pairs, true);
+            let mut ended = false;
+            while !ended {
+                ended = self.next_str_in(&[END_OF_FEATURES])?.is_some();
+                self.skip_entry()?;
+                self.skip_entry()?;
+            }
+            self.in_pairs = in_pairs;
+            return Ok(());
+        }
+        match self.next_text_size()? {
+            Some(size) => self.take(4 + size)?,
+            None => self.take(4)?,
+        };
+        Ok(())
     }
+    /// The variant of a [`TAGGED`] enum named by the next tag, which is consumed unless the
+    /// variant is [`ANY_TAG`].
+    fn next_tagged_variant(&mut self, variants: &'static [&'static str]) -> Result<&'static str> {
+        self.validate_header()?;
+        let width = if self.packed { 2 } else { 4 };
+        let bytes = self.input.peek(width)?;
+        if bytes.len() < width {
+            return Err(Error::Eof);
+        }
+        let mut cell = [0; 4];
+        cell[..width].copy_from_slice(bytes);
+        if self.byteswapped == Some(true) {
+            cell[..width].reverse();
+        }
+        let tag = u16::from_le_bytes([cell[0], cell[1]]);
+        let named = |variant: &&str| variant.parse::<u16>().ok() == Some(tag);
+        if let Some(variant) = variants.iter().copied().find(named) {
+            self.take(width)?;
+            return Ok(variant);
+        }
+        if variants.contains(&ANY_TAG) {
+            return Ok(ANY_TAG);
+        }
+        let tag = de::Unexpected::Unsigned(tag.into());
+        Err(de::Error::invalid_value(tag, &"a tag naming a variant"))
+    }
+    /// Which of `names` the next string is, without consuming it.
+    fn next_str_in(&mut self, names: &'static [&'static str]) -> Result<Option<&'static str>> {
+        self.validate_header()?;
+        let swapped = self.byteswapped == Some(true);
+        let Ok(mut cell) = <[u8; 4]>::try_from(self.input.peek(4)?) else {
+            return Ok(None);
+        };
+        if swapped {
+            cell.reverse();
+        }
+        let size = u32::from_le_bytes(cell) as usize;
+        if !names.iter().any(|name| name.len() + 1 == size) {
+            return Ok(None);
+        }
+        let text = self.input.peek(4 + size)?[4..].strip_suffix(b"\0");
+        let name = names.iter().find(|name| Some(name.as_bytes()) == text);
+        Ok(name.copied())
+    }
+    /// Consume the next `n` bytes.
+    fn take(&mut self, n: usize) -> Result<Bytes<'de, '_>> {
+        let bytes = self.input.take(n)?;
+        self.offset += n;
+        Ok(bytes)
+    }
+    /// Read an `N` byte cell and return its `M` least significant bytes, in little-endian order
+    /// whatever the byte order of the file.
     fn read_bytes<const N: usize, const M: usize>(&mut self) -> Result<[u8; M]> {
         assert!(N >= M, "N must be greater than or equal to M");
         #[cfg(feature = "debug")]
-        println!("BUF: {:x?}", &self.input[..N]);
-        let n: &[u8; N] = self.input.get(..N).ok_or(Error::Eof)?.try_into().unwrap();
+        println!("BUF: {:x?}", self.peek(N));
+        let mut n = [0; N];
+        n.copy_from_slice(&self.take(N)?);
+        if self.byteswapped == Some(true) {
+            n.reverse();
+        }
         let m: [u8; M] = n[..M].try_into().unwrap();
-        self.input = &self.input[N..];
         Ok(m)
     }
-    fn parse_digits(&mut self) -> Result<Vec<u8>> {
-        let digit_chars: [u8; 10] = [b'0', b'1', b'2', b'3', b'4', b'5', b'6', b'7', b'8', b'9'];
-        let digits: Vec<u8> = self
+    #[cfg(feature = "debug")]
+    fn peek(&mut self, n: usize) -> Vec<u8> {
+        self.input.peek(n).map(<[u8]>::to_vec).unwrap_or_default()
+    }
+    fn parse_digits(&mut self) -> Result<Bytes<'de, '_>> {
+        let mut len = 0;
+        while self
             .input
-            .iter()
-            .take_while(|c| digit_chars.contains(c))
-            .copied()
-            .collect();
-        self.input = &self.input[digits.len()..];
-        Ok(digits)
+            .peek(len + 1)?
+            .get(len)
+            .is_some_and(u8::is_ascii_digit)
+        {
+            len += 1;
+        }
+        self.take(len)
     }
 }
 
@@ -123,43 +614,97 @@ pub fn from_bytes<'a, T>(s: &'a [u8]) -> Result<T>
 where
     T: Deserialize<'a>,
 {
-    let mut deserializer = Deserializer::from_bytes(s);
-    let t = T::deserialize(&mut deserializer)?;
-    /*
-    if !deserializer.input.is_empty() {
-       return Err(Error::TrailingBytes);
-    }
-    */
+    from_bytes_with(s, DeserializerOptions::default())
+}
+
+/// [`from_bytes`], making the trade-offs of `options`.
+pub fn from_bytes_with<'a, T>(s: &'a [u8], options: DeserializerOptions) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let input = crate::trailer::verify(s)?;
+    let mut deserializer = Deserializer::from_bytes_with(input, options);
+    let t = T::deserialize(&mut deserializer).map_err(|e| deserializer.locate_in(e, input))?;
+    deserializer
+        .end()
+        .map_err(|e| deserializer.locate_in(e, input))?;
     Ok(t)
 }
 
-struct StructValues<'a, 'de: 'a> {
-    de: &'a mut Deserializer<'de>,
+/// Deserialize a `T` from `reader`, reading it in chunks. The integrity trailer is not
+/// verified, as that needs the whole input up front.
+#[cfg(feature = "std")]
+pub fn from_reader<R, T>(reader: R) -> Result<T>
+where
+    R: std::io::Read,
+    T: serde::de::DeserializeOwned,
+{
+    let mut deserializer = Deserializer::from_reader(reader);
+    T::deserialize(&mut deserializer).map_err(|e| {
+        let offset = deserializer.offset;
+        let context = deserializer.input.peek(CONTEXT).map(<[u8]>::to_vec);
+        deserializer.locate(e, &context.unwrap_or_default(), offset)
+    })
+}
+
+struct StructValues<'a, 'de: 'a, R> {
+    de: &'a mut Deserializer<'de, R>,
     fields: &'static [&'static str],
     idx: usize,
+    // A feature block may leave out fields, so it runs until its `end_of_features` pair instead
+    // of for as many pairs as there are fields.
+    feature_block: bool,
+    ended: bool,
+    // The features of a feature block seen so far, a bit for every field. Its keys are matched
+    // by name, in any order.
+    seen: [u64; MAX_FEATURES / 64],
 }
-impl<'a, 'de> StructValues<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>, fields: &'static [&'static str]) -> Self {
-        StructValues { de, fields, idx: 0 }
+impl<'a, 'de, R> StructValues<'a, 'de, R> {
+    fn new(de: &'a mut Deserializer<'de, R>, fields: &'static [&'static str]) -> Self {
+        StructValues {
+            de,
+            fields,
+            idx: 0,
+            feature_block: fields.contains(&END_OF_FEATURES),
+            ended: false,
+            seen: [0; MAX_FEATURES / 64],
+        }
+    }
+    /// Fail if the feature `field`, whose key starts at `offset`, was already read.
+    fn see(&mut self, field: &'static str, offset: usize) -> Result<()> {
+        let Some(idx) = self.fields.iter().position(|known| *known == field) else {
+            return Ok(());
+        };
+        let Some(word) = self.seen.get_mut(idx / 64) else {
+            return Err(Error::Unsupported(
+                "a feature block of more than 256 fields",
+            ));
+        };
+        let bit = 1 << (idx % 64);
+        if *word & bit != 0 {
+            return Err(Error::DuplicateFeature(field, offset));
+        }
+        *word |= bit;
+        Ok(())
     }
 }
 
 
 // NOTE: array values do not work like this, they are loaded in one chunk
-struct SeqValues<'a, 'de: 'a> {
-    de: &'a mut Deserializer<'de>,
+struct SeqValues<'a, 'de: 'a, R> {
+    de: &'a mut Deserializer<'de, R>,
     len: Option<usize>,
     idx: usize,
 }
-impl<'a, 'de> SeqValues<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
+impl<'a, 'de, R> SeqValues<'a, 'de, R> {
+    fn new(de: &'a mut Deserializer<'de, R>) -> Self {
         SeqValues {
             de,
             len: None,
             idx: 0,
         }
     }
-    fn new_with_length(de: &'a mut Deserializer<'de>, len: usize) -> Self {
+    fn new_with_length(de: &'a mut Deserializer<'de, R>, len: usize) -> Self {
         SeqValues {
             de,
             len: Some(len),
@@ -169,7 +714,7 @@ impl<'a, 'de> SeqValues<'a, 'de> {
 }
 // `SeqAccess` is provided to the `Visitor` to give it the ability to iterate
 // through elements of the sequence.
-impl<'de, 'a> SeqAccess<'de> for SeqValues<'a, 'de> {
+impl<'de, 'a, R: Input<'de>> SeqAccess<'de> for SeqValues<'a, 'de, R> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
@@ -177,11 +722,11 @@ impl<'de, 'a> SeqAccess<'de> for SeqValues<'a, 'de> {
         T: DeserializeSeed<'de>,
     {
         #[cfg(feature = "debug")]
-        println!("BUFnes: {:?}", &self.de.input[..8]);
+        println!("BUFnes: {:?}", self.de.peek(8));
         #[cfg(feature = "debug")]
         println!("size-pre: {:?}", self.len);
         if self.len == None {
-            let size = (&mut *self.de).get_size_of_next()?;
+            let size = self.de.get_len_of_next(self.de.options.max_seq_len)?;
             self.len = Some(size);
         }
         #[cfg(feature = "debug")]
@@ -197,18 +742,90 @@ impl<'de, 'a> SeqAccess<'de> for SeqValues<'a, 'de> {
     }
 }
 
+// Every entry up to the end of the input, as a `VALUE` document is read.
+struct Entries<'a, 'de: 'a, R> {
+    de: &'a mut Deserializer<'de, R>,
+}
+impl<'de, 'a, R: Input<'de>> SeqAccess<'de> for Entries<'a, 'de, R> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.de.at_end()? {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+// The variant of a `TAGGED` enum, whose tag has been read.
+struct TaggedVariant<'a, 'de: 'a, R> {
+    de: &'a mut Deserializer<'de, R>,
+    variant: &'static str,
+}
+impl<'de, 'a, R: Input<'de>> EnumAccess<'de> for TaggedVariant<'a, 'de, R> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(StrDeserializer::<Error>::new(self.variant))?;
+        Ok((variant, self))
+    }
+}
+// The fields of a variant follow its tag like those of a tuple.
+impl<'de, 'a, R: Input<'de>> VariantAccess<'de> for TaggedVariant<'a, 'de, R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.de)
+    }
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_tuple(&mut *self.de, len, visitor)
+    }
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_tuple(&mut *self.de, fields.len(), visitor)
+    }
+}
+
 // `SeqAccess` is provided to the `Visitor` to give it the ability to iterate
 // through elements of the sequence.
-impl<'de, 'a> MapAccess<'de> for StructValues<'a, 'de> {
+impl<'de, 'a, R: Input<'de>> MapAccess<'de> for StructValues<'a, 'de, R> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
     where
         K: DeserializeSeed<'de>,
     {
-        if self.fields.len() == self.idx {
+        if self.ended || (!self.feature_block && self.fields.len() == self.idx) {
             return Ok(None);
         }
+        self.de.field = if self.feature_block {
+            let field = self.de.next_str_in(self.fields)?;
+            if let Some(field) = field {
+                self.see(field, self.de.offset)?;
+            }
+            self.ended = field == Some(END_OF_FEATURES);
+            field
+        } else {
+            self.fields.get(self.idx).copied()
+        };
         let field = seed.deserialize(&mut *self.de)?;
         self.idx += 1;
         Ok(Some(field))
@@ -217,13 +834,20 @@ impl<'de, 'a> MapAccess<'de> for StructValues<'a, 'de> {
     where
         V: DeserializeSeed<'de>,
     {
-        seed.deserialize(&mut *self.de)
+        if !self.feature_block {
+            return seed.deserialize(&mut *self.de);
+        }
+        // every feature is a string, including the unknown ones serde skips with `any`
+        let in_pairs = core::mem::replace(&mut self.de.in_pairs, true);
+        let value = seed.deserialize(&mut *self.de);
+        self.de.in_pairs = in_pairs;
+        value
     }
 }
 
 // `MapAccess` is provided to the `Visitor` to give it the ability to iterate
 // through entries of the map.
-impl<'de, 'a> MapAccess<'de> for SeqValues<'a, 'de> {
+impl<'de, 'a, R: Input<'de>> MapAccess<'de> for SeqValues<'a, 'de, R> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
@@ -231,10 +855,10 @@ impl<'de, 'a> MapAccess<'de> for SeqValues<'a, 'de> {
         K: DeserializeSeed<'de>,
     {
         #[cfg(feature = "debug")]
-        println!("BUFks: {:x?}", &self.de.input[..8]);
+        println!("BUFks: {:x?}", self.de.peek(8));
         #[cfg(feature = "debug")]
         println!("TYPE: {}", std::any::type_name::<K>());
-        if self.de.input.is_empty() {
+        if self.de.at_end()? {
             return Ok(None);
         }
         // Deserialize a map key.
@@ -247,14 +871,14 @@ impl<'de, 'a> MapAccess<'de> for SeqValues<'a, 'de> {
     {
         // Deserialize a map value.
         #[cfg(feature = "debug")]
-        println!("BUFvs: {:x?}", &self.de.input[..8]);
+        println!("BUFvs: {:x?}", self.de.peek(8));
         #[cfg(feature = "debug")]
         println!("TYPE: {}", std::any::type_name::<V>());
         seed.deserialize(&mut *self.de)
     }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de, 'a, R: Input<'de>> de::Deserializer<'de> for &'a mut Deserializer<'de, R> {
     type Error = Error;
 
     // Look at the input data to decide what Serde data model type to
@@ -265,8 +889,22 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         #[cfg(feature = "debug")]
-        println!("BUFa: {:x?}", &self.input[..8]);
-        todo!("any")
+        println!("BUFa: {:x?}", self.peek(8));
+        // The format is not self-describing, but the feature block (and anything shaped like
+        // it) is a flat run of string pairs. Serde asks for `any` when it buffers content, e.g.
+        // for internally tagged enums, so at the top level hand out the rest of the input as
+        // such a map, and inside of it every value is a string.
+        if self.in_pairs {
+            return self.visit_text(visitor);
+        }
+        if self.dynamic {
+            return self.visit_entry(visitor);
+        }
+        self.validate_header()?;
+        self.in_pairs = true;
+        let value = visitor.visit_map(SeqValues::new(self));
+        self.in_pairs = false;
+        value
     }
 
     // Uses the `parse_bool` parsing function defined above to read the JSON
@@ -290,45 +928,72 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         visitor.visit_bool(self.parse_bool()?)
     }
 
-    // The `parse_signed` function is generic over the integer type `T` so here
-    // it is invoked with `T=i8`. The next 8 methods are similar.
+    // Narrow numbers take the low bytes of a whole cell, unless they are packed.
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        todo!("i8")
-        //visitor.visit_i8(self.parse_signed()?)
+        if let Some(val) = self.numeric_string() {
+            return visitor.visit_i8(val);
+        }
+        let val = if self.packed {
+            i8::from_le_bytes(self.read_bytes::<1, 1>()?)
+        } else {
+            i8::from_le_bytes(self.read_bytes::<4, 1>()?)
+        };
+        visitor.visit_i8(val)
     }
 
     fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        todo!("i16")
-        //visitor.visit_i16(self.parse_signed()?)
+        if let Some(val) = self.numeric_string() {
+            return visitor.visit_i16(val);
+        }
+        let val = if self.packed {
+            i16::from_le_bytes(self.read_bytes::<2, 2>()?)
+        } else {
+            i16::from_le_bytes(self.read_bytes::<4, 2>()?)
+        };
+        visitor.visit_i16(val)
     }
 
     fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        if let Some(val) = self.numeric_string() {
+            return visitor.visit_i32(val);
+        }
         let val = i32::from_le_bytes(self.read_bytes::<4, 4>()?);
         visitor.visit_i32(val)
     }
 
+    // Wide numbers take two cells, swapped as a whole in a big-endian file.
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        todo!("i64")
-        //visitor.visit_i64(self.parse_signed()?)
+        if let Some(val) = self.numeric_string() {
+            return visitor.visit_i64(val);
+        }
+        let val = i64::from_le_bytes(self.read_bytes::<8, 8>()?);
+        visitor.visit_i64(val)
     }
 
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let val = u8::from_le_bytes(self.read_bytes::<4, 1>()?);
+        if let Some(val) = self.numeric_string() {
+            return visitor.visit_u8(val);
+        }
+        let val = if self.packed {
+            u8::from_le_bytes(self.read_bytes::<1, 1>()?)
+        } else {
+            u8::from_le_bytes(self.read_bytes::<4, 1>()?)
+        };
         visitor.visit_u8(val)
     }
 
@@ -336,7 +1001,14 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        let val = u16::from_le_bytes(self.read_bytes::<4, 2>()?);
+        if let Some(val) = self.numeric_string() {
+            return visitor.visit_u16(val);
+        }
+        let val = if self.packed {
+            u16::from_le_bytes(self.read_bytes::<2, 2>()?)
+        } else {
+            u16::from_le_bytes(self.read_bytes::<4, 2>()?)
+        };
         visitor.visit_u16(val)
     }
 
@@ -344,6 +1016,9 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
+        if let Some(val) = self.numeric_string() {
+            return visitor.visit_u32(val);
+        }
         let val = u32::from_le_bytes(self.read_bytes::<4, 4>()?);
         visitor.visit_u32(val)
     }
@@ -352,14 +1027,18 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        todo!("u64")
+        if let Some(val) = self.numeric_string() {
+            return visitor.visit_u64(val);
+        }
+        let val = u64::from_le_bytes(self.read_bytes::<8, 8>()?);
+        visitor.visit_u64(val)
     }
 
-    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
+    fn deserialize_u128<V>(self, _visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        todo!("u128")
+        Err(Error::Unsupported("u128"))
     }
 
     // Float parsing is stupidly hard.
@@ -367,16 +1046,22 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
+        if let Some(val) = self.numeric_string() {
+            return visitor.visit_f32(val);
+        }
         let val = f32::from_le_bytes(self.read_bytes::<4, 4>()?);
         visitor.visit_f32(val)
     }
 
-    // Float parsing is stupidly hard.
-    fn deserialize_f64<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        todo!("f64")
+        if let Some(val) = self.numeric_string() {
+            return visitor.visit_f64(val);
+        }
+        let val = f64::from_le_bytes(self.read_bytes::<8, 8>()?);
+        visitor.visit_f64(val)
     }
 
     // The `Serializer` implementation on the previous page serialized chars as
@@ -386,7 +1071,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         // Parse a string, check that it is one character, call `visit_char`.
-        todo!("char")
+        Err(Error::Unsupported("char"))
     }
 
     // Refer to the "Understanding deserializer lifechronos" page for information
@@ -395,7 +1080,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_borrowed_str(self.parse_str()?)
+        self.visit_text(visitor)
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
@@ -405,35 +1090,34 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         self.deserialize_str(visitor)
     }
 
-    // The `Serializer` implementation on the previous page serialized byte
-    // arrays as JSON arrays of bytes. Handle that representation here.
-    fn deserialize_bytes<V>(self, _visitor: V) -> Result<V::Value>
+    // Byte arrays are what flite calls arrays: a size, then that many bytes, without a null
+    // terminator.
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        todo!("bytes")
+        self.validate_header()?;
+        let size = self.get_len_of_next(self.options.max_string_len)?;
+        match self.take(size)? {
+            Bytes::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Bytes::Copied(bytes) => visitor.visit_bytes(bytes),
+        }
     }
 
-    fn deserialize_byte_buf<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        todo!("bytebuf")
+        self.deserialize_bytes(visitor)
     }
 
-    // An absent optional is represented as the JSON `null` and a present
-    // optional is represented as just the contained value.
-    //
-    // As commented in `Serializer` implementation, this is a lossy
-    // representation. For example the values `Some(())` and `None` both
-    // serialize as just `null`. Unfortunately this is typically what people
-    // expect when working with JSON. Other formats are encouraged to behave
-    // more intelligently if possible.
+    // A value which is there at all is `Some`. Fields missing from a feature block are left to
+    // serde, which makes them `None`.
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        todo!("option")
+        visitor.visit_some(self)
     }
 
     // In Serde, unit means an anonymous value containing no data.
@@ -455,10 +1139,27 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     // As is done here, serializers are encouraged to treat newtype structs as
     // insignificant wrappers around the data they contain. That means not
     // parsing anything other than the contained value.
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        if name == VALUE && self.dynamic {
+            return self.deserialize_any(visitor);
+        }
+        if name == VALUE {
+            self.validate_header()?;
+            self.dynamic = true;
+            let value = visitor.visit_seq(Entries { de: &mut *self });
+            self.dynamic = false;
+            return value;
+        }
+        // the version is not stored anywhere but in the magic
+        if name == VERSION {
+            self.validate_header()?;
+            let text = self.version.unwrap_or_default().text();
+            // only ASCII goes into the text
+            return visitor.visit_str(core::str::from_utf8(&text).unwrap());
+        }
         visitor.visit_newtype_struct(self)
     }
 
@@ -471,7 +1172,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     {
         self.validate_header()?;
         #[cfg(feature = "debug")]
-        println!("SeqBUF: {:?}", &self.input[..8]);
+        println!("SeqBUF: {:?}", self.peek(8));
         visitor.visit_seq(SeqValues::new(self))
     }
 
@@ -493,7 +1194,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     // Tuple structs look just like sequences in JSON.
     fn deserialize_tuple_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         len: usize,
         visitor: V,
     ) -> Result<V::Value>
@@ -502,6 +1203,12 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     {
         #[cfg(feature = "debug")]
         println!("TUPLE STRUCT SIZE: {}", len);
+        if name == PACKED {
+            let packed = core::mem::replace(&mut self.packed, true);
+            let value = (&mut *self).deserialize_tuple(len, visitor);
+            self.packed = packed;
+            return value;
+        }
         self.deserialize_tuple(len, visitor)
     }
 
@@ -512,7 +1219,13 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_map(SeqValues::new(self))
+        // a map may have no pairs, so its end can't be told from the header
+        self.validate_header()?;
+        // like for `deserialize_any`, the values of a map are strings
+        let in_pairs = core::mem::replace(&mut self.in_pairs, true);
+        let value = visitor.visit_map(SeqValues::new(self));
+        self.in_pairs = in_pairs;
+        value
     }
 
     // Structs look just like maps in JSON.
@@ -532,7 +1245,10 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     {
         #[cfg(feature = "debug")]
         println!("FLs: {:?} ({})", fields, name);
-        visitor.visit_map(StructValues::new(self, fields))
+        let field = self.field;
+        let value = visitor.visit_map(StructValues::new(self, fields))?;
+        self.field = field;
+        Ok(value)
     }
 
     fn deserialize_enum<V>(
@@ -546,7 +1262,16 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     {
         #[cfg(feature = "debug")]
         println!("FVs: {:?}", variants);
-        visitor.visit_enum(self.parse_str()?.into_deserializer())
+        if name == TAGGED {
+            let variant = self.next_tagged_variant(variants)?;
+            return visitor.visit_enum(TaggedVariant { de: self, variant });
+        }
+        let other_allowed = self.options.unknown_variants_as_other && variants.contains(&OTHER);
+        let text = self.parse_text()?;
+        if other_allowed && !variants.contains(&&*text) {
+            return visitor.visit_enum(OTHER.into_deserializer());
+        }
+        visitor.visit_enum(text.into_deserializer())
     }
 
     // An identifier in Serde is the type that identifies a field of a struct or
@@ -576,8 +1301,9 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         #[cfg(feature = "debug")]
-        println!("BUFia: {:x?}", &self.input[..8]);
-        self.deserialize_any(visitor)
+        println!("BUFia: {:x?}", self.peek(8));
+        self.skip_entry()?;
+        visitor.visit_unit()
     }
 }
 
@@ -660,19 +1386,16 @@ fn test_str() {
 #[test]
 fn test_file() {
     use crate::{EndOfFeatures, Features, Language};
-    use chrono::NaiveDateTime;
     let data = include_bytes!("../data/cmu_us_slt.flitevox");
     let expected = Header {
+        version: FormatVersion::CURRENT,
         features: Features {
             language: "eng".to_string(),
             country: "USA".to_string(),
             variant: "none".to_string(),
             age: 30,
             gender: Gender::Unknown,
-            build_date: chrono::NaiveDateTime::new(
-                chrono::NaiveDate::from_ymd_opt(2017, 9, 14).unwrap(),
-                chrono::NaiveTime::from_hms_opt(23, 37, 0).unwrap(),
-            ),
+            build_date: crate::BuildDate::new(2017, 9, 14, 23, 37, 0).unwrap(),
             description: "unknown".to_string(),
             eng_shared: 0,
             copyright: "unknown".to_string(),
@@ -686,3 +1409,588 @@ fn test_file() {
     };
     assert_eq!(expected, from_bytes::<Header>(data).unwrap());
 }
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_option() {
+    use crate::EndOfFeatures;
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Features {
+        language: String,
+        variant: Option<String>,
+        copyright: Option<String>,
+        end_of_features: EndOfFeatures,
+    }
+    let data = "CMU_FLITE_CG_VOXDATA-v2.0\0\x01\0\0\0\x09\0\0\0language\0\x04\0\0\0eng\0\x0a\0\0\0copyright\0\x04\0\0\0BSD\0\x10\0\0\0end_of_features\0\x10\0\0\0end_of_features\0";
+    let expected = Features {
+        language: "eng".to_string(),
+        variant: None,
+        copyright: Some("BSD".to_string()),
+        end_of_features: EndOfFeatures::EndOfFeatures,
+    };
+    assert_eq!(expected, from_bytes::<Features>(data.as_bytes()).unwrap());
+
+    // a voice without the optional features
+    let mut data = include_bytes!("../data/cmu_us_slt.flitevox")[..394].to_vec();
+    for pair in [
+        &b"\x08\0\0\0variant\0\x05\0\0\0none\0"[..],
+        b"\x0a\0\0\0copyright\0\x08\0\0\0unknown\0",
+    ] {
+        let at = data.windows(pair.len()).position(|w| w == pair).unwrap();
+        data.drain(at..at + pair.len());
+    }
+    let header = from_bytes::<Header>(&data).unwrap();
+    assert_eq!(header.features.variant, "unknown");
+    assert_eq!(header.features.num_f0_models, 3);
+    assert_eq!(header.name, "cmu_us_slt");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_feature_order() {
+    use crate::HeaderRef;
+
+    let data = &include_bytes!("../data/cmu_us_slt.flitevox")[..394];
+    let header = from_bytes::<Header>(data).unwrap();
+    let end = b"\x10\0\0\0end_of_features\0";
+    let end_at = data.windows(end.len()).position(|w| w == end).unwrap();
+
+    // the language last instead of first
+    let language = b"\x09\0\0\0language\0\x04\0\0\0eng\0";
+    let mut moved = data.to_vec();
+    moved.drain(CST_MIN_LEN..CST_MIN_LEN + language.len());
+    let end_at_moved = end_at - language.len();
+    moved.splice(end_at_moved..end_at_moved, language.iter().copied());
+    assert_eq!(from_bytes::<Header>(&moved).unwrap(), header);
+    assert_eq!(
+        from_bytes::<HeaderRef>(&moved).unwrap().features.language,
+        "eng"
+    );
+
+    // a second age
+    let age = b"\x04\0\0\0age\0\x03\0\0\x0031\0";
+    let mut repeated = data.to_vec();
+    repeated.splice(end_at..end_at, age.iter().copied());
+    let err = from_bytes::<Header>(&repeated).unwrap_err();
+    assert!(matches!(err.kind(), Error::DuplicateFeature("age", at) if *at == end_at));
+    let err = from_bytes::<HeaderRef>(&repeated).unwrap_err();
+    assert!(matches!(err.kind(), Error::DuplicateFeature("age", _)));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_duplicate_past_64_fields() {
+    extern crate alloc;
+    use alloc::{boxed::Box, format, vec::Vec};
+
+    let names: Vec<&'static str> = (0..MAX_FEATURES + 1)
+        .map(|i| &*Box::leak(format!("f{i}").into_boxed_str()))
+        .chain([END_OF_FEATURES])
+        .collect();
+    let fields: &'static [&'static str] = Box::leak(names.into_boxed_slice());
+    let mut de = Deserializer::from_bytes(&[]);
+    let mut values = StructValues::new(&mut de, fields);
+    assert!(values.feature_block);
+    values.see(fields[100], 40).unwrap();
+    values.see(fields[1], 50).unwrap();
+    assert!(matches!(
+        values.see(fields[100], 60),
+        Err(Error::DuplicateFeature("f100", 60))
+    ));
+    assert!(matches!(
+        values.see(fields[MAX_FEATURES], 70),
+        Err(Error::Unsupported(_))
+    ));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_internally_tagged_enum() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    enum Entry {
+        Feature { name: String, value: String },
+        Comment { text: String },
+    }
+    let data = "CMU_FLITE_CG_VOXDATA-v2.0\0\x01\0\0\0\x05\0\0\0type\0\x08\0\0\0feature\0\x05\0\0\0name\0\x09\0\0\0language\0\x06\0\0\0value\0\x04\0\0\0eng\0";
+    let expected = Entry::Feature {
+        name: "language".to_string(),
+        value: "eng".to_string(),
+    };
+    assert_eq!(expected, from_bytes::<Entry>(data.as_bytes()).unwrap());
+    let data = "CMU_FLITE_CG_VOXDATA-v2.0\0\x01\0\0\0\x05\0\0\0text\0\x03\0\0\0hi\0\x05\0\0\0type\0\x08\0\0\0comment\0";
+    let expected = Entry::Comment {
+        text: "hi".to_string(),
+    };
+    assert_eq!(expected, from_bytes::<Entry>(data.as_bytes()).unwrap());
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_adjacently_tagged_enum() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(tag = "t", content = "c", rename_all = "snake_case")]
+    enum Entry {
+        Name(String),
+        Feature { key: String, value: String },
+    }
+    let data = "CMU_FLITE_CG_VOXDATA-v2.0\0\x01\0\0\0\x02\0\0\0t\0\x08\0\0\0feature\0\x02\0\0\0c\0\x04\0\0\0key\0\x04\0\0\0age\0\x06\0\0\0value\0\x03\0\0\x0030\0";
+    let expected = Entry::Feature {
+        key: "age".to_string(),
+        value: "30".to_string(),
+    };
+    assert_eq!(expected, from_bytes::<Entry>(data.as_bytes()).unwrap());
+    let data = "CMU_FLITE_CG_VOXDATA-v2.0\0\x01\0\0\0\x02\0\0\0t\0\x05\0\0\0name\0\x02\0\0\0c\0\x0b\0\0\0cmu_us_slt\0";
+    let expected = Entry::Name("cmu_us_slt".to_string());
+    assert_eq!(expected, from_bytes::<Entry>(data.as_bytes()).unwrap());
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_tagged_enum() {
+    use crate::ser::to_bytes;
+    use serde::Serialize;
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    #[serde(rename = "$serde_cst::Tagged")]
+    enum Shape {
+        #[serde(rename = "1")]
+        Point,
+        #[serde(rename = "2")]
+        Circle(f32),
+        #[serde(rename = "4")]
+        Rect { width: i32, height: i32 },
+        #[serde(rename = "_")]
+        Other(u16, i32),
+    }
+    let shapes = vec![
+        Shape::Point,
+        Shape::Circle(1.5),
+        Shape::Rect {
+            width: 2,
+            height: 3,
+        },
+        Shape::Other(9, -1),
+    ];
+    let data = to_bytes(&shapes).unwrap();
+    // outside of a packed struct, a tag takes a whole cell
+    assert_eq!(data[34..42], [1, 0, 0, 0, 2, 0, 0, 0]);
+    assert_eq!(data[data.len() - 8..data.len() - 4], [9, 0, 0, 0]);
+    assert_eq!(from_bytes::<Vec<Shape>>(&data).unwrap(), shapes);
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename = "$serde_cst::Tagged")]
+    enum Point {
+        #[serde(rename = "1")]
+        Point,
+    }
+    let data = b"CMU_FLITE_CG_VOXDATA-v2.0\0\x01\0\0\0\x03\0\0\0";
+    let err = from_bytes::<Point>(data).unwrap_err();
+    assert!(matches!(err.kind(), Error::Message(msg) if msg.contains("a tag naming a variant")));
+}
+
+#[test]
+fn test_malformed_input_does_not_panic() {
+    // magic without its terminator
+    assert!(from_bytes::<&str>(b"CMU_FLITE_CG_VOXDATA-v2.0").is_err());
+    // zero-sized string entry
+    let data = "CMU_FLITE_CG_VOXDATA-v2.0\0\x01\0\0\0\0\0\0\0";
+    assert!(from_bytes::<&str>(data.as_bytes()).is_err());
+    // string entry longer than the input
+    let data = b"CMU_FLITE_CG_VOXDATA-v2.0\0\x01\0\0\0\xff\0\0\0abc\0";
+    assert!(from_bytes::<&str>(data).is_err());
+    // a 64 bit number takes two cells, and one is not enough
+    let data = "CMU_FLITE_CG_VOXDATA-v2.0\0\x01\0\0\0\x01\0\0\0";
+    let mut deserializer = Deserializer::from_bytes(data.as_bytes());
+    deserializer.validate_header().unwrap();
+    let err = i64::deserialize(&mut deserializer).unwrap_err();
+    assert!(matches!(err, Error::Eof));
+}
+
+// The inputs are encoded by hand here, independently of the serializer, which then has to write
+// them back byte for byte; the round trips from values are tested in `ser`, with the same
+// strategies.
+#[cfg(all(test, feature = "alloc"))]
+pub(crate) mod proptests {
+    use super::*;
+    use crate::ser::to_bytes;
+    use crate::voice::{CstVal, Tree, TreeFeatures, TreeNode};
+    use crate::{BuildDate, EndOfFeatures, Features};
+    use alloc::collections::BTreeMap;
+    use proptest::prelude::*;
+    extern crate alloc;
+
+    const TEXT: &str = "[a-zA-Z0-9_ :.-]{0,32}";
+
+    pub(crate) fn build_date() -> impl Strategy<Value = BuildDate> {
+        // flite writes no seconds
+        (1970..=9999u16, 1..=12u8, 1..=28u8, 0..24u8, 0..60u8)
+            .prop_map(|(y, mo, d, h, mi)| BuildDate::new(y, mo, d, h, mi, 0).unwrap())
+    }
+    pub(crate) fn gender() -> impl Strategy<Value = Gender> {
+        prop_oneof![
+            Just(Gender::Male),
+            Just(Gender::Female),
+            Just(Gender::Unknown),
+            Just(Gender::None),
+        ]
+    }
+    pub(crate) fn features() -> impl Strategy<Value = Features> {
+        let text = (TEXT, TEXT, TEXT, TEXT, TEXT);
+        let numbers = (any::<u32>(), any::<u32>(), any::<u32>(), any::<u32>());
+        let rest = (any::<u32>(), any::<u32>(), gender(), build_date());
+        (text, numbers, rest).prop_map(
+            |(
+                (language, country, variant, description, copyright),
+                (age, eng_shared, num_dur_models, num_param_models),
+                (model_shape, num_f0_models, gender, build_date),
+            )| Features {
+                language,
+                country,
+                variant,
+                age,
+                gender,
+                build_date,
+                description,
+                eng_shared,
+                copyright,
+                num_dur_models,
+                num_param_models,
+                model_shape,
+                num_f0_models,
+                end_of_features: EndOfFeatures::EndOfFeatures,
+            },
+        )
+    }
+    pub(crate) fn header() -> impl Strategy<Value = Header> {
+        let version =
+            (1..=2u8, 0..10u8).prop_map(|(major, minor)| FormatVersion::new(major, minor).unwrap());
+        (version, features(), TEXT).prop_map(|(version, features, name)| Header {
+            version,
+            features,
+            name,
+        })
+    }
+    pub(crate) fn cst_val() -> impl Strategy<Value = CstVal> {
+        let other = any::<u16>().prop_filter("a known tag", |tag| ![1, 3, 5].contains(tag));
+        prop_oneof![
+            any::<i32>().prop_map(CstVal::Int),
+            any::<f32>()
+                .prop_filter("NaN", |f| !f.is_nan())
+                .prop_map(CstVal::Float),
+            TEXT.prop_map(CstVal::Str),
+            (other, any::<i32>()).prop_map(|(tag, v)| CstVal::Other(tag, v)),
+        ]
+    }
+    pub(crate) fn tree() -> impl Strategy<Value = Tree> {
+        let node = (any::<u8>(), any::<u8>(), any::<u16>(), cst_val())
+            .prop_map(|(feat, op, no_node, val)| TreeNode(feat, op, no_node, val));
+        (
+            prop::collection::vec(node, 0..8),
+            prop::collection::vec("[a-z_.]{1,16}", 0..4),
+        )
+            .prop_map(|(nodes, features)| Tree(nodes, TreeFeatures(features)))
+    }
+
+    fn document() -> Vec<u8> {
+        let mut out = FLITEVOX_MAGIC.to_vec();
+        out.extend_from_slice(&1u32.to_le_bytes());
+        out
+    }
+    fn push_str(out: &mut Vec<u8>, s: &str) {
+        out.extend_from_slice(&(s.len() as u32 + 1).to_le_bytes());
+        out.extend_from_slice(s.as_bytes());
+        out.push(0);
+    }
+    // Tags take `width` bytes, as inside a tree node; everything else a whole cell.
+    fn push_val(out: &mut Vec<u8>, val: &CstVal, width: usize) {
+        out.extend_from_slice(&val.tag().to_le_bytes());
+        out.extend_from_slice(&[0, 0][..width - 2]);
+        match val {
+            CstVal::Int(v) | CstVal::Other(_, v) => out.extend_from_slice(&v.to_le_bytes()),
+            CstVal::Float(v) => out.extend_from_slice(&v.to_le_bytes()),
+            CstVal::Str(v) => push_str(out, v),
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn strings_decode(strings in prop::collection::vec("[a-zA-Z0-9_ :.-]{0,32}", 0..16)) {
+            let mut data = document();
+            data.extend_from_slice(&(strings.len() as u32).to_le_bytes());
+            for s in &strings {
+                push_str(&mut data, s);
+            }
+            prop_assert_eq!(strings, from_bytes::<Vec<String>>(&data).unwrap());
+        }
+
+        #[test]
+        fn pairs_decode(pairs in prop::collection::btree_map("[a-z_]{1,16}", "[a-zA-Z0-9_ :.-]{0,32}", 0..16)) {
+            let mut data = document();
+            for (k, v) in &pairs {
+                push_str(&mut data, k);
+                push_str(&mut data, v);
+            }
+            let decoded = from_bytes::<BTreeMap<String, String>>(&data).unwrap();
+            prop_assert_eq!(&pairs, &decoded);
+            prop_assert_eq!(data, to_bytes(&decoded).unwrap());
+        }
+
+        #[test]
+        fn header_decodes(header in header()) {
+            let mut data = header.version.magic().to_vec();
+            data.extend_from_slice(&1u32.to_le_bytes());
+            for (key, value) in header.features.pairs() {
+                push_str(&mut data, key);
+                push_str(&mut data, &value);
+            }
+            push_str(&mut data, "end_of_features");
+            push_str(&mut data, "end_of_features");
+            push_str(&mut data, &header.name);
+            let decoded = from_bytes::<Header>(&data).unwrap();
+            prop_assert_eq!(&header, &decoded);
+            prop_assert_eq!(data, to_bytes(&decoded).unwrap());
+        }
+
+        #[test]
+        fn cst_val_decodes(val in cst_val()) {
+            let mut data = document();
+            push_val(&mut data, &val, 4);
+            let decoded = from_bytes::<CstVal>(&data).unwrap();
+            prop_assert_eq!(&val, &decoded);
+            prop_assert_eq!(data, to_bytes(&decoded).unwrap());
+        }
+
+        #[test]
+        fn tree_decodes(tree in tree()) {
+            let mut data = document();
+            data.extend_from_slice(&(tree.0.len() as u32).to_le_bytes());
+            for TreeNode(feat, op, no_node, val) in &tree.0 {
+                data.extend_from_slice(&[*feat, *op]);
+                data.extend_from_slice(&no_node.to_le_bytes());
+                push_val(&mut data, val, 2);
+            }
+            data.extend_from_slice(&(tree.1 .0.len() as u32).to_le_bytes());
+            for feature in &tree.1 .0 {
+                push_str(&mut data, feature);
+            }
+            let decoded = from_bytes::<Tree>(&data).unwrap();
+            prop_assert_eq!(&tree, &decoded);
+            prop_assert_eq!(data, to_bytes(&decoded).unwrap());
+        }
+
+        #[test]
+        fn arbitrary_body_is_an_error_not_a_panic(body in prop::collection::vec(any::<u8>(), 0..512)) {
+            let mut data = document();
+            data.extend_from_slice(&body);
+            let _ = from_bytes::<Header>(&data);
+            let _ = from_bytes::<BTreeMap<&str, &str>>(&data);
+        }
+    }
+}
+
+#[test]
+fn test_too_short() {
+    assert!(matches!(
+        from_bytes::<&str>(b"").unwrap_err().kind(),
+        Error::TooShort(0, CST_MIN_LEN)
+    ));
+    assert!(matches!(
+        from_bytes::<&str>(b"CMU_FLITE").unwrap_err().kind(),
+        Error::TooShort(9, CST_MIN_LEN)
+    ));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_located() {
+    let header = &include_bytes!("../data/cmu_us_slt.flitevox")[..394];
+    let mut data = header.to_vec();
+    let age = data.windows(3).position(|w| w == b"30\0").unwrap();
+    data[age + 1] = b'x';
+    let err = from_bytes::<Header>(&data).unwrap_err();
+    let Error::At(located) = &err else {
+        panic!("{err:?} has no location");
+    };
+    assert_eq!(located.offset, age + 3);
+    assert_eq!(located.field, Some("age"));
+    assert_eq!(located.context_start, (age + 3) / 16 * 16);
+    assert_eq!(located.context.len(), 16);
+    let message = err.to_string();
+    assert!(message.contains(&format!("at offset {:#x} in field age", age + 3)));
+    let hexdump = message.lines().nth(1).unwrap();
+    assert!(hexdump.starts_with(&format!("{:08x}", located.context_start)));
+
+    // the field ends with its struct
+    let err = from_bytes::<Header>(&header[..390]).unwrap_err();
+    assert!(matches!(err.kind(), Error::Eof));
+    let message = err.to_string();
+    assert!(message.starts_with("unexpected end of input at offset 0x17f\n"));
+}
+
+#[test]
+fn test_size_prefix() {
+    let mut de = Deserializer::from_bytes(b"\x05\0\0\0\xff\xff\xff\xff");
+    assert_eq!(de.get_size_of_next().unwrap(), 5);
+    assert_eq!(de.get_size_of_next().unwrap(), u32::MAX as usize);
+    assert!(matches!(de.get_size_of_next(), Err(Error::Eof)));
+}
+
+#[test]
+fn test_numeric_strings() {
+    // "172.5" and "16000" as strings, then a binary 7
+    let mut data = Vec::new();
+    data.extend(b"\x06\0\0\x00172.5\0\x06\0\0\x0016000\0");
+    data.extend(7i32.to_le_bytes());
+
+    let mut de = Deserializer::from_bytes(&data).tolerate_numeric_strings(true);
+    let values = <(f32, u32, i32)>::deserialize(&mut de).unwrap();
+    assert_eq!(values, (172.5, 16000, 7));
+    assert_eq!(de.remaining(), 0);
+
+    // not tolerated by default: the size is read as the number
+    let mut de = Deserializer::from_bytes(&data);
+    assert_eq!(i32::deserialize(&mut de).unwrap(), 6);
+
+    // a string which is not a number is left alone
+    let mut de = Deserializer::from_bytes(b"\x04\0\0\0eng\0").tolerate_numeric_strings(true);
+    assert_eq!(u32::deserialize(&mut de).unwrap(), 4);
+}
+
+#[test]
+fn test_widths() {
+    // narrow numbers fill a cell, wide ones take two
+    let mut data = Vec::new();
+    data.extend(b"\xfe\xff\xff\xff\xd4\xfe\xff\xff");
+    data.extend((-2i64).to_le_bytes());
+    data.extend(u64::MAX.to_le_bytes());
+    data.extend(0.1f64.to_le_bytes());
+    let mut de = Deserializer::from_bytes(&data);
+    let values = <(i8, i16, i64, u64, f64)>::deserialize(&mut de).unwrap();
+    assert_eq!(values, (-2, -300, -2, u64::MAX, 0.1));
+    assert_eq!(de.remaining(), 0);
+
+    let mut de = Deserializer::from_bytes(b"\x01\0\0\0");
+    assert!(matches!(i64::deserialize(&mut de), Err(Error::Eof)));
+}
+
+#[cfg(all(feature = "std", feature = "alloc"))]
+#[test]
+fn test_from_reader() {
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let header: Header = from_reader(&data[..]).unwrap();
+    assert_eq!(header, from_bytes::<Header>(data).unwrap());
+
+    let map: std::collections::BTreeMap<String, String> =
+        from_reader(&b"CMU_FLITE_CG_VOXDATA-v2.0\0\x01\0\0\0\x02\0\0\0a\0\x02\0\0\0b\0"[..])
+            .unwrap();
+    assert_eq!(map["a"], "b");
+    let err = from_reader::<_, Header>(&data[..100]).unwrap_err();
+    assert!(matches!(err.kind(), Error::Eof));
+    assert_eq!(err.offset(), Some(100));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_ignored_any() {
+    use serde::de::IgnoredAny;
+
+    // a struct which only wants some of the features
+    #[derive(Deserialize)]
+    struct Partial {
+        language: String,
+        #[allow(dead_code)]
+        end_of_features: crate::EndOfFeatures,
+    }
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let (features, name) = from_bytes::<(Partial, String)>(&data[..394]).unwrap();
+    assert_eq!(features.language, "eng");
+    assert_eq!(name, "cmu_us_slt");
+
+    // the whole feature block, then a number
+    let (_, name) = from_bytes::<(IgnoredAny, String)>(&data[..394]).unwrap();
+    assert_eq!(name, "cmu_us_slt");
+    let data = b"CMU_FLITE_CG_VOXDATA-v2.0\0\x01\0\0\0\x80\x3e\0\0\x04\0\0\0pau\0";
+    let (_, name) = from_bytes::<(IgnoredAny, String)>(data).unwrap();
+    assert_eq!(name, "pau");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_max_allocation() {
+    // a string which claims most of the address space
+    let data = b"CMU_FLITE_CG_VOXDATA-v2.0\0\x01\0\0\0\xff\xff\xff\x7fabc\0";
+    let mut de = Deserializer::from_bytes(data).max_allocation(1 << 20);
+    let err = String::deserialize(&mut de).unwrap_err();
+    assert!(matches!(err, Error::TooLarge(0x7fffffff, 0x100000)));
+    // and a sequence of as many elements which take no bytes at all
+    let mut de = Deserializer::from_bytes(data).max_allocation(1 << 20);
+    assert!(matches!(
+        <Vec<()>>::deserialize(&mut de),
+        Err(Error::TooLarge(..))
+    ));
+
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let mut de = Deserializer::from_bytes(data).max_allocation(1 << 16);
+    assert!(crate::voice::TreeDb::deserialize(&mut de).is_ok());
+    // the endianness marker of a big-endian voice is not a size
+    let data = b"CMU_FLITE_CG_VOXDATA-v2.0\0\0\0\0\x01\0\0\0\x02a\0";
+    let mut de = Deserializer::from_bytes(data).max_allocation(16);
+    assert_eq!(String::deserialize(&mut de).unwrap(), "a");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_options() {
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let strict = DeserializerOptions {
+        deny_trailing_bytes: true,
+        ..DeserializerOptions::default()
+    };
+    assert!(from_bytes_with::<Header>(&data[..394], strict).is_ok());
+    let err = from_bytes_with::<Header>(&data[..400], strict).unwrap_err();
+    assert!(matches!(err.kind(), Error::TrailingBytes));
+    assert_eq!(err.offset(), Some(394));
+    assert!(from_bytes::<Header>(&data[..400]).is_ok());
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Shape {
+        #[allow(dead_code)]
+        Square,
+        Other,
+    }
+    let circle = b"CMU_FLITE_CG_VOXDATA-v2.0\0\x01\0\0\0\x07\0\0\0Circle\0";
+    assert!(from_bytes::<Shape>(circle).is_err());
+    let lenient = DeserializerOptions {
+        unknown_variants_as_other: true,
+        ..DeserializerOptions::default()
+    };
+    assert_eq!(
+        from_bytes_with::<Shape>(circle, lenient).unwrap(),
+        Shape::Other
+    );
+
+    // a little-endian file whose marker is broken
+    let broken = b"CMU_FLITE_CG_VOXDATA-v2.0\0\x07\0\0\0\x02\0\0\0\x04\0\0\0eng\0\x02\0\0\0a\0";
+    assert!(from_bytes::<Vec<&str>>(broken).is_err());
+    let little = DeserializerOptions {
+        byte_order: Some(ByteOrder::Little),
+        ..DeserializerOptions::default()
+    };
+    assert_eq!(
+        from_bytes_with::<Vec<&str>>(broken, little).unwrap(),
+        ["eng", "a"]
+    );
+    let short = DeserializerOptions {
+        max_seq_len: 1,
+        ..little
+    };
+    let err = from_bytes_with::<Vec<&str>>(broken, short).unwrap_err();
+    assert!(matches!(err.kind(), Error::TooLarge(2, 1)));
+    let short = DeserializerOptions {
+        max_string_len: 3,
+        ..little
+    };
+    let err = from_bytes_with::<Vec<&str>>(broken, short).unwrap_err();
+    assert!(matches!(err.kind(), Error::TooLarge(4, 3)));
+}
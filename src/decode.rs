@@ -0,0 +1,84 @@
+//! Decoding the text of voices which is not UTF-8.
+//!
+//! flite treats strings as bytes, so old voices carry text in whatever encoding they were built
+//! with. Give the deserializer a [`StringDecoder`] with [`Deserializer::with_decoder`] to read
+//! them; it is used for every string, including feature names and struct keys.
+//!
+//! [`Deserializer::with_decoder`]: crate::de::Deserializer::with_decoder
+
+extern crate alloc;
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+use crate::error::Result;
+
+/// Turns the bytes of a string, without the null terminator, into text.
+pub trait StringDecoder: Sync {
+    fn decode<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>>;
+}
+
+/// Strict UTF-8, like the deserializer without a decoder.
+pub struct Utf8;
+impl StringDecoder for Utf8 {
+    fn decode<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>> {
+        Ok(Cow::Borrowed(core::str::from_utf8(bytes)?))
+    }
+}
+
+/// UTF-8, with invalid sequences replaced by `U+FFFD`.
+pub struct Utf8Lossy;
+impl StringDecoder for Utf8Lossy {
+    fn decode<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>> {
+        Ok(String::from_utf8_lossy(bytes))
+    }
+}
+
+/// ISO 8859-1: every byte is the code point of the same value.
+pub struct Latin1;
+impl StringDecoder for Latin1 {
+    fn decode<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>> {
+        if bytes.is_ascii() {
+            // ASCII is the same in both, so nothing needs converting
+            return Ok(Cow::Borrowed(core::str::from_utf8(bytes)?));
+        }
+        Ok(Cow::Owned(bytes.iter().map(|&b| char::from(b)).collect()))
+    }
+}
+
+/// UTF-8 where it is valid, ISO 8859-1 otherwise: the usual mix in a directory of old voices.
+pub struct Utf8OrLatin1;
+impl StringDecoder for Utf8OrLatin1 {
+    fn decode<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>> {
+        match core::str::from_utf8(bytes) {
+            Ok(s) => Ok(Cow::Borrowed(s)),
+            Err(_) => Latin1.decode(bytes),
+        }
+    }
+}
+
+#[test]
+fn test_decoders() {
+    assert_eq!(Latin1.decode(b"caf\xe9").unwrap(), "café");
+    assert!(matches!(
+        Latin1.decode(b"eng").unwrap(),
+        Cow::Borrowed("eng")
+    ));
+    assert_eq!(Utf8Lossy.decode(b"caf\xe9").unwrap(), "caf\u{fffd}");
+    assert_eq!(Utf8OrLatin1.decode("café".as_bytes()).unwrap(), "café");
+    assert_eq!(Utf8OrLatin1.decode(b"caf\xe9").unwrap(), "café");
+    assert!(Utf8.decode(b"caf\xe9").is_err());
+}
+
+#[test]
+fn test_with_decoder() {
+    use crate::de::{from_bytes, Deserializer};
+    use alloc::collections::BTreeMap;
+    use serde::Deserialize;
+
+    let data = b"CMU_FLITE_CG_VOXDATA-v2.0\0\x01\0\0\0\x08\0\0\0auteur\xe9\0\x05\0\0\0Jos\xe9\0";
+    assert!(from_bytes::<BTreeMap<String, String>>(data).is_err());
+    let mut de = Deserializer::from_bytes(data).with_decoder(&Latin1);
+    let map = BTreeMap::<String, String>::deserialize(&mut de).unwrap();
+    assert_eq!(map["auteuré"], "José");
+}
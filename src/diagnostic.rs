@@ -0,0 +1,129 @@
+//! Structured findings about a voice, for tools which collect them across many voices.
+//!
+//! A [`Diagnostic`] serializes with any serde format, so the output of [`preflight::diagnose`]
+//! and the warnings of [`best_effort::from_bytes`] can be dumped as JSON, CSV and so on.
+//!
+//! [`preflight::diagnose`]: crate::preflight::diagnose
+//! [`best_effort::from_bytes`]: crate::best_effort::from_bytes
+
+use core::fmt::{self, Display, Formatter};
+
+use serde::Serialize;
+
+use crate::preflight::Inconsistency;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Something was off, but the value could be recovered or defaulted.
+    Warning,
+    /// The voice, or a part of it, cannot be used.
+    Error,
+}
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    /// A stable, kebab-case identifier of the kind of finding, e.g. `"bad-date"`.
+    pub code: &'static str,
+    pub severity: Severity,
+    /// The byte offset in the file the finding is about, if known.
+    pub offset: Option<usize>,
+    /// The feature name, `"name"` for the voice name, or the section of the body.
+    pub field: String,
+    pub message: String,
+}
+impl Diagnostic {
+    pub fn warning(code: &'static str, field: &str, message: String) -> Diagnostic {
+        Diagnostic {
+            code,
+            severity: Severity::Warning,
+            offset: None,
+            field: field.to_string(),
+            message,
+        }
+    }
+    pub fn error(code: &'static str, field: &str, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            ..Diagnostic::warning(code, field, message)
+        }
+    }
+    pub fn at(self, offset: usize) -> Diagnostic {
+        Diagnostic {
+            offset: Some(offset),
+            ..self
+        }
+    }
+}
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}] {}", self.severity, self.code, self.field)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {offset}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl From<&Inconsistency> for Diagnostic {
+    fn from(inconsistency: &Inconsistency) -> Diagnostic {
+        let message = inconsistency.to_string();
+        match *inconsistency {
+            Inconsistency::InvalidHeader => {
+                Diagnostic::error("invalid-header", "header", message).at(0)
+            }
+            Inconsistency::Overrun {
+                offset, section, ..
+            } => Diagnostic::error("overrun", section, message).at(offset),
+            Inconsistency::BadFeature { offset, feature } => {
+                Diagnostic::error("bad-feature", feature, message).at(offset)
+            }
+            Inconsistency::DuplicateFeature { offset, feature } => {
+                Diagnostic::error("duplicate-feature", feature, message).at(offset)
+            }
+            Inconsistency::TrailingBytes { offset, .. } => {
+                Diagnostic::error("trailing-bytes", "body", message).at(offset)
+            }
+        }
+    }
+}
+
+#[test]
+fn test_from_inconsistency() {
+    let diagnostic = Diagnostic::from(&Inconsistency::BadFeature {
+        offset: 120,
+        feature: "num_f0_models",
+    });
+    assert_eq!(diagnostic.code, "bad-feature");
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert_eq!(diagnostic.offset, Some(120));
+    assert_eq!(
+        diagnostic.to_string(),
+        "error[bad-feature] num_f0_models at offset 120: \
+         feature num_f0_models at offset 120 is not a number"
+    );
+}
+
+#[test]
+fn test_diagnose() {
+    use crate::preflight::diagnose;
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    assert_eq!(diagnose(data), None);
+    let diagnostic = diagnose(&data[..data.len() - 1]).unwrap();
+    assert_eq!(
+        (
+            diagnostic.code,
+            diagnostic.field.as_str(),
+            diagnostic.offset
+        ),
+        ("overrun", "gain", Some(data.len() - 4))
+    );
+}
@@ -0,0 +1,257 @@
+//! What differs between two parsed voices.
+//!
+//! [`diff`] compares the decoded voices, so it can tell which trees changed rather than only how
+//! many bytes their sections take; [`diff_bytes`] compares two files, and also measures how many
+//! bytes every section of each takes. [`report`](crate::report) renders a [`VoiceDiff`] as text or
+//! Markdown.
+
+use crate::error::Result;
+use crate::sections::Sections;
+use crate::voice::{SpamF0, Tree, TreeDb, Trees};
+
+/// A feature whose value differs, as it is stored. Every voice has every feature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureChange {
+    pub feature: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// A count which differs: bytes of a section, or trees in it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Delta {
+    pub section: &'static str,
+    pub old: usize,
+    pub new: usize,
+}
+impl Delta {
+    pub fn change(&self) -> i128 {
+        self.new as i128 - self.old as i128
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A tree which only one of the voices has, or which differs between them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeChange {
+    /// `f0_trees`, `param_trees`, `spamf0_accent_tree`, `spamf0_phrase_tree` or `dur_cart`, as
+    /// the sections of a [`MemoryReport`](crate::accounting::MemoryReport) are named.
+    pub section: &'static str,
+    /// Which model of the section the tree belongs to.
+    pub model: usize,
+    /// For `f0_trees` and `param_trees`, the state the tree is for, an index into `db_types`.
+    pub state: Option<usize>,
+    pub kind: TreeChangeKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoiceDiff {
+    pub old_name: String,
+    pub new_name: String,
+    pub features: Vec<FeatureChange>,
+    /// In file order.
+    pub trees: Vec<TreeChange>,
+    /// Sections whose size in bytes differs between the files, in file order. Only
+    /// [`diff_bytes`] has the files to measure, so always empty from [`diff`].
+    pub sections: Vec<Delta>,
+}
+impl VoiceDiff {
+    pub fn is_empty(&self) -> bool {
+        self.old_name == self.new_name
+            && self.features.is_empty()
+            && self.trees.is_empty()
+            && self.sections.is_empty()
+    }
+}
+
+/// The counts which differ, for every section of either.
+fn deltas(old: &[(&'static str, usize)], new: &[(&'static str, usize)]) -> Vec<Delta> {
+    let count = |counts: &[(&str, usize)], section| {
+        counts
+            .iter()
+            .find(|(known, _)| *known == section)
+            .map_or(0, |(_, n)| *n)
+    };
+    let mut sections: Vec<&'static str> = old.iter().map(|(section, _)| *section).collect();
+    for (section, _) in new {
+        if !sections.contains(section) {
+            sections.push(*section);
+        }
+    }
+    sections
+        .into_iter()
+        .map(|section| Delta {
+            section,
+            old: count(old, section),
+            new: count(new, section),
+        })
+        .filter(|delta| delta.old != delta.new)
+        .collect()
+}
+
+fn compare(
+    changes: &mut Vec<TreeChange>,
+    section: &'static str,
+    model: usize,
+    state: Option<usize>,
+    old: Option<&Tree>,
+    new: Option<&Tree>,
+) {
+    let kind = match (old, new) {
+        (Some(old), Some(new)) if old == new => return,
+        (Some(_), Some(_)) => TreeChangeKind::Changed,
+        (None, Some(_)) => TreeChangeKind::Added,
+        (Some(_), None) => TreeChangeKind::Removed,
+        (None, None) => return,
+    };
+    changes.push(TreeChange {
+        section,
+        model,
+        state,
+        kind,
+    });
+}
+
+fn compare_models(
+    changes: &mut Vec<TreeChange>,
+    section: &'static str,
+    old: &[Trees],
+    new: &[Trees],
+) {
+    for model in 0..old.len().max(new.len()) {
+        let (old, new) = (old.get(model), new.get(model));
+        let states = old
+            .map_or(0, |t| t.0.len())
+            .max(new.map_or(0, |t| t.0.len()));
+        for state in 0..states {
+            let old = old.and_then(|t| t.0.get(state));
+            let new = new.and_then(|t| t.0.get(state));
+            compare(changes, section, model, Some(state), old, new);
+        }
+    }
+}
+
+fn spamf0_trees(spamf0: Option<&SpamF0>) -> [Option<&Tree>; 2] {
+    match spamf0 {
+        Some(spamf0) => [Some(&spamf0.accent_tree), Some(&spamf0.phrase_tree)],
+        None => [None, None],
+    }
+}
+
+/// Compare two voices: their features and every tree.
+pub fn diff(a: &TreeDb, b: &TreeDb) -> VoiceDiff {
+    let old_features = a.header.features.pairs();
+    let new_features = b.header.features.pairs();
+    let features = old_features
+        .into_iter()
+        .zip(new_features)
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((feature, old), (_, new))| FeatureChange {
+            feature: feature.to_string(),
+            old,
+            new,
+        })
+        .collect();
+
+    let (old, new) = (&a.body, &b.body);
+    let mut trees = Vec::new();
+    compare_models(&mut trees, "f0_trees", &old.f0_trees, &new.f0_trees);
+    compare_models(
+        &mut trees,
+        "param_trees",
+        &old.param_trees,
+        &new.param_trees,
+    );
+    let spamf0 = ["spamf0_accent_tree", "spamf0_phrase_tree"];
+    let spamf0 = spamf0
+        .into_iter()
+        .zip(spamf0_trees(old.spamf0.as_ref()))
+        .zip(spamf0_trees(new.spamf0.as_ref()));
+    for ((section, old), new) in spamf0 {
+        compare(&mut trees, section, 0, None, old, new);
+    }
+    for model in 0..old.dur_models.len().max(new.dur_models.len()) {
+        let old = old.dur_models.get(model).map(|m| &m.1);
+        let new = new.dur_models.get(model).map(|m| &m.1);
+        compare(&mut trees, "dur_cart", model, None, old, new);
+    }
+
+    VoiceDiff {
+        old_name: a.header.name.clone(),
+        new_name: b.header.name.clone(),
+        features,
+        trees,
+        sections: Vec::new(),
+    }
+}
+
+/// Compare two voice files: like [`diff`], and the bytes every section takes in each. Their
+/// trailers, if they have any, are verified.
+pub fn diff_bytes(old: &[u8], new: &[u8]) -> Result<VoiceDiff> {
+    let read = |input| -> Result<(TreeDb, Vec<(&'static str, usize)>)> {
+        let sections = Sections::new(input)?;
+        Ok((
+            TreeDb::from_sections(&sections)?,
+            sections.sizes().collect(),
+        ))
+    };
+    let (a, old) = read(old)?;
+    let (b, new) = read(new)?;
+    let mut diff = diff(&a, &b);
+    diff.sections = deltas(&old, &new);
+    Ok(diff)
+}
+
+#[test]
+fn test_diff() {
+    use crate::de::from_bytes;
+    use crate::voice::CstVal;
+
+    let old: TreeDb = from_bytes(include_bytes!("../data/cmu_us_slt.flitevox")).unwrap();
+    assert!(diff(&old, &old).is_empty());
+
+    let mut new = old.clone();
+    new.header.features.copyright = "MIT".to_string();
+    new.body.f0_trees[1].0[7].0[0].3 = CstVal::Float(-12345.0);
+    let d = diff(&old, &new);
+    assert_eq!(d.features.len(), 1);
+    assert_eq!(d.features[0].feature, "copyright");
+    assert_eq!(d.features[0].new, "MIT");
+    let change = |section, model, state, kind| TreeChange {
+        section,
+        model,
+        state: Some(state),
+        kind,
+    };
+    assert_eq!(d.trees, [change("f0_trees", 1, 7, TreeChangeKind::Changed)]);
+    assert!(d.sections.is_empty());
+
+    // the files are measured as they are
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let mut written = crate::ser::to_bytes(&new).unwrap();
+    crate::trailer::append_trailer(&mut written);
+    let d = diff_bytes(data, &written).unwrap();
+    assert_eq!(d.trees, [change("f0_trees", 1, 7, TreeChangeKind::Changed)]);
+    let delta = |section, old, new| Delta { section, old, new };
+    assert_eq!(
+        d.sections,
+        [
+            delta("features", 349, 345),
+            delta("f0_trees", 361187, 361185)
+        ]
+    );
+    assert!(diff_bytes(data, &data[..data.len() - 1]).is_err());
+
+    new.body.param_trees[2].0.pop();
+    let d = diff(&old, &new);
+    assert_eq!(
+        d.trees[1..],
+        [change("param_trees", 2, 123, TreeChangeKind::Removed)]
+    );
+}
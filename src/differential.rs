@@ -0,0 +1,169 @@
+//! Differential tests against flite's own loader.
+//!
+//! Only built for `cargo test --features differential`, and it links against an installed
+//! `libflite` (plus the US English language and CMU lexicon it needs to accept `eng` voices).
+//! Each voice is loaded by both flite and this crate, and the fields flite exposes are compared.
+//! Extra voices can be listed in `SERDE_CST_DIFF_VOICES`, separated by the platform's path
+//! separator.
+
+extern crate std;
+
+use core::ffi::{c_char, c_float, c_int, c_void, CStr};
+use std::{env, ffi::CString, fs, path::PathBuf, string::String, vec::Vec};
+
+use crate::{de::from_bytes, header_ref::FEATURE_KEYS, voice::TreeDb, Gender};
+
+#[repr(C)]
+struct CstVoice {
+    name: *const c_char,
+    features: *mut c_void,
+    ffunctions: *mut c_void,
+    utt_init: *mut c_void,
+}
+
+// The leading fields of flite's `cst_cg_db`, which have kept their layout across releases.
+#[repr(C)]
+struct CstCgDbPrefix {
+    name: *const c_char,
+    types: *const *const c_char,
+    num_types: c_int,
+    sample_rate: c_int,
+    f0_mean: c_float,
+    f0_stddev: c_float,
+}
+
+#[link(name = "flite")]
+#[link(name = "flite_usenglish")]
+#[link(name = "flite_cmulex")]
+extern "C" {
+    fn flite_init() -> c_int;
+    fn flite_add_lang(
+        lang: *const c_char,
+        lang_init: unsafe extern "C" fn(*mut CstVoice),
+        lex_init: unsafe extern "C" fn() -> *mut c_void,
+    ) -> c_int;
+    fn usenglish_init(voice: *mut CstVoice);
+    fn cmu_lex_init() -> *mut c_void;
+    fn flite_voice_load(voice_filename: *const c_char) -> *mut CstVoice;
+    fn flite_get_param_string(
+        features: *const c_void,
+        name: *const c_char,
+        def: *const c_char,
+    ) -> *const c_char;
+    fn feat_val(features: *const c_void, name: *const c_char) -> *const c_void;
+    fn val_cg_db(val: *const c_void) -> *const CstCgDbPrefix;
+}
+
+/// What flite reports for a voice, in the same shapes this crate uses.
+#[derive(Debug, PartialEq)]
+struct Reported {
+    features: Vec<(&'static str, String)>,
+    name: String,
+    types: Vec<String>,
+    num_types: i32,
+    sample_rate: i32,
+    f0_mean: f32,
+    f0_stddev: f32,
+}
+
+// Every feature but the end marker, which flite does not keep.
+fn feature_keys() -> &'static [&'static str] {
+    &FEATURE_KEYS[..FEATURE_KEYS.len() - 1]
+}
+
+unsafe fn string(s: *const c_char) -> String {
+    CStr::from_ptr(s).to_str().unwrap().to_string()
+}
+
+fn load_with_flite(path: &CStr) -> Reported {
+    unsafe {
+        let voice = flite_voice_load(path.as_ptr());
+        assert!(!voice.is_null(), "flite could not load {path:?}");
+        let features = (*voice).features;
+        let reported = feature_keys()
+            .iter()
+            .map(|&feature| {
+                let name = CString::new(feature).unwrap();
+                let value = flite_get_param_string(features, name.as_ptr(), c"".as_ptr());
+                (feature, string(value))
+            })
+            .collect();
+        let db = &*val_cg_db(feat_val(features, c"cg_db".as_ptr()));
+        // null terminated, like every list in flite
+        let mut types = Vec::new();
+        while !(*db.types.add(types.len())).is_null() {
+            types.push(string(*db.types.add(types.len())));
+        }
+        Reported {
+            features: reported,
+            name: string(db.name),
+            types,
+            num_types: db.num_types,
+            sample_rate: db.sample_rate,
+            f0_mean: db.f0_mean,
+            f0_stddev: db.f0_stddev,
+        }
+    }
+}
+
+fn load_with_serde_cst(data: &[u8]) -> Reported {
+    let TreeDb { header, body } = from_bytes::<TreeDb>(data).unwrap();
+    let f = header.features;
+    let gender = match f.gender {
+        Gender::Male => "male",
+        Gender::Female => "female",
+        Gender::Unknown => "unknown",
+        Gender::None => "none",
+    };
+    let features = [
+        f.language,
+        f.country,
+        f.variant,
+        f.age.to_string(),
+        gender.to_string(),
+        f.build_date.to_string(),
+        f.description,
+        f.eng_shared.to_string(),
+        f.copyright,
+        f.num_dur_models.to_string(),
+        f.num_param_models.to_string(),
+        f.model_shape.to_string(),
+        f.num_f0_models.to_string(),
+    ];
+    Reported {
+        features: feature_keys().iter().copied().zip(features).collect(),
+        name: header.name,
+        types: body.db_types,
+        num_types: body.num_types,
+        sample_rate: body.sample_rate,
+        f0_mean: body.f0_mean,
+        f0_stddev: body.f0_stddev,
+    }
+}
+
+fn voices() -> Vec<PathBuf> {
+    let mut voices = vec![PathBuf::from(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/data/cmu_us_slt.flitevox"
+    ))];
+    if let Some(extra) = env::var_os("SERDE_CST_DIFF_VOICES") {
+        voices.extend(env::split_paths(&extra));
+    }
+    voices
+}
+
+#[test]
+fn test_against_flite() {
+    unsafe {
+        flite_init();
+        flite_add_lang(c"eng".as_ptr(), usenglish_init, cmu_lex_init);
+        flite_add_lang(c"usenglish".as_ptr(), usenglish_init, cmu_lex_init);
+    }
+    for path in voices() {
+        let data = fs::read(&path).unwrap();
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+        let ours = load_with_serde_cst(&data);
+        let theirs = load_with_flite(&c_path);
+        assert_eq!(ours, theirs, "{}", path.display());
+    }
+}
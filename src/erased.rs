@@ -0,0 +1,45 @@
+//! Use the deserializer through [`erased_serde`], for pipelines which pick the target type at
+//! runtime.
+//!
+//! Errors coming out of the erased deserializer are [`erased_serde::Error`]s, which only keep the
+//! message of the original [`Error`](crate::error::Error).
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+
+use crate::de::Deserializer;
+
+/// Wrap `de` as an object-safe deserializer.
+pub fn erase<'a, 'de>(
+    de: &'a mut Deserializer<'de>,
+) -> Box<dyn erased_serde::Deserializer<'de> + 'a> {
+    Box::new(<dyn erased_serde::Deserializer>::erase(de))
+}
+
+/// Deserialize a `T` from an erased deserializer, e.g. one returned by [`erase`].
+pub fn deserialize<'de, T: serde::Deserialize<'de>>(
+    de: &mut dyn erased_serde::Deserializer<'de>,
+) -> Result<T, erased_serde::Error> {
+    erased_serde::deserialize(de)
+}
+
+#[test]
+fn test_erase() {
+    use crate::Header;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    // the target type is only known at runtime
+    type Reader = fn(&mut dyn erased_serde::Deserializer<'_>) -> Option<String>;
+    let readers: [Reader; 2] = [
+        |de| deserialize::<Header>(de).ok().map(|header| header.name),
+        |de| deserialize::<String>(de).ok(),
+    ];
+    let read: Vec<Option<String>> = readers
+        .iter()
+        .map(|reader| reader(&mut *erase(&mut Deserializer::from_bytes(data))))
+        .collect();
+    assert_eq!(read, [Some("cmu_us_slt".into()), Some("language".into())]);
+}
@@ -4,18 +4,78 @@ use core::num::ParseIntError;
 use core::result;
 use core::str::Utf8Error;
 
+#[cfg(feature = "alloc")]
+use crate::diagnostic::Diagnostic;
+use crate::preflight::Inconsistency;
+
 #[derive(Debug)]
 pub enum Error {
     Eof,
     InvalidHeader,
+    /// The input is shorter than the smallest possible voice file: (actual, minimum).
+    TooShort(usize, usize),
     ExpectedSize(usize, usize),
+    /// A size prefix which does not fit in a `usize` on this target.
+    SizeOverflow(u32),
+    /// A size prefix above the limit set with
+    /// [`Deserializer::max_allocation`](crate::de::Deserializer::max_allocation): (size, limit).
+    TooLarge(usize, usize),
     ExpectedBool,
     NotUtf8(Utf8Error),
     ParseInt(ParseIntError),
     WrongLength(usize),
     UnexpectedField(&'static str),
     TrailingBytes,
+    Unsupported(&'static str),
+    /// The integrity trailer does not match the data: (stored, computed).
+    ChecksumMismatch(u32, u32),
+    /// A feature appears twice in the feature block: (key, offset of the second).
+    DuplicateFeature(&'static str, usize),
+    /// A section does not match its digest in the digest trailer: (section, offset).
+    SectionChecksumMismatch(&'static str, usize),
+    /// The input ends in the magic of a digest trailer, but is too short for the digests it
+    /// counts.
+    TruncatedTrailer,
+    /// The declared structure of the file does not fit its length.
+    Structure(Inconsistency),
+    /// A voice being written has a problem which would keep flite from loading it.
+    #[cfg(feature = "alloc")]
+    Invalid(Box<Diagnostic>),
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
     Message(String),
+    /// Another error, and where in the input it happened.
+    At(Box<Located>),
+}
+
+/// Where in the input the [`Deserializer`](crate::de::Deserializer) was when it failed.
+#[derive(Debug)]
+pub struct Located {
+    pub error: Error,
+    /// Bytes read before the error, including the magic.
+    pub offset: usize,
+    /// The field of the struct being read, if any.
+    pub field: Option<&'static str>,
+    /// The bytes around `offset`, starting at `context_start`.
+    pub context: Vec<u8>,
+    pub context_start: usize,
+}
+
+impl Error {
+    /// The error without its location.
+    pub fn kind(&self) -> &Error {
+        match self {
+            Error::At(located) => located.error.kind(),
+            error => error,
+        }
+    }
+    /// Where in the input the error happened, if known.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Error::At(located) => Some(located.offset),
+            _ => None,
+        }
+    }
 }
 impl From<Utf8Error> for Error {
     fn from(utf8e: Utf8Error) -> Error {
@@ -27,16 +87,101 @@ impl From<ParseIntError> for Error {
         Error::ParseInt(pie)
     }
 }
+impl From<Inconsistency> for Error {
+    fn from(inconsistency: Inconsistency) -> Error {
+        Error::Structure(inconsistency)
+    }
+}
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.write_str("Error?")
+        match self {
+            Error::Eof => f.write_str("unexpected end of input"),
+            Error::InvalidHeader => f.write_str("not a CG voice file"),
+            Error::TooShort(actual, minimum) => write!(
+                f,
+                "{actual} bytes is too short for a voice file, which takes at least {minimum}"
+            ),
+            Error::ExpectedSize(actual, expected) => {
+                write!(f, "expected a size of {expected}, found {actual}")
+            }
+            Error::SizeOverflow(size) => write!(f, "size {size} does not fit in memory"),
+            Error::TooLarge(size, limit) => write!(f, "size {size} is above the limit of {limit}"),
+            Error::ExpectedBool => f.write_str("expected a bool"),
+            Error::NotUtf8(err) => write!(f, "string is not UTF-8: {err}"),
+            Error::ParseInt(err) => write!(f, "not a number: {err}"),
+            Error::WrongLength(len) => write!(f, "wrong length {len}"),
+            Error::UnexpectedField(field) => write!(f, "unexpected field {field}"),
+            Error::TrailingBytes => f.write_str("unexpected bytes after the end of the input"),
+            Error::Unsupported(what) => write!(f, "{what} is not supported by the format"),
+            Error::ChecksumMismatch(stored, computed) => write!(
+                f,
+                "the integrity trailer holds checksum {stored:08x}, but the data has {computed:08x}"
+            ),
+            Error::DuplicateFeature(key, offset) => {
+                write!(f, "feature {key} appears again at offset {offset}")
+            }
+            Error::SectionChecksumMismatch(section, offset) => write!(
+                f,
+                "section {section} at offset {offset} does not match its digest"
+            ),
+            Error::TruncatedTrailer => f.write_str("the digest trailer is truncated"),
+            Error::Structure(inconsistency) => write!(f, "{inconsistency}"),
+            #[cfg(feature = "alloc")]
+            Error::Invalid(diagnostic) => write!(f, "{diagnostic}"),
+            #[cfg(feature = "std")]
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Message(msg) => f.write_str(msg),
+            Error::At(located) => write!(f, "{located}"),
+        }
+    }
+}
+/// The error, where it happened, and a hexdump of the bytes around it.
+impl Display for Located {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {:#x}", self.error, self.offset)?;
+        if let Some(field) = self.field {
+            write!(f, " in field {field}")?;
+        }
+        for (i, line) in self.context.chunks(16).enumerate() {
+            write!(f, "\n{:08x} ", self.context_start + i * 16)?;
+            for byte in line {
+                write!(f, " {byte:02x}")?;
+            }
+            write!(f, "{:1$} |", "", 3 * (16 - line.len()))?;
+            for &byte in line {
+                let c = if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                };
+                write!(f, "{c}")?;
+            }
+            f.write_str("|")?;
+        }
+        Ok(())
+    }
+}
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::NotUtf8(err) => Some(err),
+            Error::ParseInt(err) => Some(err),
+            #[cfg(feature = "std")]
+            Error::Io(err) => Some(err),
+            Error::At(located) => Some(&located.error),
+            _ => None,
+        }
     }
 }
-impl error::Error for Error {}
 impl serde::de::Error for Error {
     fn custom<T: Display>(msg: T) -> Self {
         Error::Message(msg.to_string())
     }
 }
+impl serde::ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
 
 pub type Result<T> = result::Result<T, Error>;
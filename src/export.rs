@@ -0,0 +1,211 @@
+//! Export model parameters as CSV and NumPy `.npy` files, for analysis in pandas or NumPy.
+//!
+//! Every writer takes any [`Write`]; [`export_dir`] writes the whole set for a voice into a
+//! directory.
+
+extern crate std;
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::model::{CgModel, DurModel, ParamModel, Quantization};
+
+fn write_npy_header<W: Write>(w: &mut W, descr: &str, shape: &[usize]) -> io::Result<()> {
+    let shape = match shape {
+        [len] => format!("({len},)"),
+        _ => {
+            let dims: Vec<String> = shape.iter().map(|dim| dim.to_string()).collect();
+            format!("({})", dims.join(", "))
+        }
+    };
+    let mut header = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+    // magic, version and header length take 10 bytes; the data has to start 64 byte aligned
+    let unpadded = 10 + header.len() + 1;
+    header.extend(core::iter::repeat_n(' ', (64 - unpadded % 64) % 64));
+    header.push('\n');
+    w.write_all(b"\x93NUMPY\x01\x00")?;
+    w.write_all(&(header.len() as u16).to_le_bytes())?;
+    w.write_all(header.as_bytes())
+}
+
+/// Frames are stored as little-endian `u16` cells for the base model shape, and as single byte
+/// table indices for quantized shapes.
+fn frame_cells(model: &ParamModel) -> io::Result<(usize, usize)> {
+    let Some(width) = model.frames.row_len() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "frames of a model differ in length",
+        ));
+    };
+    if width == model.num_channels * 2 {
+        Ok((2, model.num_channels))
+    } else {
+        Ok((1, width))
+    }
+}
+
+/// Write the stored frame values of `model`, one frame per row.
+pub fn write_frames_csv<W: Write>(model: &ParamModel, mut w: W) -> io::Result<()> {
+    let (cell, columns) = frame_cells(model)?;
+    let header: Vec<String> = (0..columns).map(|c| format!("c{c}")).collect();
+    writeln!(w, "{}", header.join(","))?;
+    for row in model.frames.rows() {
+        let values: Vec<String> = row
+            .0
+            .chunks_exact(cell)
+            .map(|bytes| match bytes {
+                [lo, hi] => u16::from_le_bytes([*lo, *hi]).to_string(),
+                [byte] => byte.to_string(),
+                _ => unreachable!(),
+            })
+            .collect();
+        writeln!(w, "{}", values.join(","))?;
+    }
+    Ok(())
+}
+
+/// Write the stored frame values of `model` as a `(frames, channels)` array of `u16` (or `u8`
+/// for quantized shapes).
+pub fn write_frames_npy<W: Write>(model: &ParamModel, mut w: W) -> io::Result<()> {
+    let (cell, columns) = frame_cells(model)?;
+    let descr = if cell == 2 { "<u2" } else { "|u1" };
+    write_npy_header(&mut w, descr, &[model.frames.len(), columns])?;
+    // already little-endian
+    w.write_all(model.frames.as_bytes())
+}
+
+/// Write `channel,min,range`, one channel per row.
+pub fn write_min_range_csv<W: Write>(quantization: &Quantization, mut w: W) -> io::Result<()> {
+    writeln!(w, "channel,min,range")?;
+    for (channel, (min, range)) in quantization
+        .model_min
+        .iter()
+        .zip(&quantization.model_range)
+        .enumerate()
+    {
+        writeln!(w, "{channel},{min},{range}")?;
+    }
+    Ok(())
+}
+
+/// Write a `(2, channels)` array of `f32`: the minimums, then the ranges.
+pub fn write_min_range_npy<W: Write>(quantization: &Quantization, mut w: W) -> io::Result<()> {
+    let channels = quantization
+        .model_min
+        .len()
+        .min(quantization.model_range.len());
+    write_npy_header(&mut w, "<f4", &[2, channels])?;
+    for value in quantization.model_min[..channels]
+        .iter()
+        .chain(&quantization.model_range[..channels])
+    {
+        w.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Write `phone,mean,stddev`, one phone per row.
+pub fn write_dur_stats_csv<W: Write>(model: &DurModel, mut w: W) -> io::Result<()> {
+    writeln!(w, "phone,mean,stddev")?;
+    for stat in &model.stats {
+        writeln!(w, "{},{},{}", stat.phone, stat.mean, stat.stddev)?;
+    }
+    Ok(())
+}
+
+/// Write a `(phones, 2)` array of `f32` holding mean and standard deviation, in the same phone
+/// order as [`write_dur_stats_csv`].
+pub fn write_dur_stats_npy<W: Write>(model: &DurModel, mut w: W) -> io::Result<()> {
+    write_npy_header(&mut w, "<f4", &[model.stats.len(), 2])?;
+    for stat in &model.stats {
+        w.write_all(&stat.mean.to_le_bytes())?;
+        w.write_all(&stat.stddev.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Write `mean,stddev` of the voice's f0.
+pub fn write_f0_csv<W: Write>(model: &CgModel, mut w: W) -> io::Result<()> {
+    writeln!(w, "mean,stddev")?;
+    writeln!(w, "{},{}", model.f0_mean, model.f0_stddev)
+}
+
+/// Write a `(2,)` array of `f32` holding the f0 mean and standard deviation.
+pub fn write_f0_npy<W: Write>(model: &CgModel, mut w: W) -> io::Result<()> {
+    write_npy_header(&mut w, "<f4", &[2])?;
+    w.write_all(&model.f0_mean.to_le_bytes())?;
+    w.write_all(&model.f0_stddev.to_le_bytes())
+}
+
+fn create(dir: &Path, name: &str) -> io::Result<BufWriter<File>> {
+    File::create(dir.join(name)).map(BufWriter::new)
+}
+
+/// Write every export for `model` into `dir`, creating it if needed: `f0`, `model_min_range`,
+/// `frames_<n>` per parameter model and `dur_stats_<n>` per duration model, each as `.csv` and
+/// `.npy`.
+pub fn export_dir(model: &CgModel, dir: impl AsRef<Path>) -> io::Result<()> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    write_f0_csv(model, create(dir, "f0.csv")?)?;
+    write_f0_npy(model, create(dir, "f0.npy")?)?;
+    write_min_range_csv(&model.quantization, create(dir, "model_min_range.csv")?)?;
+    write_min_range_npy(&model.quantization, create(dir, "model_min_range.npy")?)?;
+    for (i, params) in model.param_models.iter().enumerate() {
+        write_frames_csv(params, create(dir, &format!("frames_{i}.csv"))?)?;
+        write_frames_npy(params, create(dir, &format!("frames_{i}.npy"))?)?;
+    }
+    for (i, dur) in model.dur_models.iter().enumerate() {
+        write_dur_stats_csv(dur, create(dir, &format!("dur_stats_{i}.csv"))?)?;
+        write_dur_stats_npy(dur, create(dir, &format!("dur_stats_{i}.npy"))?)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_frames() {
+    let model = ParamModel {
+        num_channels: 2,
+        frames: [[1, 0, 0, 1], [0xff, 0xff, 2, 0]].into_iter().collect(),
+    };
+    let mut csv = Vec::new();
+    write_frames_csv(&model, &mut csv).unwrap();
+    assert_eq!(csv, b"c0,c1\n1,256\n65535,2\n");
+
+    let mut npy = Vec::new();
+    write_frames_npy(&model, &mut npy).unwrap();
+    assert!(npy.starts_with(b"\x93NUMPY\x01\x00"));
+    let header_len = usize::from(u16::from_le_bytes([npy[8], npy[9]]));
+    assert_eq!((10 + header_len) % 64, 0);
+    let header = core::str::from_utf8(&npy[10..10 + header_len]).unwrap();
+    assert!(header.starts_with("{'descr': '<u2', 'fortran_order': False, 'shape': (2, 2), }"));
+    assert!(header.ends_with('\n'));
+    assert_eq!(&npy[10 + header_len..], &[1, 0, 0, 1, 0xff, 0xff, 2, 0]);
+
+    let ragged = ParamModel {
+        num_channels: 2,
+        frames: [&[1, 0, 0, 1][..], &[0]].into_iter().collect(),
+    };
+    assert!(write_frames_npy(&ragged, Vec::new()).is_err());
+}
+
+#[test]
+fn test_min_range() {
+    let quantization = Quantization {
+        model_shape: 1,
+        model_min: vec![-1.0, 0.5],
+        model_range: vec![2.0, 4.0],
+        qtables: Vec::new(),
+    };
+    let mut csv = Vec::new();
+    write_min_range_csv(&quantization, &mut csv).unwrap();
+    assert_eq!(csv, b"channel,min,range\n0,-1,2\n1,0.5,4\n");
+    let mut npy = Vec::new();
+    write_min_range_npy(&quantization, &mut npy).unwrap();
+    let data: Vec<f32> = npy[npy.len() - 16..]
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+        .collect();
+    assert_eq!(data, [-1.0, 0.5, 2.0, 4.0]);
+}
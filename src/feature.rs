@@ -0,0 +1,183 @@
+//! Typed values for reading feature blocks generically.
+//!
+//! Every feature is stored as a string, but many of them hold numbers. A [`FeatureVal`] keeps
+//! the value as the most specific of string, int or float it parses as.
+
+use core::fmt::{self, Display, Formatter};
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::{Serialize, Serializer};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeatureVal {
+    Str(String),
+    Int(i32),
+    Float(f32),
+}
+impl FeatureVal {
+    /// Parse a stored feature value: an int if it is one, else a finite float, else a string.
+    pub fn parse(s: &str) -> FeatureVal {
+        if let Ok(i) = s.parse() {
+            FeatureVal::Int(i)
+        } else if let Some(f) = s.parse::<f32>().ok().filter(|f| f.is_finite()) {
+            FeatureVal::Float(f)
+        } else {
+            FeatureVal::Str(s.to_string())
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            FeatureVal::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+    pub fn as_int(&self) -> Option<i32> {
+        match self {
+            FeatureVal::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+    /// The value as a float; ints convert.
+    pub fn as_float(&self) -> Option<f32> {
+        match self {
+            FeatureVal::Int(i) => Some(*i as f32),
+            FeatureVal::Float(f) => Some(*f),
+            FeatureVal::Str(_) => None,
+        }
+    }
+}
+
+/// Formats the value the way it would be stored.
+impl Display for FeatureVal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FeatureVal::Str(s) => f.write_str(s),
+            FeatureVal::Int(i) => write!(f, "{i}"),
+            FeatureVal::Float(x) => write!(f, "{x}"),
+        }
+    }
+}
+
+/// The feature value is not of the requested type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WrongType(pub FeatureVal);
+impl Display for WrongType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "feature value {:?} has the wrong type", self.0)
+    }
+}
+
+impl From<String> for FeatureVal {
+    fn from(s: String) -> FeatureVal {
+        FeatureVal::Str(s)
+    }
+}
+impl From<&str> for FeatureVal {
+    fn from(s: &str) -> FeatureVal {
+        FeatureVal::Str(s.to_string())
+    }
+}
+impl From<i32> for FeatureVal {
+    fn from(i: i32) -> FeatureVal {
+        FeatureVal::Int(i)
+    }
+}
+impl From<f32> for FeatureVal {
+    fn from(f: f32) -> FeatureVal {
+        FeatureVal::Float(f)
+    }
+}
+impl TryFrom<FeatureVal> for String {
+    type Error = WrongType;
+    fn try_from(val: FeatureVal) -> Result<String, WrongType> {
+        match val {
+            FeatureVal::Str(s) => Ok(s),
+            other => Err(WrongType(other)),
+        }
+    }
+}
+impl TryFrom<FeatureVal> for i32 {
+    type Error = WrongType;
+    fn try_from(val: FeatureVal) -> Result<i32, WrongType> {
+        val.as_int().ok_or(WrongType(val))
+    }
+}
+impl TryFrom<FeatureVal> for f32 {
+    type Error = WrongType;
+    fn try_from(val: FeatureVal) -> Result<f32, WrongType> {
+        val.as_float().ok_or(WrongType(val))
+    }
+}
+
+impl Serialize for FeatureVal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            FeatureVal::Str(s) => serializer.serialize_str(s),
+            FeatureVal::Int(i) => serializer.serialize_i32(*i),
+            FeatureVal::Float(f) => serializer.serialize_f32(*f),
+        }
+    }
+}
+
+struct FeatureValVisitor;
+impl<'de> Visitor<'de> for FeatureValVisitor {
+    type Value = FeatureVal;
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("a string, int or float feature value")
+    }
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<FeatureVal, E> {
+        Ok(FeatureVal::parse(s))
+    }
+    fn visit_i64<E: de::Error>(self, i: i64) -> Result<FeatureVal, E> {
+        i32::try_from(i)
+            .map(FeatureVal::Int)
+            .map_err(|_| E::custom(format!("feature value {i} does not fit in an i32")))
+    }
+    fn visit_u64<E: de::Error>(self, u: u64) -> Result<FeatureVal, E> {
+        i32::try_from(u)
+            .map(FeatureVal::Int)
+            .map_err(|_| E::custom(format!("feature value {u} does not fit in an i32")))
+    }
+    fn visit_f64<E: de::Error>(self, f: f64) -> Result<FeatureVal, E> {
+        Ok(FeatureVal::Float(f as f32))
+    }
+}
+impl<'de> Deserialize<'de> for FeatureVal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<FeatureVal, D::Error> {
+        deserializer.deserialize_any(FeatureValVisitor)
+    }
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(FeatureVal::parse("30"), FeatureVal::Int(30));
+    assert_eq!(FeatureVal::parse("-0.5"), FeatureVal::Float(-0.5));
+    assert_eq!(FeatureVal::parse("eng"), FeatureVal::Str("eng".to_string()));
+    assert_eq!(FeatureVal::parse("nan"), FeatureVal::Str("nan".to_string()));
+    assert_eq!(FeatureVal::parse("30").to_string(), "30");
+    assert_eq!(i32::try_from(FeatureVal::Int(30)), Ok(30));
+    assert_eq!(f32::try_from(FeatureVal::Int(30)), Ok(30.0));
+    assert_eq!(
+        i32::try_from(FeatureVal::Float(0.5)),
+        Err(WrongType(FeatureVal::Float(0.5)))
+    );
+    assert!(String::try_from(FeatureVal::Int(30)).is_err());
+}
+
+#[test]
+fn test_deserialize() {
+    use crate::FLITEVOX_MAGIC;
+    use std::collections::BTreeMap;
+
+    let mut data = FLITEVOX_MAGIC.to_vec();
+    data.extend_from_slice(&1u32.to_le_bytes());
+    for s in ["age", "30", "mlsa_alpha", "0.42", "language", "eng"] {
+        data.extend_from_slice(&(s.len() as u32 + 1).to_le_bytes());
+        data.extend_from_slice(s.as_bytes());
+        data.push(0);
+    }
+    let features: BTreeMap<String, FeatureVal> = crate::de::from_bytes(&data).unwrap();
+    assert_eq!(features["age"], FeatureVal::Int(30));
+    assert_eq!(features["mlsa_alpha"], FeatureVal::Float(0.42));
+    assert_eq!(features["language"], FeatureVal::Str("eng".to_string()));
+}
@@ -0,0 +1,427 @@
+//! A parsed voice laid out as flite's own `cst_cg_db`.
+//!
+//! [`CgDb`] builds the C structures flite's clustergen synthesizer reads from a
+//! [`CgModel`], the same ones [`codegen::c_source`](crate::codegen::c_source) writes out as
+//! source. It owns every array it points to, so a voice parsed in Rust can be handed to libflite,
+//! as the `cg_db` feature of a voice through flite's `cg_db_val`, without a file in between.
+//! `freeable` is 0, so flite never frees it; the [`CgDb`] must outlive the voice using it.
+//!
+//! The layouts follow `cst_cg.h`, `cst_cart.h` and `cst_val.h` of flite 2.x, including the
+//! atom layout `cst_val.h` picks on x86-64. Voices using spamf0 lose those models.
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::ffi::{CString, NulError};
+use alloc::vec::Vec;
+use core::any::Any;
+use core::ffi::{c_char, c_double, c_float, c_int, c_void};
+use core::ptr;
+
+use crate::model::{Cart, CgModel, Value};
+
+// Value type tags of `cst_val.h`.
+const CST_VAL_TYPE_INT: Tag = 1;
+const CST_VAL_TYPE_FLOAT: Tag = 3;
+const CST_VAL_TYPE_STRING: Tag = 5;
+// The reference count of flite's static values, which are never freed.
+const STATIC_REF_COUNT: Tag = -1;
+// A node which ends the rule table of a cart.
+const CART_END: CstCartNode = CstCartNode {
+    feat: 255,
+    op: 0,
+    no_node: 0,
+    val: ptr::null(),
+};
+
+#[cfg(target_arch = "x86_64")]
+type Tag = c_int;
+#[cfg(not(target_arch = "x86_64"))]
+type Tag = core::ffi::c_short;
+
+/// The value of a [`CstVal`]; a string is a `const char *` in `vval`.
+#[cfg(target_arch = "x86_64")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union AtomValue {
+    pub fval: c_double,
+    pub ival: core::ffi::c_longlong,
+    pub vval: *mut c_void,
+}
+/// The value of a [`CstVal`]; a string is a `const char *` in `vval`.
+#[cfg(not(target_arch = "x86_64"))]
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union AtomValue {
+    pub fval: c_float,
+    pub ival: c_int,
+    pub vval: *mut c_void,
+}
+
+/// flite's `cst_val`, as an atom: an int, a float or a string.
+#[repr(C)]
+pub struct CstVal {
+    #[cfg(target_endian = "little")]
+    pub ty: Tag,
+    pub ref_count: Tag,
+    #[cfg(target_endian = "big")]
+    pub ty: Tag,
+    pub v: AtomValue,
+}
+
+#[repr(C)]
+pub struct CstCartNode {
+    pub feat: u8,
+    pub op: u8,
+    pub no_node: u16,
+    pub val: *const CstVal,
+}
+
+#[repr(C)]
+pub struct CstCart {
+    /// Ends with a node whose `feat` is 255 and whose `val` is null.
+    pub rule_table: *const CstCartNode,
+    /// Null terminated.
+    pub feat_table: *const *const c_char,
+}
+
+#[repr(C)]
+pub struct CstDurStat {
+    pub phone: *const c_char,
+    pub mean: c_float,
+    pub stddev: c_float,
+}
+
+/// flite's `cst_cg_db`. Lists of pointers are null terminated, as in flite.
+#[repr(C)]
+pub struct CstCgDb {
+    pub name: *const c_char,
+    pub types: *const *const c_char,
+    pub num_types: c_int,
+    pub sample_rate: c_int,
+    pub f0_mean: c_float,
+    pub f0_stddev: c_float,
+    pub num_f0_models: c_int,
+    pub f0_trees: *const *const *const CstCart,
+    pub num_param_models: c_int,
+    pub param_trees: *const *const *const CstCart,
+    pub spamf0_accent_tree: *const CstCart,
+    pub spamf0_phrase_tree: *const CstCart,
+    pub num_channels: *mut c_int,
+    pub num_frames: *mut c_int,
+    pub model_vectors: *const *const *const u16,
+    pub num_channels_spamf0_accent: c_int,
+    pub num_frames_spamf0_accent: c_int,
+    pub spamf0_accent_vectors: *const *const c_float,
+    pub model_min: *const c_float,
+    pub model_range: *const c_float,
+    pub qtable: *const *const *const c_float,
+    pub model_shape: c_int,
+    pub frame_advance: c_float,
+    pub num_dur_models: c_int,
+    pub dur_stats: *const *const *const CstDurStat,
+    pub dur_cart: *const *const CstCart,
+    pub phone_states: *const *const *const c_char,
+    pub do_mlpg: c_int,
+    pub dynwin: *mut c_float,
+    pub dynwinsize: c_int,
+    pub mlsa_alpha: c_float,
+    pub mlsa_beta: c_float,
+    pub multimodel: c_int,
+    pub mixed_excitation: c_int,
+    pub me_num: c_int,
+    pub me_order: c_int,
+    pub me_h: *const *const c_double,
+    pub spamf0: c_int,
+    pub gain: c_float,
+    /// Always 0: the arrays belong to the [`CgDb`].
+    pub freeable: c_int,
+}
+
+/// Keeps alive everything the structures point to. Moving a `Vec` or a `CString` doesn't move
+/// its heap buffer, so the pointers stay valid however the arena moves.
+#[derive(Default)]
+struct Arena {
+    kept: Vec<Box<dyn Any>>,
+}
+impl Arena {
+    fn keep<T: 'static>(&mut self, values: Vec<T>) -> *const T {
+        let ptr = values.as_ptr();
+        self.kept.push(Box::new(values));
+        ptr
+    }
+    /// `pointers`, followed by a null pointer.
+    fn terminated<T: 'static>(&mut self, mut pointers: Vec<*const T>) -> *const *const T {
+        pointers.push(ptr::null());
+        self.keep(pointers)
+    }
+    fn str(&mut self, s: &str) -> Result<*const c_char, NulError> {
+        let s = CString::new(s)?;
+        let ptr = s.as_ptr();
+        self.kept.push(Box::new(s));
+        Ok(ptr)
+    }
+    fn strs<'s>(
+        &mut self,
+        strs: impl IntoIterator<Item = &'s String>,
+    ) -> Result<*const *const c_char, NulError> {
+        let pointers = strs
+            .into_iter()
+            .map(|s| self.str(s))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self.terminated(pointers))
+    }
+    fn val(&mut self, value: &Value) -> Result<CstVal, NulError> {
+        let (ty, v) = match value {
+            Value::Int(i) => (CST_VAL_TYPE_INT, AtomValue { ival: (*i).into() }),
+            Value::Float(f) => (CST_VAL_TYPE_FLOAT, AtomValue { fval: (*f).into() }),
+            Value::Str(s) => {
+                let vval = self.str(s)?.cast_mut().cast();
+                (CST_VAL_TYPE_STRING, AtomValue { vval })
+            }
+        };
+        Ok(CstVal {
+            ty,
+            ref_count: STATIC_REF_COUNT,
+            v,
+        })
+    }
+    fn cart(&mut self, cart: &Cart) -> Result<*const CstCart, NulError> {
+        let vals = cart
+            .nodes
+            .iter()
+            .map(|node| self.val(&node.value))
+            .collect::<Result<Vec<_>, _>>()?;
+        let vals = self.keep(vals);
+        let mut nodes: Vec<CstCartNode> = cart
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| CstCartNode {
+                feat: node.feature,
+                op: node.op.into(),
+                no_node: node.no_node,
+                val: vals.wrapping_add(i),
+            })
+            .collect();
+        nodes.push(CART_END);
+        let cart = CstCart {
+            rule_table: self.keep(nodes),
+            feat_table: self.strs(&cart.features)?,
+        };
+        Ok(self.keep(alloc::vec![cart]))
+    }
+    /// Trees per model, as `const cst_cart ***`.
+    fn carts(&mut self, models: &[Vec<Cart>]) -> Result<*const *const *const CstCart, NulError> {
+        let mut tables = Vec::new();
+        for carts in models {
+            let carts = carts
+                .iter()
+                .map(|cart| self.cart(cart))
+                .collect::<Result<Vec<_>, _>>()?;
+            tables.push(self.terminated(carts));
+        }
+        Ok(self.terminated(tables))
+    }
+    /// Rows of `f32`s, or null if there are none.
+    fn table(&mut self, rows: &[Vec<f32>]) -> *const *const c_float {
+        if rows.is_empty() {
+            return ptr::null();
+        }
+        let rows: Vec<_> = rows.iter().map(|row| self.keep(row.clone())).collect();
+        self.terminated(rows)
+    }
+}
+
+/// A [`CstCgDb`] and everything it points to.
+pub struct CgDb {
+    db: Box<CstCgDb>,
+    _arena: Arena,
+}
+impl CgDb {
+    /// Lay out `model` for flite. Fails if a string of the model has a null byte, which a C
+    /// string can't hold.
+    pub fn new(model: &CgModel) -> Result<CgDb, NulError> {
+        let mut arena = Arena::default();
+        let a = &mut arena;
+
+        let mut model_vectors = Vec::new();
+        for params in &model.param_models {
+            let frame_len = params.frames.row(0).map_or(0, |row| row.0.len());
+            // quantized shapes store byte indices, which flite reads through the same pointers
+            let rows: Vec<*const u16> = if frame_len == params.num_channels * 2 {
+                let cells: Vec<u16> = params
+                    .frames
+                    .as_bytes()
+                    .chunks_exact(2)
+                    .map(|cell| u16::from_le_bytes([cell[0], cell[1]]))
+                    .collect();
+                let cells = a.keep(cells);
+                let rows = (0..params.frames.len()).map(|i| cells.wrapping_add(i * frame_len / 2));
+                rows.collect()
+            } else {
+                let bytes = a.keep(params.frames.as_bytes().to_vec());
+                let rows = (0..params.frames.len()).map(|i| bytes.wrapping_add(i * frame_len));
+                rows.map(<*const u8>::cast::<u16>).collect()
+            };
+            model_vectors.push(a.terminated(rows));
+        }
+        let num_channels = model.param_models.iter().map(|p| p.num_channels as c_int);
+        let num_frames = model.param_models.iter().map(|p| p.frames.len() as c_int);
+
+        let quantization = &model.quantization;
+        let qtable = match quantization.qtables.is_empty() {
+            true => ptr::null(),
+            false => {
+                let tables: Vec<_> = quantization.qtables.iter().map(|t| a.table(t)).collect();
+                a.terminated(tables)
+            }
+        };
+
+        let mut dur_stats = Vec::new();
+        let mut dur_cart = Vec::new();
+        for dur in &model.dur_models {
+            let stats = dur
+                .stats
+                .iter()
+                .map(|stat| {
+                    Ok::<_, NulError>(CstDurStat {
+                        phone: a.str(&stat.phone)?,
+                        mean: stat.mean,
+                        stddev: stat.stddev,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let len = stats.len();
+            let stats = a.keep(stats);
+            let stats: Vec<_> = (0..len).map(|i| stats.wrapping_add(i)).collect();
+            dur_stats.push(a.terminated(stats));
+            dur_cart.push(a.cart(&dur.tree)?);
+        }
+
+        // flite lists the phone first, then its states
+        let phone_states = model
+            .phone_states
+            .phones
+            .iter()
+            .map(|phone| a.strs(core::iter::once(&phone.phone).chain(&phone.states)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let synthesis = &model.synthesis;
+        let dynwin = match synthesis.dynwin.is_empty() {
+            true => ptr::null(),
+            false => a.keep(synthesis.dynwin.clone()),
+        };
+        let me_h = match synthesis.me_filters.is_empty() {
+            true => ptr::null(),
+            false => {
+                let filters: Vec<_> = synthesis
+                    .me_filters
+                    .iter()
+                    .map(|filter| a.keep(filter.clone()))
+                    .collect();
+                a.terminated(filters)
+            }
+        };
+
+        let db = CstCgDb {
+            name: a.str(&model.name)?,
+            types: a.strs(&model.state_names)?,
+            num_types: model.state_names.len() as c_int,
+            sample_rate: model.sample_rate as c_int,
+            f0_mean: model.f0_mean,
+            f0_stddev: model.f0_stddev,
+            num_f0_models: model.f0_trees.len() as c_int,
+            f0_trees: a.carts(&model.f0_trees)?,
+            num_param_models: model.param_models.len() as c_int,
+            param_trees: a.carts(&model.param_trees)?,
+            spamf0_accent_tree: ptr::null(),
+            spamf0_phrase_tree: ptr::null(),
+            num_channels: a.keep(num_channels.collect()).cast_mut(),
+            num_frames: a.keep(num_frames.collect()).cast_mut(),
+            model_vectors: a.terminated(model_vectors),
+            num_channels_spamf0_accent: 0,
+            num_frames_spamf0_accent: 0,
+            spamf0_accent_vectors: ptr::null(),
+            model_min: a.keep(quantization.model_min.clone()),
+            model_range: a.keep(quantization.model_range.clone()),
+            qtable,
+            model_shape: quantization.model_shape as c_int,
+            frame_advance: model.frame_advance,
+            num_dur_models: model.dur_models.len() as c_int,
+            dur_stats: a.terminated(dur_stats),
+            dur_cart: a.terminated(dur_cart),
+            phone_states: a.terminated(phone_states),
+            do_mlpg: synthesis.do_mlpg.into(),
+            dynwin: dynwin.cast_mut(),
+            dynwinsize: synthesis.dynwin.len() as c_int,
+            mlsa_alpha: synthesis.mlsa_alpha,
+            mlsa_beta: synthesis.mlsa_beta,
+            multimodel: synthesis.multimodel.into(),
+            mixed_excitation: synthesis.mixed_excitation.into(),
+            me_num: synthesis.me_filters.len() as c_int,
+            me_order: synthesis.me_filters.first().map_or(0, Vec::len) as c_int,
+            me_h,
+            spamf0: 0,
+            gain: synthesis.gain,
+            freeable: 0,
+        };
+        Ok(CgDb {
+            db: Box::new(db),
+            _arena: arena,
+        })
+    }
+    pub fn get(&self) -> &CstCgDb {
+        &self.db
+    }
+    /// The `cst_cg_db *` to hand to flite. Valid for as long as `self` is.
+    pub fn as_ptr(&self) -> *const CstCgDb {
+        &*self.db
+    }
+    pub fn as_mut_ptr(&mut self) -> *mut CstCgDb {
+        &mut *self.db
+    }
+}
+
+#[test]
+fn test_cg_db() {
+    use core::ffi::CStr;
+
+    let voice = crate::de::from_bytes(include_bytes!("../data/cmu_us_slt.flitevox")).unwrap();
+    let model = CgModel::from_voice(&voice);
+    let db = CgDb::new(&model).unwrap();
+    // moving the owner leaves the pointers valid
+    let db = Box::new(db);
+    let c = db.get();
+    let text = |s: *const c_char| unsafe { CStr::from_ptr(s) }.to_str().unwrap();
+    assert_eq!(text(c.name), "cmu_us_slt");
+    assert_eq!(c.num_types as usize, model.state_names.len());
+    assert_eq!(c.sample_rate, 16000);
+    unsafe {
+        assert_eq!(text(*c.types), model.state_names[0]);
+        assert!((*c.types.add(model.state_names.len())).is_null());
+
+        let cart = &**(*c.f0_trees);
+        let node = &*cart.rule_table;
+        let expected = &model.f0_trees[0][0].nodes[0];
+        assert_eq!(node.feat, expected.feature);
+        assert_eq!(node.no_node, expected.no_node);
+        let nodes = model.f0_trees[0][0].nodes.len();
+        assert_eq!((*cart.rule_table.add(nodes)).feat, 255);
+        assert_eq!(text(*cart.feat_table), model.f0_trees[0][0].features[0]);
+
+        let stat = &**(*c.dur_stats);
+        assert_eq!(text(stat.phone), "pau");
+        assert_eq!(*c.num_channels, model.param_models[0].num_channels as c_int);
+        assert_eq!(*c.num_frames.add(1), 30135);
+        let states = *c.phone_states;
+        assert_eq!([text(*states), text(*states.add(1))], ["aa", "aa_1"]);
+        assert!((*c.phone_states.add(model.phone_states.phones.len())).is_null());
+    }
+    assert_eq!(c.freeable, 0);
+    assert!(CgDb::new(&CgModel {
+        name: "nul\0".into(),
+        ..model
+    })
+    .is_err());
+}
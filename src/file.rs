@@ -0,0 +1,52 @@
+//! Reading and writing voice files.
+//!
+//! [`read_voice`] and [`write_voice`] do what every user of the crate would otherwise write
+//! around [`from_bytes`](crate::de::from_bytes) and [`to_bytes`](crate::ser::to_bytes).
+//! [`read_voice_header`] streams the file and stops after the name of the voice, so listing
+//! voices doesn't read their models.
+
+extern crate std;
+
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::voice::TreeDb;
+use crate::Header;
+
+/// Read a whole voice. Its integrity trailer, if it has one, is verified.
+pub fn read_voice(path: impl AsRef<Path>) -> Result<TreeDb> {
+    let data = fs::read(path).map_err(Error::Io)?;
+    crate::de::from_bytes(&data)
+}
+
+/// Read only the header of a voice.
+pub fn read_voice_header(path: impl AsRef<Path>) -> Result<Header> {
+    let file = File::open(path).map_err(Error::Io)?;
+    crate::de::from_reader(BufReader::new(file))
+}
+
+/// Write `voice` to `path`, replacing the file if there is one. Nothing is written if the
+/// voice can't be serialized.
+pub fn write_voice(path: impl AsRef<Path>, voice: &TreeDb) -> Result<()> {
+    let data = crate::ser::to_bytes(voice)?;
+    fs::write(path, data).map_err(Error::Io)
+}
+
+#[test]
+fn test_read_write_voice() {
+    let path = std::env::temp_dir().join(format!("serde_cst_{}.flitevox", std::process::id()));
+    let voice = read_voice(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/data/cmu_us_slt.flitevox"
+    ))
+    .unwrap();
+    write_voice(&path, &voice).unwrap();
+    assert_eq!(read_voice(&path).unwrap(), voice);
+    assert_eq!(read_voice_header(&path).unwrap(), voice.header);
+    fs::remove_file(&path).unwrap();
+
+    let err = read_voice(&path).unwrap_err();
+    assert!(matches!(err, Error::Io(ref err) if err.kind() == std::io::ErrorKind::NotFound));
+}
@@ -1,14 +1,14 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug, PartialEq, Default)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum Gender {
     Male,
     Female,
     #[default]
-    #[serde(alias = "none")]
-    // TODO: make Option<Gender>
     Unknown,
+    /// What some voices write instead of `unknown`; kept apart so it is written back as it was.
+    None,
 }
 
 impl core::str::FromStr for Gender {
@@ -17,8 +17,20 @@ impl core::str::FromStr for Gender {
         match s {
             "male" => Ok(Gender::Male),
             "female" => Ok(Gender::Female),
-            "unknown" | "none" => Ok(Gender::Unknown),
+            "unknown" => Ok(Gender::Unknown),
+            "none" => Ok(Gender::None),
             _ => Err("invalid variant for gender"),
         }
     }
 }
+
+impl core::fmt::Display for Gender {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Gender::Male => "male",
+            Gender::Female => "female",
+            Gender::Unknown => "unknown",
+            Gender::None => "none",
+        })
+    }
+}
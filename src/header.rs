@@ -1,73 +1,547 @@
 //! Types required to be used when reading CST files.
 
-use crate::Gender;
-use serde::Deserialize;
-use serde_with::{serde_as, DisplayFromStr};
+extern crate alloc;
 
-#[derive(Deserialize, Debug, PartialEq)]
-#[serde(untagged)]
+use core::fmt;
+
+use crate::header_ref::{
+    DEFAULT_MODEL_SHAPE, DEFAULT_NUM_DUR_MODELS, DEFAULT_NUM_F0_MODELS, DEFAULT_NUM_PARAM_MODELS,
+    FEATURE_KEYS,
+};
+use crate::{BuildDate, FormatVersion, Gender};
+use serde::de::value::MapDeserializer;
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DeserializeFromStr, DisplayFromStr, SerializeDisplay};
+
+/// The `language` feature of a voice, as the languages flite ships write it.
+#[derive(SerializeDisplay, DeserializeFromStr, Debug, Clone, PartialEq, Eq)]
 pub enum Language {
-    #[serde(rename = "eng")]
+    /// `eng`
     English,
+    /// `ind_ben`
+    Bengali,
+    /// `ind_guj`
+    Gujarati,
+    /// `ind_hin`
+    Hindi,
+    /// `ind_kan`
+    Kannada,
+    /// `ind_mar`
+    Marathi,
+    /// `ind_pan`
+    Punjabi,
+    /// `ind_tam`
+    Tamil,
+    /// `ind_tel`
+    Telugu,
+    /// Any other code, kept as it is written.
+    Other(String),
+}
+
+// Every language but `Other`, by code.
+const LANGUAGES: &[(&str, Language)] = &[
+    ("eng", Language::English),
+    ("ind_ben", Language::Bengali),
+    ("ind_guj", Language::Gujarati),
+    ("ind_hin", Language::Hindi),
+    ("ind_kan", Language::Kannada),
+    ("ind_mar", Language::Marathi),
+    ("ind_pan", Language::Punjabi),
+    ("ind_tam", Language::Tamil),
+    ("ind_tel", Language::Telugu),
+];
+
+impl Language {
+    /// The code the voice stores.
+    pub fn code(&self) -> &str {
+        match self {
+            Language::Other(code) => code,
+            known => LANGUAGES
+                .iter()
+                .find(|(_, language)| language == known)
+                .map_or("", |(code, _)| code),
+        }
+    }
+}
+impl core::str::FromStr for Language {
+    type Err = core::convert::Infallible;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(LANGUAGES
+            .iter()
+            .find(|(code, _)| *code == s)
+            .map_or_else(|| Language::Other(s.to_string()), |(_, l)| l.clone()))
+    }
+}
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum EndOfFeatures {
     EndOfFeatures,
 }
 
 #[serde_as]
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct Features {
     pub language: String,
     pub country: String,
+    #[serde(default = "unknown")]
     pub variant: String,
     #[serde_as(as = "DisplayFromStr")]
     pub age: u32,
     #[serde_as(as = "DisplayFromStr")]
     pub gender: Gender,
-    #[serde(with = "crate::date")]
-    pub build_date: chrono::NaiveDateTime,
+    pub build_date: BuildDate,
+    #[serde(default = "unknown")]
     pub description: String,
     #[serde_as(as = "DisplayFromStr")]
     pub eng_shared: u32,
+    #[serde(default = "unknown")]
     pub copyright: String,
     #[serde_as(as = "DisplayFromStr")]
+    #[serde(default = "default_num_dur_models")]
     pub num_dur_models: u32,
     #[serde_as(as = "DisplayFromStr")]
+    #[serde(default = "default_num_param_models")]
     pub num_param_models: u32,
     #[serde_as(as = "DisplayFromStr")]
+    #[serde(default = "default_model_shape")]
     pub model_shape: u32,
     #[serde_as(as = "DisplayFromStr")]
+    #[serde(default = "default_num_f0_models")]
     pub num_f0_models: u32,
     pub end_of_features: EndOfFeatures,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+impl Features {
+    /// The `language` feature, parsed.
+    pub fn language(&self) -> Language {
+        self.language.parse().unwrap_or_else(|never| match never {})
+    }
+    /// Every feature but the end marker as it is stored: its key, and its value as a string.
+    pub fn pairs(&self) -> [(&'static str, String); 13] {
+        [
+            ("language", self.language.clone()),
+            ("country", self.country.clone()),
+            ("variant", self.variant.clone()),
+            ("age", self.age.to_string()),
+            ("gender", self.gender.to_string()),
+            ("build_date", self.build_date.to_string()),
+            ("description", self.description.clone()),
+            ("eng_shared", self.eng_shared.to_string()),
+            ("copyright", self.copyright.clone()),
+            ("num_dur_models", self.num_dur_models.to_string()),
+            ("num_param_models", self.num_param_models.to_string()),
+            ("model_shape", self.model_shape.to_string()),
+            ("num_f0_models", self.num_f0_models.to_string()),
+        ]
+    }
+}
+
+/// A feature block which keeps the features [`Features`] has no field for, as festvox adds
+/// custom ones to some voices. Read the header of such a voice as `(FeatureMap, String)` to keep
+/// them; [`Header`] drops them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureMap {
+    pub features: Features,
+    /// Every unknown feature, in the order they were read. They are written after the known
+    /// ones.
+    pub extra: Vec<(String, String)>,
+}
+
+// Every feature of a block in the order it is stored, the end marker included. Neither a known
+// nor an unknown feature may appear twice.
+#[derive(Clone)]
+struct FeaturePairs(Vec<(String, String)>);
+impl FeaturePairs {
+    fn into_map<E: de::Error>(self) -> Result<FeatureMap, E> {
+        let (known, extra): (Vec<_>, Vec<_>) = self
+            .0
+            .into_iter()
+            .partition(|(key, _)| FEATURE_KEYS.contains(&key.as_str()));
+        let known = MapDeserializer::<_, E>::new(known.into_iter());
+        let features = Features::deserialize(known)?;
+        Ok(FeatureMap { features, extra })
+    }
+    // The pairs with the values of `features`, in the order they were read. Features the block
+    // did not have go before its end.
+    fn patch(mut self, features: &Features) -> FeaturePairs {
+        for (key, value) in features.pairs() {
+            match self.0.iter_mut().find(|(k, _)| k == key) {
+                Some((_, old)) => *old = value,
+                None => {
+                    let end = self.0.iter().position(|(k, _)| k == "end_of_features");
+                    let end = end.unwrap_or(self.0.len());
+                    self.0.insert(end, (key.to_string(), value));
+                }
+            }
+        }
+        self
+    }
+}
+
+struct FeaturePairsVisitor;
+impl<'de> Visitor<'de> for FeaturePairsVisitor {
+    type Value = FeaturePairs;
+    fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("A feature block")
+    }
+    fn visit_map<A>(self, mut map: A) -> Result<FeaturePairs, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut pairs: Vec<(String, String)> = Vec::new();
+        while let Some((key, value)) = map.next_entry::<String, String>()? {
+            // repeated known features are caught with their offset as they are read
+            if pairs.iter().any(|(k, _)| *k == key) {
+                return Err(de::Error::custom(format_args!(
+                    "feature {key} appears twice"
+                )));
+            }
+            pairs.push((key, value));
+        }
+        Ok(FeaturePairs(pairs))
+    }
+}
+impl<'de> Deserialize<'de> for FeaturePairs {
+    fn deserialize<D>(deserializer: D) -> Result<FeaturePairs, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("FeatureMap", FEATURE_KEYS, FeaturePairsVisitor)
+    }
+}
+impl Serialize for FeaturePairs {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (key, value) in &self.0 {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for FeatureMap {
+    fn deserialize<D>(deserializer: D) -> Result<FeatureMap, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        FeaturePairs::deserialize(deserializer)?.into_map()
+    }
+}
+impl Serialize for FeatureMap {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        for (key, value) in self.features.pairs() {
+            map.serialize_entry(key, &value)?;
+        }
+        for (key, value) in &self.extra {
+            map.serialize_entry(key, value)?;
+        }
+        map.serialize_entry("end_of_features", &self.features.end_of_features)?;
+        map.end()
+    }
+}
+
+// The value of the optional features festvox leaves out of some voices.
+fn unknown() -> String {
+    "unknown".to_string()
+}
+// The counts flite assumes for v1 voices, which were built before the count features existed.
+fn default_num_dur_models() -> u32 {
+    DEFAULT_NUM_DUR_MODELS
+}
+fn default_num_param_models() -> u32 {
+    DEFAULT_NUM_PARAM_MODELS
+}
+fn default_model_shape() -> u32 {
+    DEFAULT_MODEL_SHAPE
+}
+fn default_num_f0_models() -> u32 {
+    DEFAULT_NUM_F0_MODELS
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 // "Why not deserialize Header directly?"
 // https://github.com/serde-rs/serde/issues/1803
 // basically, the named fields (even if flattened) cause Serde to ask for the `Content` (private
 // serde) type, and it needs to use `deserialize_any`, which this format does not support
 #[serde(from = "_Header", into = "_Header")]
 pub struct Header {
+    /// The version in the magic of the voice.
+    pub version: FormatVersion,
     pub features: Features,
     pub name: String,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
-struct _Header(pub Features, pub String);
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct _Header(pub FormatVersion, pub Features, pub String);
 impl From<Header> for _Header {
     fn from(head: Header) -> _Header {
-        _Header(head.features, head.name)
+        _Header(head.version, head.features, head.name)
     }
 }
 impl From<_Header> for Header {
     fn from(head: _Header) -> Header {
         Header {
-            features: head.0,
-            name: head.1,
+            version: head.0,
+            features: head.1,
+            name: head.2,
         }
     }
 }
 
+impl Header {
+    /// Edit the header of the voice in `voice`, keeping its body as it is: only the header is
+    /// rewritten, so fixing a copyright string doesn't decode and re-encode megabytes of models.
+    /// Features [`Features`] has no field for, the order of the features, the byte order and the
+    /// version are kept, and the integrity and digest trailers are recomputed, the latter with the
+    /// same algorithm.
+    pub fn patch_in_place<F>(voice: &mut Vec<u8>, edit: F) -> Result<(), crate::error::Error>
+    where
+        F: FnOnce(&mut Header),
+    {
+        use crate::{integrity, trailer};
+
+        let (data, trailers) = trailer::strip_trailers(voice);
+        trailers.verify()?;
+        let (has_trailer, digests) = (trailers.has_crc(), trailers.digests());
+        let mut de = crate::de::Deserializer::from_bytes(data);
+        let (version, pairs, name) = <(FormatVersion, FeaturePairs, String)>::deserialize(&mut de)
+            .map_err(|e| de.locate_in(e, data))?;
+        let (header_len, body_end) = (data.len() - de.remaining(), data.len());
+        let mut serializer = match de.is_byteswapped() {
+            Some(true) => crate::ser::Serializer::big_endian(),
+            _ => crate::ser::Serializer::new(),
+        };
+
+        let mut header = Header {
+            version,
+            features: pairs.clone().into_map::<crate::error::Error>()?.features,
+            name,
+        };
+        edit(&mut header);
+        let pairs = pairs.patch(&header.features);
+        (header.version, pairs, header.name).serialize(&mut serializer)?;
+        voice.truncate(body_end);
+        voice.splice(..header_len, serializer.into_inner());
+        if let Some(algorithm) = digests {
+            integrity::append_digests(voice, algorithm)?;
+        }
+        if has_trailer {
+            trailer::append_trailer(voice);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_feature_map() {
+    use crate::de::from_bytes;
+    use crate::ser::to_bytes;
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let header: Header = from_bytes(data).unwrap();
+    let mut data = data[..394].to_vec();
+    let end = data.len() - 55;
+    assert_eq!(data[end..end + 20], *b"\x10\0\0\0end_of_features\0");
+    let custom = b"\x0e\0\0\0festvox_phset\0\x07\0\0\0radio2\0";
+    data.splice(end..end, custom.iter().copied());
+
+    let (map, name): (FeatureMap, String) = from_bytes(&data).unwrap();
+    assert_eq!(name, "cmu_us_slt");
+    assert_eq!(map.features, header.features);
+    assert_eq!(
+        map.extra,
+        [("festvox_phset".to_string(), "radio2".to_string())]
+    );
+    assert_eq!(to_bytes(&(&map, &name)).unwrap(), data);
+
+    // without a place for them, unknown features are skipped
+    assert_eq!(from_bytes::<Header>(&data).unwrap(), header);
+
+    // kept in the order they were read
+    let second = b"\x06\0\0\0aaaaa\0\x02\0\0\0b\0";
+    let mut two = data.clone();
+    two.splice(
+        end + custom.len()..end + custom.len(),
+        second.iter().copied(),
+    );
+    let (map, _): (FeatureMap, String) = from_bytes(&two).unwrap();
+    let keys: Vec<_> = map.extra.iter().map(|(key, _)| key.as_str()).collect();
+    assert_eq!(keys, ["festvox_phset", "aaaaa"]);
+    assert_eq!(to_bytes(&(&map, &name)).unwrap(), two);
+
+    // an unknown feature may not repeat, nor a known one
+    let mut repeated = data.clone();
+    repeated.splice(end..end, custom.iter().copied());
+    let err = from_bytes::<(FeatureMap, String)>(&repeated).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("feature festvox_phset appears twice"));
+    let age = b"\x04\0\0\0age\0\x03\0\0\x0031\0";
+    let mut repeated = data.clone();
+    repeated.splice(end..end, age.iter().copied());
+    let err = from_bytes::<(FeatureMap, String)>(&repeated).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        crate::error::Error::DuplicateFeature("age", at) if *at == end
+    ));
+    let mut voice = repeated;
+    assert!(Header::patch_in_place(&mut voice, |_| {}).is_err());
+}
+
+#[test]
+fn test_v1_header() {
+    use crate::de::from_bytes;
+    use crate::ser::to_bytes;
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let mut data = data[..394].to_vec();
+    assert_eq!(
+        to_bytes(&from_bytes::<Header>(&data).unwrap()).unwrap(),
+        data
+    );
+
+    // a v1.5 voice, from before num_f0_models was written
+    data[..26].copy_from_slice(&FormatVersion::new(1, 5).unwrap().magic());
+    let key = data
+        .windows(14)
+        .position(|w| w == b"num_f0_models\0")
+        .unwrap();
+    data.drain(key - 4..key + 20);
+    let header: Header = from_bytes(&data).unwrap();
+    assert_eq!(header.version, FormatVersion::new(1, 5).unwrap());
+    assert_eq!(header.features.num_f0_models, 1);
+    assert_eq!(header.features.num_param_models, 3);
+    assert_eq!(header.name, "cmu_us_slt");
+
+    let written = to_bytes(&header).unwrap();
+    assert_eq!(written[..26], *b"CMU_FLITE_CG_VOXDATA-v1.5\0");
+    assert_eq!(from_bytes::<Header>(&written).unwrap(), header);
+}
+
+#[test]
+fn test_patch_in_place() {
+    use crate::de::from_bytes;
+    use crate::integrity;
+    use crate::voice::TreeDb;
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let mut voice = data.to_vec();
+    crate::trailer::append_trailer(&mut voice);
+    Header::patch_in_place(&mut voice, |header| {
+        header.features.copyright = "Carnegie Mellon University".to_string();
+        header.features.age = 31;
+    })
+    .unwrap();
+
+    // 19 more bytes of copyright, none of age, and the trailer
+    assert_eq!(voice.len(), data.len() + 19 + 12);
+    assert_eq!(voice[394 + 19..voice.len() - 12], data[394..]);
+    let patched: TreeDb = from_bytes(&voice).unwrap();
+    let original: TreeDb = from_bytes(data).unwrap();
+    assert_eq!(
+        patched.header.features.copyright,
+        "Carnegie Mellon University"
+    );
+    assert_eq!(patched.header.features.age, 31);
+    assert_eq!(patched.header.name, original.header.name);
+    assert_eq!(patched.body, original.body);
+
+    // a digest trailer is recomputed with the algorithm it had
+    let mut voice = data.to_vec();
+    integrity::append_digests(&mut voice, integrity::Algorithm::XxHash32).unwrap();
+    crate::trailer::append_trailer(&mut voice);
+    Header::patch_in_place(&mut voice, |header| header.features.age = 31).unwrap();
+    let (digested, trailers) = crate::trailer::strip_trailers(&voice);
+    assert!(trailers.verify().is_ok());
+    assert_eq!(trailers.digests(), Some(integrity::Algorithm::XxHash32));
+    assert_eq!(digested.len(), data.len());
+    let patched: TreeDb = from_bytes(&voice).unwrap();
+    assert_eq!(patched.header.features.age, 31);
+    assert_eq!(patched.body, original.body);
+
+    assert!(Header::patch_in_place(&mut b"RIFF".to_vec(), |_| {}).is_err());
+}
+
+#[test]
+fn test_patch_keeps_build_date() {
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let stored = b"\x11\0\0\x002017-09-14_23:37\0";
+    let at = data
+        .windows(stored.len())
+        .position(|w| w == stored)
+        .unwrap();
+    for date in ["unknown", "2017-09-14_23:37:05"] {
+        let len = (date.len() as u32 + 1).to_le_bytes();
+        let record = [&len[..], date.as_bytes(), b"\0"].concat();
+        let mut voice = [&data[..at], &record, &data[at + stored.len()..]].concat();
+        Header::patch_in_place(&mut voice, |header| header.features.age = 31).unwrap();
+        assert_eq!(voice[at..at + record.len()], record, "{date}");
+        let header: Header = crate::de::from_bytes(&voice).unwrap();
+        assert_eq!(header.features.build_date.to_string(), date);
+        assert_eq!(header.features.age, 31);
+    }
+}
+
+#[test]
+fn test_patch_keeps_order() {
+    let data = &include_bytes!("../data/cmu_us_slt.flitevox")[..394];
+    let end = data.len() - 55;
+    // the language moved last, with an unknown feature before it
+    let language = b"\x09\0\0\0language\0\x04\0\0\0eng\0";
+    let custom = b"\x0e\0\0\0festvox_phset\0\x07\0\0\0radio2\0";
+    let at = crate::FLITEVOX_MAGIC.len() + 4;
+    assert_eq!(data[at..at + language.len()], *language);
+    let mut voice = data.to_vec();
+    voice.splice(end..end, custom.iter().chain(language).copied());
+    voice.drain(at..at + language.len());
+    let before = voice.clone();
+
+    Header::patch_in_place(&mut voice, |header| header.features.age = 31).unwrap();
+    let age = b"\x03\0\0\x0030\0";
+    let age_at = before.windows(age.len()).position(|w| w == age).unwrap();
+    assert_eq!(voice[..age_at], before[..age_at]);
+    assert_eq!(voice[age_at..age_at + 7], *b"\x03\0\0\x0031\0");
+    assert_eq!(voice[age_at + 7..], before[age_at + 7..]);
+}
+
+#[test]
+fn test_language_gender() {
+    use crate::de::from_bytes;
+    use crate::ser::to_bytes;
+    assert_eq!("ind_tel".parse::<Language>(), Ok(Language::Telugu));
+    assert_eq!("eng".parse::<Language>(), Ok(Language::English));
+    let other: Language = "cym".parse().unwrap();
+    assert_eq!(other, Language::Other("cym".to_string()));
+    assert_eq!(other.to_string(), "cym");
+    assert_eq!(Language::Kannada.code(), "ind_kan");
+
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let data = data[..394].to_vec();
+    let header: Header = from_bytes(&data).unwrap();
+    assert_eq!(header.features.language(), Language::English);
+    assert_eq!(header.features.gender, Gender::Unknown);
+
+    // "none" is read and written back as it is
+    let unknown = b"gender\0\x08\0\0\0unknown\0";
+    let at = data
+        .windows(unknown.len())
+        .position(|w| w == unknown)
+        .unwrap();
+    let mut none = data.clone();
+    none.splice(at..at + unknown.len(), *b"gender\0\x05\0\0\0none\0");
+    let header: Header = from_bytes(&none).unwrap();
+    assert_eq!(header.features.gender, Gender::None);
+    assert_eq!(to_bytes(&header).unwrap(), none);
+}
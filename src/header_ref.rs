@@ -0,0 +1,283 @@
+//! The header of a voice, borrowed from the input.
+//!
+//! [`HeaderRef`] and [`FeaturesRef`] mirror [`Header`](crate::Header) and
+//! [`Features`](crate::Features), but their strings point into the input, so they need neither
+//! `alloc` nor a heap: firmware can read the metadata of a voice kept in flash. The build date is
+//! left as flite wrote it.
+
+use core::fmt;
+use core::str::FromStr;
+
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use crate::error::Result;
+use crate::{FormatVersion, Gender};
+
+// The keys of the features, in the order flite writes them.
+pub(crate) const FEATURE_KEYS: &[&str] = &[
+    "language",
+    "country",
+    "variant",
+    "age",
+    "gender",
+    "build_date",
+    "description",
+    "eng_shared",
+    "copyright",
+    "num_dur_models",
+    "num_param_models",
+    "model_shape",
+    "num_f0_models",
+    "end_of_features",
+];
+
+// The counts flite assumes for voices which leave them out, as v1 voices were built before the
+// count features existed.
+pub(crate) const DEFAULT_NUM_DUR_MODELS: u32 = 1;
+pub(crate) const DEFAULT_NUM_PARAM_MODELS: u32 = 3;
+pub(crate) const DEFAULT_NUM_F0_MODELS: u32 = 1;
+pub(crate) const DEFAULT_MODEL_SHAPE: u32 = 0;
+
+/// The feature block, borrowed from the input. Features it has no field for are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeaturesRef<'de> {
+    pub language: &'de str,
+    pub country: &'de str,
+    pub variant: &'de str,
+    pub age: u32,
+    pub gender: Gender,
+    /// As written, e.g. `2017-09-14_23:37`.
+    pub build_date: &'de str,
+    pub description: &'de str,
+    pub eng_shared: u32,
+    pub copyright: &'de str,
+    pub num_dur_models: u32,
+    pub num_param_models: u32,
+    pub model_shape: u32,
+    pub num_f0_models: u32,
+}
+
+/// Told about every pair of a feature block read from the input, the end marker included.
+pub(crate) trait FeatureSink<'de> {
+    fn feature<E: de::Error>(
+        &mut self,
+        key: &'de str,
+        value: &'de str,
+    ) -> core::result::Result<(), E>;
+}
+
+// Reads a feature block into a sink. Both borrowed readers of the header split the block here.
+struct FeatureBlock<S>(S);
+impl<'de, S: FeatureSink<'de>> Visitor<'de> for FeatureBlock<S> {
+    type Value = S;
+    fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("A feature block")
+    }
+    fn visit_map<A>(mut self, mut map: A) -> core::result::Result<S, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        while let Some(key) = map.next_key::<&'de str>()? {
+            let value = map.next_value::<&'de str>()?;
+            self.0.feature(key, value)?;
+        }
+        Ok(self.0)
+    }
+}
+impl<'de, S: FeatureSink<'de>> DeserializeSeed<'de> for FeatureBlock<S> {
+    type Value = S;
+    fn deserialize<D>(self, deserializer: D) -> core::result::Result<S, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("Features", FEATURE_KEYS, self)
+    }
+}
+
+// Reads a header, with its feature block into a sink: the version, the sink and the name.
+pub(crate) struct HeaderBlock<S>(pub S);
+impl<'de, S: FeatureSink<'de>> Visitor<'de> for HeaderBlock<S> {
+    type Value = (FormatVersion, S, &'de str);
+    fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("A header: the feature block, then the name of the voice")
+    }
+    fn visit_seq<A>(self, mut seq: A) -> core::result::Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let version = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let features = seq
+            .next_element_seed(FeatureBlock(self.0))?
+            .ok_or_else(|| de::Error::invalid_length(1, &"a feature block"))?;
+        let name = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(2, &"the name of the voice"))?;
+        Ok((version, features, name))
+    }
+}
+impl<'de, S: FeatureSink<'de>> DeserializeSeed<'de> for HeaderBlock<S> {
+    type Value = (FormatVersion, S, &'de str);
+    fn deserialize<D>(self, deserializer: D) -> core::result::Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(3, self)
+    }
+}
+
+// The features read so far; the optional ones get the same defaults as in `Features`.
+struct PartialFeatures<'de> {
+    features: FeaturesRef<'de>,
+    language: Option<&'de str>,
+    country: Option<&'de str>,
+    age: Option<u32>,
+    gender: Option<Gender>,
+    build_date: Option<&'de str>,
+    eng_shared: Option<u32>,
+}
+impl<'de> PartialFeatures<'de> {
+    fn new() -> Self {
+        PartialFeatures {
+            features: FeaturesRef {
+                language: "",
+                country: "",
+                variant: "unknown",
+                age: 0,
+                gender: Gender::Unknown,
+                build_date: "",
+                description: "unknown",
+                eng_shared: 0,
+                copyright: "unknown",
+                num_dur_models: DEFAULT_NUM_DUR_MODELS,
+                num_param_models: DEFAULT_NUM_PARAM_MODELS,
+                model_shape: DEFAULT_MODEL_SHAPE,
+                num_f0_models: DEFAULT_NUM_F0_MODELS,
+            },
+            language: None,
+            country: None,
+            age: None,
+            gender: None,
+            build_date: None,
+            eng_shared: None,
+        }
+    }
+    fn finish<E: de::Error>(self) -> core::result::Result<FeaturesRef<'de>, E> {
+        let mut features = self.features;
+        features.language = self.language.ok_or_else(|| E::missing_field("language"))?;
+        features.country = self.country.ok_or_else(|| E::missing_field("country"))?;
+        features.age = self.age.ok_or_else(|| E::missing_field("age"))?;
+        features.gender = self.gender.ok_or_else(|| E::missing_field("gender"))?;
+        features.build_date = self
+            .build_date
+            .ok_or_else(|| E::missing_field("build_date"))?;
+        features.eng_shared = self
+            .eng_shared
+            .ok_or_else(|| E::missing_field("eng_shared"))?;
+        Ok(features)
+    }
+}
+impl<'de> FeatureSink<'de> for PartialFeatures<'de> {
+    fn feature<E: de::Error>(
+        &mut self,
+        key: &'de str,
+        value: &'de str,
+    ) -> core::result::Result<(), E> {
+        let features = &mut self.features;
+        match key {
+            "language" => self.language = Some(value),
+            "country" => self.country = Some(value),
+            "variant" => features.variant = value,
+            "age" => self.age = Some(parse(key, value)?),
+            "gender" => self.gender = Some(parse(key, value)?),
+            "build_date" => self.build_date = Some(value),
+            "description" => features.description = value,
+            "eng_shared" => self.eng_shared = Some(parse(key, value)?),
+            "copyright" => features.copyright = value,
+            "num_dur_models" => features.num_dur_models = parse(key, value)?,
+            "num_param_models" => features.num_param_models = parse(key, value)?,
+            "model_shape" => features.model_shape = parse(key, value)?,
+            "num_f0_models" => features.num_f0_models = parse(key, value)?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+/// A feature which is not a string, but stored as one like every feature is.
+pub(crate) fn parse<T: FromStr, E: de::Error>(
+    key: &str,
+    value: &str,
+) -> core::result::Result<T, E> {
+    value
+        .parse()
+        .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &FeatureExpected(key)))
+}
+struct FeatureExpected<'a>(&'a str);
+impl de::Expected for FeatureExpected<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "a valid {}", self.0)
+    }
+}
+impl<'de: 'a, 'a> Deserialize<'de> for FeaturesRef<'a> {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<FeaturesRef<'a>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        FeatureBlock(PartialFeatures::new())
+            .deserialize(deserializer)?
+            .finish()
+    }
+}
+
+/// The header of a voice, borrowed from the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderRef<'de> {
+    pub version: FormatVersion,
+    pub features: FeaturesRef<'de>,
+    pub name: &'de str,
+}
+impl<'de> HeaderRef<'de> {
+    /// Read the header from the start of a voice file. Only the header needs to be present, and
+    /// reading it allocates nothing.
+    pub fn parse(input: &'de [u8]) -> Result<HeaderRef<'de>> {
+        HeaderRef::deserialize(&mut crate::de::Deserializer::from_bytes(input))
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for HeaderRef<'a> {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<HeaderRef<'a>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (version, features, name) =
+            HeaderBlock(PartialFeatures::new()).deserialize(deserializer)?;
+        Ok(HeaderRef {
+            version,
+            features: features.finish()?,
+            name,
+        })
+    }
+}
+
+#[test]
+fn test_header_ref() {
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let header = HeaderRef::parse(&data[..394]).unwrap();
+    assert_eq!(header.version, FormatVersion::CURRENT);
+    assert_eq!(header.name, "cmu_us_slt");
+    assert_eq!(header.features.language, "eng");
+    assert_eq!(header.features.gender, Gender::Unknown);
+    assert_eq!(header.features.build_date, "2017-09-14_23:37");
+    assert_eq!(header.features.num_f0_models, 3);
+    // borrowed, not copied
+    assert!(data.as_ptr_range().contains(&header.name.as_ptr()));
+    assert!(HeaderRef::parse(&data[..393]).is_err());
+
+    #[cfg(feature = "alloc")]
+    {
+        let owned = crate::lazy::read_header(data).unwrap();
+        assert_eq!(header.features.copyright, owned.features.copyright);
+        assert_eq!(header.features.model_shape, owned.features.model_shape);
+    }
+}
@@ -0,0 +1,191 @@
+//! Guess metadata from the conventional names of voices, like `cmu_us_slt` or `cmu_indic_hin_ab`.
+//!
+//! Header features are often left as `unknown`. [`infer`] only returns guesses, each with a
+//! [`Confidence`]; [`NameHints::check`] compares them against the header without changing it.
+
+use crate::diagnostic::Diagnostic;
+use crate::{Features, Gender};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    /// Follows from a loose convention.
+    Low,
+    /// Follows from the naming scheme of a voice collection.
+    Medium,
+    /// The speaker is known.
+    High,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Guess<T> {
+    pub value: T,
+    pub confidence: Confidence,
+}
+fn guess<T>(value: T, confidence: Confidence) -> Option<Guess<T>> {
+    Some(Guess { value, confidence })
+}
+
+/// What the name of a voice suggests. Languages and countries use the codes flite voices use in
+/// their features (`eng`, `USA`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NameHints<'a> {
+    /// The speaker code, the last part of the name.
+    pub speaker: Option<&'a str>,
+    pub language: Option<Guess<&'static str>>,
+    pub country: Option<Guess<&'static str>>,
+    /// An accent the language code alone does not tell, e.g. `Scottish`.
+    pub accent: Option<Guess<&'static str>>,
+    pub gender: Option<Guess<Gender>>,
+}
+
+/// A speaker code, gender and, if not American, accent and country.
+type Speaker = (&'static str, Gender, Option<(&'static str, &'static str)>);
+
+// The CMU ARCTIC and related speakers.
+const SPEAKERS: &[Speaker] = &[
+    ("aew", Gender::Male, None),
+    ("ahw", Gender::Male, None),
+    ("aup", Gender::Male, Some(("Indian", "IND"))),
+    ("awb", Gender::Male, Some(("Scottish", "GBR"))),
+    ("axb", Gender::Female, Some(("Indian", "IND"))),
+    ("bdl", Gender::Male, None),
+    ("clb", Gender::Female, None),
+    ("eey", Gender::Female, None),
+    ("fem", Gender::Male, None),
+    ("gka", Gender::Male, Some(("Indian", "IND"))),
+    ("jmk", Gender::Male, Some(("Canadian", "CAN"))),
+    ("kal", Gender::Male, None),
+    ("ksp", Gender::Male, Some(("Indian", "IND"))),
+    ("ljm", Gender::Female, None),
+    ("lnh", Gender::Female, None),
+    ("rms", Gender::Male, None),
+    ("rxr", Gender::Male, None),
+    ("slp", Gender::Female, Some(("Indian", "IND"))),
+    ("slt", Gender::Female, None),
+];
+
+// The language codes of the CMU Indic voices.
+const INDIC: &[&str] = &["ben", "guj", "hin", "kan", "mar", "pan", "tam", "tel"];
+
+/// Guess what the name of a voice says about it.
+pub fn infer(name: &str) -> NameHints<'_> {
+    use Confidence::*;
+    let parts: Vec<&str> = name.split(['_', '-']).filter(|p| !p.is_empty()).collect();
+    let mut hints = NameHints {
+        speaker: parts.last().copied().filter(|_| parts.len() > 1),
+        ..NameHints::default()
+    };
+    let region = parts.iter().position(|part| ["us", "indic"].contains(part));
+    match region.map(|at| (parts[at], parts.get(at + 1).copied())) {
+        Some(("us", _)) => {
+            hints.language = guess("eng", High);
+            hints.country = guess("USA", Medium);
+        }
+        Some(("indic", Some(language))) => {
+            if let Some(language) = INDIC.iter().find(|code| **code == language) {
+                hints.language = guess(*language, High);
+                hints.country = guess("IND", High);
+            }
+        }
+        _ => {}
+    }
+    if let Some(&(_, gender, accent)) = hints
+        .speaker
+        .and_then(|speaker| SPEAKERS.iter().find(|(code, _, _)| *code == speaker))
+    {
+        // the speaker codes are only unambiguous within the English collections
+        let confidence = if hints.language.is_some_and(|l| l.value == "eng") {
+            High
+        } else {
+            Low
+        };
+        hints.gender = guess(gender, confidence);
+        if let Some((accent, country)) = accent {
+            hints.accent = guess(accent, confidence);
+            hints.country = guess(country, confidence);
+        }
+    }
+    hints
+}
+
+fn gender_name(gender: Gender) -> &'static str {
+    match gender {
+        Gender::Male => "male",
+        Gender::Female => "female",
+        Gender::Unknown => "unknown",
+        Gender::None => "none",
+    }
+}
+
+impl NameHints<'_> {
+    /// Compare the guesses with the header: a feature left as `unknown` gets a `name-suggests`
+    /// warning, a feature which contradicts a guess of at least `min` confidence gets a
+    /// `name-contradicts` one.
+    pub fn check(&self, features: &Features, min: Confidence) -> Vec<Diagnostic> {
+        let gender = self.gender.map(|guess| Guess {
+            value: gender_name(guess.value),
+            confidence: guess.confidence,
+        });
+        let mut warnings = Vec::new();
+        for (field, guess, value) in [
+            ("language", self.language, features.language.as_str()),
+            ("country", self.country, features.country.as_str()),
+            ("gender", gender, gender_name(features.gender)),
+        ] {
+            let Some(guess) = guess else { continue };
+            if value == "unknown" || value.is_empty() {
+                let message = format!(
+                    "not set, the name suggests {:?} ({:?} confidence)",
+                    guess.value, guess.confidence
+                );
+                warnings.push(Diagnostic::warning("name-suggests", field, message));
+            } else if !value.eq_ignore_ascii_case(guess.value) && guess.confidence >= min {
+                let message = format!(
+                    "{value:?}, but the name suggests {:?} ({:?} confidence)",
+                    guess.value, guess.confidence
+                );
+                warnings.push(Diagnostic::warning("name-contradicts", field, message));
+            }
+        }
+        warnings
+    }
+}
+
+#[test]
+fn test_infer() {
+    let slt = infer("cmu_us_slt");
+    assert_eq!(slt.speaker, Some("slt"));
+    assert_eq!(slt.language, guess("eng", Confidence::High));
+    assert_eq!(slt.country, guess("USA", Confidence::Medium));
+    assert_eq!(slt.gender, guess(Gender::Female, Confidence::High));
+    assert_eq!(slt.accent, None);
+
+    let awb = infer("cmu_us_awb");
+    assert_eq!(awb.country, guess("GBR", Confidence::High));
+    assert_eq!(awb.accent, guess("Scottish", Confidence::High));
+
+    let hin = infer("cmu_indic_hin_ab");
+    assert_eq!(hin.speaker, Some("ab"));
+    assert_eq!(hin.language, guess("hin", Confidence::High));
+    assert_eq!(hin.country, guess("IND", Confidence::High));
+    assert_eq!(hin.gender, None);
+
+    assert_eq!(infer("voice"), NameHints::default());
+}
+
+#[test]
+fn test_check() {
+    use crate::de::from_bytes;
+    use crate::Header;
+    let header: Header = from_bytes(include_bytes!("../data/cmu_us_slt.flitevox")).unwrap();
+    let warnings = infer(&header.name).check(&header.features, Confidence::Medium);
+    let found: Vec<(&str, &str)> = warnings
+        .iter()
+        .map(|warning| (warning.code, warning.field.as_str()))
+        .collect();
+    assert_eq!(found, [("name-suggests", "gender")]);
+
+    let warnings = infer("cmu_us_awb").check(&header.features, Confidence::High);
+    assert_eq!(warnings[0].code, "name-contradicts");
+    assert_eq!(warnings[0].field, "country");
+}
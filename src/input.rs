@@ -0,0 +1,151 @@
+//! Where the [`Deserializer`](crate::de::Deserializer) gets its bytes from.
+//!
+//! A slice lends its bytes out, so strings can be borrowed from it. An [`IoInput`] reads a
+//! [`std::io::Read`] in chunks, keeping only what has not been consumed yet in memory.
+
+use core::ops::Deref;
+
+use crate::error::{Error, Result};
+
+/// Bytes taken from an [`Input`]: borrowed from the input itself, or copied into a buffer which
+/// only lives until the next read.
+pub enum Bytes<'de, 's> {
+    Borrowed(&'de [u8]),
+    Copied(&'s [u8]),
+}
+impl<'de: 's, 's> Bytes<'de, 's> {
+    pub fn get(self) -> &'s [u8] {
+        match self {
+            Bytes::Borrowed(bytes) => bytes,
+            Bytes::Copied(bytes) => bytes,
+        }
+    }
+}
+impl Deref for Bytes<'_, '_> {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        match *self {
+            Bytes::Borrowed(bytes) => bytes,
+            Bytes::Copied(bytes) => bytes,
+        }
+    }
+}
+
+pub trait Input<'de> {
+    /// The next `n` bytes without consuming them, or fewer at the end of the input.
+    fn peek(&mut self, n: usize) -> Result<&[u8]>;
+    /// Consume the next `n` bytes; [`Error::Eof`] if there are fewer.
+    fn take(&mut self, n: usize) -> Result<Bytes<'de, '_>>;
+}
+
+impl<'de> Input<'de> for &'de [u8] {
+    fn peek(&mut self, n: usize) -> Result<&[u8]> {
+        Ok(&self[..n.min(self.len())])
+    }
+    fn take(&mut self, n: usize) -> Result<Bytes<'de, '_>> {
+        if n > self.len() {
+            return Err(Error::Eof);
+        }
+        let (taken, rest) = self.split_at(n);
+        *self = rest;
+        Ok(Bytes::Borrowed(taken))
+    }
+}
+
+#[cfg(feature = "std")]
+pub use io::IoInput;
+
+#[cfg(feature = "std")]
+mod io {
+    use std::io::{ErrorKind, Read};
+
+    use super::{Bytes, Input};
+    use crate::error::{Error, Result};
+
+    // How much is read from the reader at once. Declared sizes are never trusted for this, so
+    // a corrupt size cannot make the buffer balloon before the data is actually there.
+    const CHUNK: usize = 64 * 1024;
+
+    /// Reads a [`Read`] in chunks. Consumed bytes are dropped when more room is needed, so
+    /// memory use is bounded by the largest single string or cell plus a chunk.
+    pub struct IoInput<R> {
+        reader: R,
+        buf: Vec<u8>,
+        pos: usize,
+        eof: bool,
+    }
+    impl<R: Read> IoInput<R> {
+        pub fn new(reader: R) -> Self {
+            IoInput {
+                reader,
+                buf: Vec::new(),
+                pos: 0,
+                eof: false,
+            }
+        }
+        pub fn into_inner(self) -> R {
+            self.reader
+        }
+        /// Buffer at least `n` unconsumed bytes, unless the reader ends first.
+        fn fill(&mut self, n: usize) -> Result<()> {
+            if self.buf.len() - self.pos >= n || self.eof {
+                return Ok(());
+            }
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+            while self.buf.len() < n && !self.eof {
+                let len = self.buf.len();
+                self.buf.resize(len + CHUNK, 0);
+                let read = loop {
+                    match self.reader.read(&mut self.buf[len..]) {
+                        Ok(read) => break read,
+                        Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                        Err(err) => {
+                            self.buf.truncate(len);
+                            return Err(Error::Io(err));
+                        }
+                    }
+                };
+                self.buf.truncate(len + read);
+                self.eof = read == 0;
+            }
+            Ok(())
+        }
+    }
+    impl<'de, R: Read> Input<'de> for IoInput<R> {
+        fn peek(&mut self, n: usize) -> Result<&[u8]> {
+            self.fill(n)?;
+            let end = self.buf.len().min(self.pos + n);
+            Ok(&self.buf[self.pos..end])
+        }
+        fn take(&mut self, n: usize) -> Result<Bytes<'de, '_>> {
+            self.fill(n)?;
+            if self.buf.len() - self.pos < n {
+                return Err(Error::Eof);
+            }
+            let start = self.pos;
+            self.pos += n;
+            Ok(Bytes::Copied(&self.buf[start..self.pos]))
+        }
+    }
+
+    #[test]
+    fn test_io_input() {
+        // hands out at most 3 bytes per read
+        struct Trickle<'a>(&'a [u8]);
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = buf.len().min(3).min(self.0.len());
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+        let mut input = IoInput::new(Trickle(b"abcdefgh"));
+        assert_eq!(input.peek(5).unwrap(), b"abcde");
+        assert_eq!(&*input.take(2).unwrap(), b"ab");
+        assert_eq!(&*input.take(5).unwrap(), b"cdefg");
+        assert_eq!(input.peek(4).unwrap(), b"h");
+        assert!(matches!(input.take(2), Err(Error::Eof)));
+    }
+}
@@ -0,0 +1,112 @@
+//! Everything a voice manager lists about a voice, in one call.
+//!
+//! [`summarize`] reads the header with [`read_header`](crate::lazy::read_header) and takes the
+//! sample rate and the size of every section from a [`preflight`](crate::preflight) walk, so no
+//! tree or model is decoded. Listing dozens of installed voices stays cheap.
+
+use crate::error::Result;
+use crate::lazy::read_header;
+use crate::preflight::{Sink, Walker};
+use crate::{BuildDate, FormatVersion, Gender};
+
+/// What `flitevox-inspect` would print about a voice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoiceSummary {
+    pub name: String,
+    pub version: FormatVersion,
+    pub language: String,
+    pub country: String,
+    pub variant: String,
+    pub gender: Gender,
+    pub age: u32,
+    pub build_date: BuildDate,
+    /// In Hz.
+    pub sample_rate: u32,
+    pub num_f0_models: u32,
+    pub num_param_models: u32,
+    pub num_dur_models: u32,
+    pub model_shape: u32,
+    /// The bytes every section takes, in the order sections first appear, named as in
+    /// [`Inconsistency`](crate::preflight::Inconsistency). The magic and an integrity trailer
+    /// belong to no section.
+    pub sections: Vec<(&'static str, usize)>,
+}
+impl VoiceSummary {
+    /// The bytes `section` takes, 0 if the voice does not have it.
+    pub fn section_size(&self, section: &str) -> usize {
+        self.sections
+            .iter()
+            .find(|(known, _)| *known == section)
+            .map_or(0, |(_, bytes)| *bytes)
+    }
+}
+
+#[derive(Default)]
+struct Inspection {
+    sizes: Vec<(&'static str, usize)>,
+    sample_rate: usize,
+}
+impl Sink for Inspection {
+    fn account(&mut self, _section: &'static str, _bytes: usize, _allocations: usize) {}
+    fn read(&mut self, section: &'static str, bytes: usize) {
+        match self.sizes.iter_mut().find(|(known, _)| *known == section) {
+            Some((_, size)) => *size += bytes,
+            None => self.sizes.push((section, bytes)),
+        }
+    }
+    fn int(&mut self, section: &'static str, _offset: usize, value: usize) {
+        if section == "sample_rate" {
+            self.sample_rate = value;
+        }
+    }
+}
+
+/// Summarize the voice in `input`. Its trailers, if it has any, are verified.
+pub fn summarize(input: &[u8]) -> Result<VoiceSummary> {
+    let input = crate::trailer::verify(input)?;
+    let header = read_header(input)?;
+    let mut walker = Walker::new(input, Inspection::default())?;
+    walker.voice()?;
+    let inspection = walker.finish()?;
+    let features = header.features;
+    Ok(VoiceSummary {
+        name: header.name,
+        version: header.version,
+        language: features.language,
+        country: features.country,
+        variant: features.variant,
+        gender: features.gender,
+        age: features.age,
+        build_date: features.build_date,
+        sample_rate: u32::try_from(inspection.sample_rate).unwrap_or(u32::MAX),
+        num_f0_models: features.num_f0_models,
+        num_param_models: features.num_param_models,
+        num_dur_models: features.num_dur_models,
+        model_shape: features.model_shape,
+        sections: inspection.sizes,
+    })
+}
+
+#[test]
+fn test_summarize() {
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let summary = summarize(data).unwrap();
+    assert_eq!(summary.name, "cmu_us_slt");
+    assert_eq!(summary.version, FormatVersion::CURRENT);
+    assert_eq!(
+        (&*summary.language, &*summary.country, &*summary.variant),
+        ("eng", "USA", "none")
+    );
+    assert_eq!(summary.gender, Gender::Unknown);
+    assert_eq!(summary.age, 30);
+    assert_eq!(summary.build_date.to_string(), "2017-09-14_23:37");
+    assert_eq!(summary.sample_rate, 16000);
+    assert_eq!(summary.num_param_models, 3);
+    assert_eq!(summary.section_size("sample_rate"), 4);
+    assert_eq!(summary.section_size("spamf0_accent_tree"), 0);
+    let total: usize = summary.sections.iter().map(|(_, bytes)| bytes).sum();
+    assert_eq!(total + crate::FLITEVOX_MAGIC.len(), data.len());
+
+    // a truncated voice has no sizes to report
+    assert!(summarize(&data[..data.len() - 1]).is_err());
+}
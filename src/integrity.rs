@@ -0,0 +1,411 @@
+//! Digests of every section of a voice, and a trailer which carries them.
+//!
+//! The CRC32 [trailer](crate::trailer) tells that a voice is corrupt, but not where. A digest
+//! trailer holds a digest of every section, so a voice damaged on its way to a device fails with
+//! [`Error::SectionChecksumMismatch`] naming the section, instead of a parse error somewhere in
+//! the trees. The sections are those of a [`preflight`](crate::preflight) walk, named as in
+//! [`Inconsistency`](crate::preflight::Inconsistency).
+//!
+//! The trailer is, in little-endian cells: the digest of every section in file order, the digest
+//! of everything before the trailer, the number of sections, the [`Algorithm`], and
+//! [`DIGEST_MAGIC`]. flite stops reading after the last section, so it still loads the voice.
+//! [`crate::de::from_bytes`] verifies the trailer when one is present.
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use serde::Deserialize;
+
+#[cfg(feature = "alloc")]
+use crate::de::{Deserializer, DeserializerOptions};
+use crate::error::{Error, Result};
+#[cfg(feature = "alloc")]
+use crate::preflight::{Sink, Walker};
+use crate::trailer::crc32;
+
+/// Marks the end of a digest trailer.
+pub const DIGEST_MAGIC: &[u8; 8] = b"CSTDIGST";
+// The whole digest, the section count and the algorithm, then the magic.
+const FIXED_LEN: usize = 12 + DIGEST_MAGIC.len();
+
+/// How sections are digested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Algorithm {
+    /// CRC32 (IEEE), as in the [trailer](crate::trailer).
+    #[default]
+    Crc32,
+    /// xxHash32 with a seed of 0: faster, but not an error-detecting code.
+    XxHash32,
+}
+impl Algorithm {
+    pub fn digest(self, data: &[u8]) -> u32 {
+        match self {
+            Algorithm::Crc32 => crc32(data),
+            Algorithm::XxHash32 => xxhash32(data),
+        }
+    }
+    #[cfg(feature = "alloc")]
+    fn id(self) -> u32 {
+        match self {
+            Algorithm::Crc32 => 1,
+            Algorithm::XxHash32 => 2,
+        }
+    }
+    fn from_id(id: u32) -> Option<Algorithm> {
+        match id {
+            1 => Some(Algorithm::Crc32),
+            2 => Some(Algorithm::XxHash32),
+            _ => None,
+        }
+    }
+}
+
+const PRIME_1: u32 = 0x9e37_79b1;
+const PRIME_2: u32 = 0x85eb_ca77;
+const PRIME_3: u32 = 0xc2b2_ae3d;
+const PRIME_4: u32 = 0x27d4_eb2f;
+const PRIME_5: u32 = 0x1656_67b1;
+
+fn lane(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn round(acc: u32, lane: u32) -> u32 {
+    acc.wrapping_add(lane.wrapping_mul(PRIME_2))
+        .rotate_left(13)
+        .wrapping_mul(PRIME_1)
+}
+
+/// The xxHash32 of `data`, with a seed of 0.
+pub fn xxhash32(data: &[u8]) -> u32 {
+    let stripes = data.chunks_exact(16);
+    let rest = stripes.remainder();
+    let mut hash = if data.len() >= 16 {
+        let mut acc = [
+            PRIME_1.wrapping_add(PRIME_2),
+            PRIME_2,
+            0,
+            PRIME_1.wrapping_neg(),
+        ];
+        for stripe in stripes {
+            for (acc, lane_bytes) in acc.iter_mut().zip(stripe.chunks_exact(4)) {
+                *acc = round(*acc, lane(lane_bytes));
+            }
+        }
+        acc[0]
+            .rotate_left(1)
+            .wrapping_add(acc[1].rotate_left(7))
+            .wrapping_add(acc[2].rotate_left(12))
+            .wrapping_add(acc[3].rotate_left(18))
+    } else {
+        PRIME_5
+    };
+    // xxHash mixes in the length modulo 2^32
+    hash = hash.wrapping_add(data.len() as u32);
+    let words = rest.chunks_exact(4);
+    let bytes = words.remainder();
+    for word in words {
+        hash = hash
+            .wrapping_add(lane(word).wrapping_mul(PRIME_3))
+            .rotate_left(17)
+            .wrapping_mul(PRIME_4);
+    }
+    for &byte in bytes {
+        hash = hash
+            .wrapping_add(u32::from(byte).wrapping_mul(PRIME_5))
+            .rotate_left(11)
+            .wrapping_mul(PRIME_1);
+    }
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(PRIME_2);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(PRIME_3);
+    hash ^ (hash >> 16)
+}
+
+/// The digest of one run of a section.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectionDigest {
+    pub section: &'static str,
+    /// Where the run starts in the file.
+    pub offset: usize,
+    pub len: usize,
+    pub digest: u32,
+}
+
+/// The digests of a voice. A section which the file interrupts, like the trees of every model,
+/// has a digest for each of its runs.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Digests {
+    pub algorithm: Algorithm,
+    /// The digest of the whole voice, without its trailers.
+    pub whole: u32,
+    pub sections: Vec<SectionDigest>,
+}
+#[cfg(feature = "alloc")]
+impl Digests {
+    /// The digests of every run of `section`.
+    pub fn section<'a>(&'a self, section: &'a str) -> impl Iterator<Item = &'a SectionDigest> {
+        self.sections.iter().filter(move |s| s.section == section)
+    }
+}
+
+/// The runs of sections a walk steps over.
+#[cfg(feature = "alloc")]
+struct Runs {
+    pos: usize,
+    runs: Vec<(&'static str, usize, usize)>,
+}
+#[cfg(feature = "alloc")]
+impl Sink for Runs {
+    fn account(&mut self, _section: &'static str, _bytes: usize, _allocations: usize) {}
+    fn read(&mut self, section: &'static str, bytes: usize) {
+        match self.runs.last_mut() {
+            Some((last, _, len)) if *last == section => *len += bytes,
+            _ => self.runs.push((section, self.pos, bytes)),
+        }
+        self.pos += bytes;
+    }
+}
+
+/// Digest every section of the voice in `input`, which has no trailers.
+#[cfg(feature = "alloc")]
+fn digest_voice(input: &[u8], algorithm: Algorithm) -> Result<Digests> {
+    let runs = Runs {
+        pos: crate::FLITEVOX_MAGIC.len(),
+        runs: Vec::new(),
+    };
+    let mut walker = Walker::new(input, runs)?;
+    walker.voice()?;
+    let runs = walker.finish()?;
+    let sections = runs
+        .runs
+        .into_iter()
+        .map(|(section, offset, len)| SectionDigest {
+            section,
+            offset,
+            len,
+            digest: algorithm.digest(&input[offset..offset + len]),
+        })
+        .collect();
+    Ok(Digests {
+        algorithm,
+        whole: algorithm.digest(input),
+        sections,
+    })
+}
+
+/// Digest every section of the voice in `input`. Its trailers, if it has any, are verified and
+/// not digested.
+#[cfg(feature = "alloc")]
+pub fn digest(input: &[u8], algorithm: Algorithm) -> Result<Digests> {
+    digest_voice(crate::trailer::verify(input)?, algorithm)
+}
+
+/// Append the digest trailer of the voice already in `out`.
+#[cfg(feature = "alloc")]
+pub fn append_digests(out: &mut Vec<u8>, algorithm: Algorithm) -> Result<()> {
+    let digests = digest_voice(out, algorithm)?;
+    let count = u32::try_from(digests.sections.len())
+        .map_err(|_| Error::WrongLength(digests.sections.len()))?;
+    for section in &digests.sections {
+        out.extend_from_slice(&section.digest.to_le_bytes());
+    }
+    out.extend_from_slice(&digests.whole.to_le_bytes());
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&algorithm.id().to_le_bytes());
+    out.extend_from_slice(DIGEST_MAGIC);
+    Ok(())
+}
+
+/// Whether `input` ends in a digest trailer, valid or not.
+pub fn has_digests(input: &[u8]) -> bool {
+    input.ends_with(DIGEST_MAGIC)
+}
+
+/// A digest trailer, split from the voice before it by
+/// [`strip_trailers`](crate::trailer::strip_trailers), or what keeps it from being read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DigestTrailer<'a> {
+    Intact {
+        data: &'a [u8],
+        stored: &'a [u8],
+        whole: u32,
+        algorithm: Algorithm,
+    },
+    /// The magic is there, but the cells it counts are not.
+    Truncated,
+    /// An algorithm this crate doesn't know.
+    UnknownAlgorithm,
+}
+
+/// The digest trailer of `input`, if it ends in one.
+pub(crate) fn split(input: &[u8]) -> Option<DigestTrailer<'_>> {
+    if !has_digests(input) {
+        return None;
+    }
+    let Some(fixed) = input.len().checked_sub(FIXED_LEN) else {
+        return Some(DigestTrailer::Truncated);
+    };
+    let cells = &input[fixed..];
+    let (whole, count, id) = (lane(cells), lane(&cells[4..]), lane(&cells[8..]));
+    let Some(algorithm) = Algorithm::from_id(id) else {
+        return Some(DigestTrailer::UnknownAlgorithm);
+    };
+    let start = usize::try_from(count)
+        .ok()
+        .and_then(|count| fixed.checked_sub(count.checked_mul(4)?));
+    let Some(start) = start else {
+        return Some(DigestTrailer::Truncated);
+    };
+    Some(DigestTrailer::Intact {
+        data: &input[..start],
+        stored: &input[start..fixed],
+        whole,
+        algorithm,
+    })
+}
+
+impl DigestTrailer<'_> {
+    /// Check the voice against the trailer.
+    ///
+    /// Only the digest of the whole voice is computed for an intact voice. Otherwise the voice
+    /// is walked to find the first section which changed; if it can't be walked, or the change
+    /// is in no section, the whole digest is reported with [`Error::ChecksumMismatch`].
+    pub(crate) fn verify(&self) -> Result<()> {
+        let (data, stored, whole, algorithm) = match *self {
+            DigestTrailer::Intact {
+                data,
+                stored,
+                whole,
+                algorithm,
+            } => (data, stored, whole, algorithm),
+            DigestTrailer::Truncated => return Err(Error::TruncatedTrailer),
+            DigestTrailer::UnknownAlgorithm => {
+                return Err(Error::Unsupported("this digest algorithm"))
+            }
+        };
+        let computed = algorithm.digest(data);
+        if computed == whole {
+            return Ok(());
+        }
+        #[cfg(feature = "alloc")]
+        if let Ok(digests) = digest_voice(data, algorithm) {
+            let changed = digests
+                .sections
+                .iter()
+                .zip(stored.chunks_exact(4))
+                .find(|(section, stored)| section.digest != lane(stored));
+            if let Some((section, _)) = changed {
+                return Err(Error::SectionChecksumMismatch(
+                    section.section,
+                    section.offset,
+                ));
+            }
+        }
+        #[cfg(not(feature = "alloc"))]
+        let _ = stored;
+        Err(Error::ChecksumMismatch(whole, computed))
+    }
+}
+
+/// [`crate::de::from_bytes_with`], also handing back the digest of every section if
+/// `options.digests` names an algorithm.
+#[cfg(feature = "alloc")]
+pub fn from_bytes_with<'a, T>(
+    input: &'a [u8],
+    options: DeserializerOptions,
+) -> Result<(T, Option<Digests>)>
+where
+    T: Deserialize<'a>,
+{
+    let input = crate::trailer::verify(input)?;
+    let mut deserializer = Deserializer::from_bytes_with(input, options);
+    let t = T::deserialize(&mut deserializer).map_err(|e| deserializer.locate_in(e, input))?;
+    deserializer
+        .end()
+        .map_err(|e| deserializer.locate_in(e, input))?;
+    let digests = match options.digests {
+        Some(algorithm) => Some(digest_voice(input, algorithm)?),
+        None => None,
+    };
+    Ok((t, digests))
+}
+
+#[test]
+fn test_xxhash32() {
+    assert_eq!(xxhash32(b""), 0x02cc_5d05);
+    assert_eq!(xxhash32(b"abc"), 0x32d1_53ff);
+    assert_eq!(
+        xxhash32(b"Nobody inspects the spammish repetition"),
+        0xe229_3b2f
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_digests() {
+    use crate::de::from_bytes;
+    use crate::trailer::{strip_trailers, verify};
+    use crate::voice::TreeDb;
+
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let digests = digest(data, Algorithm::Crc32).unwrap();
+    assert_eq!(digests.whole, crc32(data));
+    let types = digests.section("types").next().unwrap();
+    assert_eq!(types.offset, 394);
+    assert_eq!(types.digest, crc32(&data[394..394 + types.len]));
+    let total: usize = digests.sections.iter().map(|s| s.len).sum();
+    assert_eq!(total + crate::FLITEVOX_MAGIC.len(), data.len());
+
+    let options = DeserializerOptions {
+        digests: Some(Algorithm::XxHash32),
+        ..DeserializerOptions::default()
+    };
+    let (voice, computed) = from_bytes_with::<TreeDb>(data, options).unwrap();
+    assert_eq!(computed, Some(digest(data, Algorithm::XxHash32).unwrap()));
+
+    let written = crate::ser::to_bytes_with_digests(&voice, Algorithm::XxHash32).unwrap();
+    assert!(has_digests(&written));
+    assert_eq!(from_bytes::<TreeDb>(&written).unwrap(), voice);
+
+    let mut with_digests = data.to_vec();
+    append_digests(&mut with_digests, Algorithm::Crc32).unwrap();
+    assert_eq!(verify(&with_digests).unwrap(), &data[..]);
+    assert_eq!(
+        strip_trailers(&with_digests).1.digests(),
+        Some(Algorithm::Crc32)
+    );
+    assert_eq!(digest(&with_digests, Algorithm::Crc32).unwrap(), digests);
+
+    // a flipped bit in a value is reported with its section
+    let f0_mean = digests.section("f0_mean").next().unwrap();
+    with_digests[f0_mean.offset] ^= 1;
+    assert!(matches!(
+        verify(&with_digests),
+        Err(Error::SectionChecksumMismatch("f0_mean", offset)) if offset == f0_mean.offset
+    ));
+    // and one turning the version in the magic, which is in no section, into 2.1, with the
+    // whole digest
+    let minor = crate::FLITEVOX_MAGIC.len() - 2;
+    with_digests[f0_mean.offset] ^= 1;
+    with_digests[minor] ^= 1;
+    assert!(matches!(
+        verify(&with_digests),
+        Err(Error::ChecksumMismatch(..))
+    ));
+    with_digests[minor] ^= 1;
+
+    // a trailer counting more digests than there are bytes
+    let count = with_digests.len() - FIXED_LEN + 4;
+    with_digests[count..count + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    let (voice, trailers) = strip_trailers(&with_digests);
+    assert_eq!(voice.len(), with_digests.len());
+    assert!(matches!(trailers.verify(), Err(Error::TruncatedTrailer)));
+}
@@ -0,0 +1,294 @@
+//! Reading the header of a voice without decoding its body.
+//!
+//! Listing the installed voices only needs the name, language, gender and build date of each,
+//! and those sit in the first few hundred bytes of the file. [`read_header`] reads just them, so
+//! it also works on a prefix of the file. [`LazyVoice`] reads the header, checks the structure of
+//! the rest with a [`preflight`](crate::preflight) walk and notes where every section starts;
+//! sections of the body are then only decoded when asked for.
+
+use serde::de::{Deserialize, DeserializeSeed, SeqAccess};
+
+use crate::de::Deserializer;
+use crate::error::{Error, Result};
+use crate::preflight::{Sink, Walker};
+use crate::sections::{Section, SectionKind};
+use crate::voice::{
+    body_with_models, Body, BodyDeserializer, DurModel, FixedLengthSeq, ModelVectors, Models,
+    TreeDb, Trees,
+};
+use crate::{Header, FLITEVOX_MAGIC};
+
+/// Read only the header of a voice. Nothing after the name of the voice is looked at, so the
+/// integrity trailer is not verified.
+pub fn read_header(input: &[u8]) -> Result<Header> {
+    let mut de = Deserializer::from_bytes(input);
+    Header::deserialize(&mut de).map_err(|e| de.locate_in(e, input))
+}
+
+/// Where every section first appears, how many bytes it takes, and where every model starts, in
+/// file order.
+struct Offsets {
+    pos: usize,
+    sections: Vec<(&'static str, usize)>,
+    sizes: Vec<(&'static str, usize)>,
+    models: Vec<Section>,
+}
+impl Offsets {
+    fn model(&mut self, kind: SectionKind, offset: usize) {
+        let model = self.models.iter().filter(|s| s.kind == kind).count();
+        self.models.push(Section {
+            kind,
+            model,
+            offset,
+        });
+    }
+}
+impl Sink for Offsets {
+    fn account(&mut self, _section: &'static str, _bytes: usize, _allocations: usize) {}
+    fn read(&mut self, section: &'static str, bytes: usize) {
+        match self.sizes.iter_mut().find(|(known, _)| *known == section) {
+            Some((_, size)) => *size += bytes,
+            None => {
+                self.sections.push((section, self.pos));
+                self.sizes.push((section, bytes));
+            }
+        }
+        self.pos += bytes;
+    }
+    fn int(&mut self, section: &'static str, offset: usize, _value: usize) {
+        if section == "num_channels" {
+            self.model(SectionKind::ParamModel, offset);
+        }
+    }
+    fn list(&mut self, section: &'static str, offset: usize, _len: usize) {
+        if section == "dur_stats" {
+            self.model(SectionKind::DurModel, offset);
+        }
+    }
+    fn trees(&mut self, section: &'static str, offset: usize, _len: usize) {
+        match section {
+            "f0_trees" => self.model(SectionKind::F0Trees, offset),
+            "param_trees" => self.model(SectionKind::ParamTrees, offset),
+            _ => {}
+        }
+    }
+}
+
+/// A voice whose header has been read, and whose body is decoded one section at a time.
+#[derive(Debug, Clone)]
+pub struct LazyVoice<'de> {
+    header: Header,
+    input: &'de [u8],
+    byteswapped: bool,
+    body: usize,
+    sections: Vec<(&'static str, usize)>,
+    sizes: Vec<(&'static str, usize)>,
+    models: Vec<Section>,
+}
+impl<'de> LazyVoice<'de> {
+    /// Read the header of `input` and check that the rest of it is structurally sound. Its
+    /// trailers, if it has any, are verified.
+    pub fn new(input: &'de [u8]) -> Result<Self> {
+        let input = crate::trailer::verify(input)?;
+        let mut de = Deserializer::from_bytes(input);
+        let header = Header::deserialize(&mut de).map_err(|e| de.locate_in(e, input))?;
+        let offsets = Offsets {
+            pos: FLITEVOX_MAGIC.len(),
+            sections: Vec::new(),
+            sizes: Vec::new(),
+            models: Vec::new(),
+        };
+        let mut walker = Walker::new(input, offsets)?;
+        walker.voice()?;
+        let offsets = walker.finish()?;
+        Ok(LazyVoice {
+            header,
+            input,
+            byteswapped: de.is_byteswapped() == Some(true),
+            body: input.len() - de.remaining(),
+            sections: offsets.sections,
+            sizes: offsets.sizes,
+            models: offsets.models,
+        })
+    }
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+    pub fn into_header(self) -> Header {
+        self.header
+    }
+    /// Where `section`, named as in [`Inconsistency`](crate::preflight::Inconsistency), first
+    /// appears in the file. `None` if the voice does not have it.
+    pub fn offset(&self, section: &str) -> Option<usize> {
+        self.sections
+            .iter()
+            .find(|(known, _)| *known == section)
+            .map(|(_, offset)| *offset)
+    }
+    /// Every section and where it first appears, in file order.
+    pub fn sections(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        self.sections.iter().copied()
+    }
+    /// Every section and how many bytes it takes in all, in file order.
+    pub fn sizes(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        self.sizes.iter().copied()
+    }
+    /// Every model, in file order.
+    pub(crate) fn models(&self) -> &[Section] {
+        &self.models
+    }
+    fn at(&self, offset: usize) -> Deserializer<'de> {
+        Deserializer::resume(&self.input[offset..], self.byteswapped)
+    }
+    /// Decode a `T` at `offset`, and return it with the offset it ends at.
+    pub(crate) fn read_at<T: Deserialize<'de>>(&self, offset: usize) -> Result<(T, usize)> {
+        let mut de = self.at(offset);
+        let value = T::deserialize(&mut de)?;
+        Ok((value, self.input.len() - de.remaining()))
+    }
+    // `len` values starting at `section`, which the voice only has if `len` is not zero.
+    fn fixed<T: Deserialize<'de> + 'de>(&self, section: &str, len: u32) -> Result<Vec<T>> {
+        match self.offset(section) {
+            Some(offset) => {
+                FixedLengthSeq::from_len(len as usize).deserialize(&mut self.at(offset))
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+    fn section<T: Deserialize<'de>>(&self, section: &str) -> Result<T> {
+        let offset = self.offset(section).unwrap_or(self.input.len());
+        T::deserialize(&mut self.at(offset))
+    }
+    pub fn db_types(&self) -> Result<Vec<String>> {
+        self.section("types")
+    }
+    pub fn f0_trees(&self) -> Result<Vec<Trees>> {
+        self.fixed("f0_trees", self.header.features.num_f0_models)
+    }
+    pub fn param_trees(&self) -> Result<Vec<Trees>> {
+        self.fixed("param_trees", self.header.features.num_param_models)
+    }
+    pub fn param_models(&self) -> Result<Vec<ModelVectors>> {
+        self.fixed("num_channels", self.header.features.num_param_models)
+    }
+    pub fn dur_models(&self) -> Result<Vec<DurModel>> {
+        self.fixed("dur_stats", self.header.features.num_dur_models)
+    }
+    pub fn phone_states(&self) -> Result<Vec<Vec<String>>> {
+        self.section("phone_states")
+    }
+    /// Decode the whole body.
+    pub fn body(&self) -> Result<Body> {
+        BodyDeserializer {
+            header: &self.header,
+        }
+        .deserialize(&mut self.at(self.body))
+    }
+    /// Decode the body but for its `models`, which were decoded already and span `runs` of the
+    /// file: the start of the first model of a kind and the end of the last.
+    pub(crate) fn body_with(&self, models: Models, runs: &[(usize, usize)]) -> Result<Body> {
+        let rest = Rest {
+            voice: self,
+            de: self.at(self.body),
+            runs,
+        };
+        body_with_models(&self.header, models, rest)
+    }
+    pub fn into_voice(self) -> Result<TreeDb> {
+        let body = self.body()?;
+        Ok(TreeDb {
+            header: self.header,
+            body,
+        })
+    }
+}
+
+// The elements of a body, read from the file but for the runs of models, which are stepped over.
+struct Rest<'a, 'de> {
+    voice: &'a LazyVoice<'de>,
+    de: Deserializer<'de>,
+    runs: &'a [(usize, usize)],
+}
+impl<'de> SeqAccess<'de> for Rest<'_, 'de> {
+    type Error = Error;
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        // a run starts where the element before it ended; an element of no bytes may start there
+        // too, and reads the same after the run
+        loop {
+            let pos = self.voice.input.len() - self.de.remaining();
+            match self
+                .runs
+                .iter()
+                .find(|(start, end)| *start == pos && end > start)
+            {
+                Some(&(_, end)) => self.de = self.voice.at(end),
+                None => break,
+            }
+        }
+        seed.deserialize(&mut self.de).map(Some)
+    }
+}
+
+#[test]
+fn test_read_header() {
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let header = read_header(&data[..394]).unwrap();
+    assert_eq!(header.name, "cmu_us_slt");
+    assert_eq!(header.features.language, "eng");
+    assert_eq!(
+        header,
+        crate::de::from_bytes::<TreeDb>(data).unwrap().header
+    );
+    assert!(read_header(&data[..393]).is_err());
+    assert!(read_header(b"RIFF").is_err());
+}
+
+#[test]
+fn test_lazy_voice() {
+    use crate::error::Error;
+    use crate::preflight::Inconsistency;
+
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let voice = LazyVoice::new(data).unwrap();
+    assert_eq!(voice.header().name, "cmu_us_slt");
+    assert_eq!(voice.offset("endianness"), Some(26));
+    assert_eq!(voice.offset("types"), Some(394));
+    assert_eq!(voice.offset("spamf0_accent_tree"), None);
+    assert_eq!(voice.sections().last(), Some(("gain", data.len() - 4)));
+    assert_eq!(voice.sizes().last(), Some(("gain", 4)));
+    let size: usize = voice.sizes().map(|(_, bytes)| bytes).sum();
+    assert_eq!(size, data.len() - FLITEVOX_MAGIC.len());
+    assert_eq!(voice.models().len(), 12);
+    assert_eq!(voice.models()[0].offset, voice.offset("f0_trees").unwrap());
+
+    let f0_trees = voice.f0_trees().unwrap();
+    assert_eq!(f0_trees.len(), 3);
+    assert!(f0_trees.iter().all(|trees| trees.0.len() == 124));
+    let models = voice.param_models().unwrap();
+    assert_eq!(
+        models.iter().map(|m| m.1).collect::<Vec<_>>(),
+        [30238, 30135, 30220]
+    );
+    let dur_models = voice.dur_models().unwrap();
+    assert_eq!(dur_models.len(), 3);
+    assert_eq!(dur_models[0].0[0].2, "pau");
+    assert_eq!(
+        voice.phone_states().unwrap()[0],
+        ["aa", "aa_1", "aa_2", "aa_3"]
+    );
+
+    let voice = voice.into_voice().unwrap();
+    assert_eq!(voice, crate::de::from_bytes::<TreeDb>(data).unwrap());
+    assert_eq!(voice.body.f0_trees, f0_trees);
+
+    assert!(matches!(
+        LazyVoice::new(&data[..data.len() - 1]),
+        Err(Error::Structure(Inconsistency::Overrun {
+            section: "gain",
+            ..
+        }))
+    ));
+}
@@ -0,0 +1,65 @@
+//! Compiled lexicons.
+//!
+//! flite compiles a lexicon into a C array of packed entries and a table of the phones they
+//! index, the fields of its `cst_lexicon`. A [`Lexicon`] holds the same fields, stored like the
+//! entries of a voice after the [`LEXDATA_MAGIC`](crate::container::LEXDATA_MAGIC), so the
+//! lexicon of a voice can ship as data next to it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::array::Array;
+use crate::container::Container;
+use crate::de::Deserializer;
+use crate::error::Result;
+use crate::ser::Serializer;
+
+/// The compiled lexicon of flite's `cst_lexicon`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Lexicon {
+    /// e.g. `cmu`.
+    pub name: String,
+    pub num_entries: i32,
+    /// The entries, sorted and packed as flite compiles them: phones are indices into
+    /// `phone_table`.
+    pub data: Array,
+    pub phone_table: Vec<String>,
+}
+impl Lexicon {
+    /// Read a lexicon dump.
+    pub fn from_bytes(input: &[u8]) -> Result<Lexicon> {
+        let mut de = Deserializer::from_bytes(input).with_container(Container::LEXICON);
+        Lexicon::deserialize(&mut de).map_err(|e| de.locate_in(e, input))
+    }
+    /// Write a lexicon dump.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut serializer = Serializer::for_container(Container::LEXICON);
+        self.serialize(&mut serializer)?;
+        Ok(serializer.into_inner())
+    }
+    /// The phone an index in `data` stands for.
+    pub fn phone(&self, idx: u8) -> Option<&str> {
+        self.phone_table.get(usize::from(idx)).map(String::as_str)
+    }
+}
+
+#[test]
+fn test_lexicon() {
+    let lexicon = Lexicon {
+        name: "test".to_string(),
+        num_entries: 1,
+        data: Array(b"\x00\x03\x02\x01hello\x00n".to_vec()),
+        phone_table: ["pau", "hh", "ah", "l"].map(String::from).to_vec(),
+    };
+    let data = lexicon.to_bytes().unwrap();
+    assert!(data.starts_with(crate::container::LEXDATA_MAGIC));
+    assert_eq!(Lexicon::from_bytes(&data).unwrap(), lexicon);
+    assert_eq!(lexicon.phone(1), Some("hh"));
+
+    // a voice is not a lexicon, and the other way around
+    let voice = include_bytes!("../data/cmu_us_slt.flitevox");
+    assert!(matches!(
+        Lexicon::from_bytes(voice).unwrap_err().kind(),
+        crate::error::Error::InvalidHeader
+    ));
+    assert!(crate::de::from_bytes::<crate::Header>(&data).is_err());
+}
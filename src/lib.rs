@@ -4,21 +4,126 @@
 //! It is not recommended to use this crate without the types provided (must enable the `alloc` feature). The format is not well
 //! suited to general use.
 //!
-//! This crate is `no_std` compatible, but `std` support can be activated if desired.
+//! This crate is `no_std` compatible, but `std` support can be activated if desired. Without
+//! `alloc`, [`HeaderRef`] and [`summary::HeaderSummary`] still read the metadata of a voice.
+//!
+//! Parsing never panics on malformed input: every failure is reported as an [`error::Error`].
+//! The `fuzz/` directory holds the `cargo fuzz` targets that keep it that way.
+//! Untrusted files read from a reader should also bound what their sizes can make the parser
+//! buffer, with [`de::Deserializer::max_allocation`].
+#[cfg(feature = "accounting")]
+pub mod accounting;
+#[cfg(feature = "alloc")]
+pub mod addenda;
+pub mod array;
+#[cfg(all(feature = "std", feature = "alloc"))]
+pub mod audit;
+pub mod baked;
+#[cfg(feature = "alloc")]
+pub mod best_effort;
+#[cfg(feature = "alloc")]
+pub mod build;
 #[cfg(feature = "alloc")]
+pub mod codegen;
+pub mod container;
 pub mod date;
 pub mod de;
+#[cfg(feature = "alloc")]
+pub mod decode;
+#[cfg(feature = "alloc")]
+pub mod diagnostic;
+#[cfg(feature = "alloc")]
+pub mod diff;
+#[cfg(feature = "erased")]
+pub mod erased;
 pub mod error;
+#[cfg(all(feature = "std", feature = "alloc"))]
+pub mod export;
+#[cfg(feature = "alloc")]
+pub mod feature;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(all(feature = "std", feature = "alloc"))]
+pub mod file;
 pub mod gender;
+#[cfg(feature = "alloc")]
+pub mod infer;
+pub mod input;
+#[cfg(feature = "alloc")]
+pub mod inspect;
+pub mod integrity;
+#[cfg(feature = "alloc")]
+pub mod lazy;
+#[cfg(feature = "alloc")]
+pub mod lexicon;
+pub mod preflight;
+pub mod raw;
+#[cfg(all(feature = "std", feature = "alloc"))]
+pub mod registry;
+#[cfg(feature = "alloc")]
+pub mod report;
+#[cfg(feature = "alloc")]
+pub mod sections;
+#[cfg(feature = "alloc")]
 pub mod ser;
+pub mod summary;
+#[cfg(feature = "alloc")]
+pub mod track;
+pub mod trailer;
+#[cfg(feature = "alloc")]
+pub mod validate;
+#[cfg(feature = "alloc")]
+pub mod value;
+pub mod version;
+pub use date::BuildDate;
 pub use gender::*;
+pub use version::FormatVersion;
 #[cfg(feature = "alloc")]
 pub mod header;
+pub mod header_ref;
+#[cfg(all(feature = "std", feature = "alloc"))]
+pub use file::{read_voice, read_voice_header, write_voice};
 #[cfg(feature = "alloc")]
 pub use header::*;
+pub use header_ref::{FeaturesRef, HeaderRef};
+
+#[cfg(feature = "alloc")]
+pub mod model;
+#[cfg(feature = "alloc")]
+pub mod normalize;
 
 #[cfg(feature = "alloc")]
 pub mod voice;
+#[cfg(feature = "watch")]
+pub mod watch;
+
+#[cfg(all(test, feature = "differential"))]
+mod differential;
 
 #[cfg(feature = "std")]
 extern crate std;
+
+/// The magic every CG voice file starts with, including its null terminator.
+pub const FLITEVOX_MAGIC: &[u8] = b"CMU_FLITE_CG_VOXDATA-v2.0\0";
+
+// Model shapes above this carry quantization tables after `model_range`, whose values the
+// frames index instead of storing `u16` cells.
+pub(crate) const MODEL_SHAPE_BASE_MINRANGE: u32 = 1;
+
+/// Check whether `input` starts with the magic of a version this crate reads: [`FLITEVOX_MAGIC`],
+/// or that of another v1.x or v2.x [`FormatVersion`].
+///
+/// This is a `const fn`, so firmware can reject a voice blob pulled in with `include_bytes!` at
+/// compile time, and it is the same check the deserializer performs at runtime.
+pub const fn is_flitevox(input: &[u8]) -> bool {
+    FormatVersion::detect(input).is_some()
+}
+
+#[test]
+fn test_is_flitevox() {
+    const _: () = assert!(is_flitevox(include_bytes!("../data/cmu_us_slt.flitevox")));
+    assert!(!is_flitevox(b"CMU_FLITE_CG_VOXDATA-v2.0"));
+    assert!(is_flitevox(b"CMU_FLITE_CG_VOXDATA-v1.5\0"));
+    assert!(!is_flitevox(b"RIFF\0\0\0\0WAVEfmt "));
+    assert!(!is_flitevox(b""));
+}
@@ -0,0 +1,440 @@
+//! The engine-facing model of a clustergen voice.
+//!
+//! The types in [`voice`](crate::voice) mirror the file layout, and change whenever support for
+//! the format does. The types here are what a synthesis engine should build on instead: they
+//! are decoupled from the on-disk representation, every struct and enum is `#[non_exhaustive]`,
+//! and they only ever grow within a major version.
+
+use crate::voice::{Array2d, CstVal, TreeDb, Trees};
+
+/// Everything a clustergen synthesizer needs from a voice.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct CgModel {
+    pub name: String,
+    pub sample_rate: u32,
+    /// Seconds between two frames.
+    pub frame_advance: f32,
+    pub f0_mean: f32,
+    pub f0_stddev: f32,
+    /// Names of the HMM states (`aa_1`, `aa_2`, ...). The per-state trees are in this order.
+    pub state_names: Vec<String>,
+    /// For every f0 model, one tree per state.
+    pub f0_trees: Vec<Vec<Cart>>,
+    /// For every parameter model, one tree per state. Leaves hold frame indices into the
+    /// matching entry of `param_models`.
+    pub param_trees: Vec<Vec<Cart>>,
+    pub param_models: Vec<ParamModel>,
+    pub quantization: Quantization,
+    pub dur_models: Vec<DurModel>,
+    pub phone_states: PhoneStates,
+    pub synthesis: Synthesis,
+}
+
+impl CgModel {
+    /// Convert a parsed voice. Arrays are read as little-endian.
+    pub fn from_voice(voice: &TreeDb) -> Self {
+        let body = &voice.body;
+        let carts = |models: &[Trees]| -> Vec<Vec<Cart>> {
+            models
+                .iter()
+                .map(|model| model.0.iter().map(Cart::from_raw).collect())
+                .collect()
+        };
+        CgModel {
+            name: voice.header.name.clone(),
+            sample_rate: body.sample_rate.max(0) as u32,
+            frame_advance: body.frame_advance,
+            f0_mean: body.f0_mean,
+            f0_stddev: body.f0_stddev,
+            state_names: body.db_types.clone(),
+            f0_trees: carts(&body.f0_trees),
+            param_trees: carts(&body.param_trees),
+            param_models: body
+                .param_models
+                .iter()
+                .map(|model| ParamModel {
+                    num_channels: model.0.max(0) as usize,
+                    frames: model.2.clone(),
+                })
+                .collect(),
+            quantization: Quantization {
+                model_shape: voice.header.features.model_shape,
+                model_min: body.model_min.f32s().collect(),
+                model_range: body.model_range.f32s().collect(),
+                qtables: body
+                    .qtables
+                    .iter()
+                    .map(|tables| tables.rows().map(|table| table.f32s().collect()).collect())
+                    .collect(),
+            },
+            dur_models: body
+                .dur_models
+                .iter()
+                .map(|model| DurModel {
+                    stats: model
+                        .0
+                        .iter()
+                        .map(|stat| DurStat {
+                            phone: stat.2.clone(),
+                            mean: stat.0,
+                            stddev: stat.1,
+                        })
+                        .collect(),
+                    tree: Cart::from_raw(&model.1),
+                })
+                .collect(),
+            phone_states: PhoneStates {
+                phones: body
+                    .phone_states
+                    .iter()
+                    .filter_map(|states| states.split_first())
+                    .map(|(phone, states)| PhoneState {
+                        phone: phone.clone(),
+                        states: states.to_vec(),
+                    })
+                    .collect(),
+            },
+            synthesis: Synthesis {
+                do_mlpg: body.do_mlpg != 0,
+                dynwin: body.dynwin.f32s().collect(),
+                mlsa_alpha: body.mlsa_alpha,
+                mlsa_beta: body.mlsa_beta,
+                multimodel: body.multimodel != 0,
+                mixed_excitation: body.mixed_excitation != 0,
+                me_filters: body.me_h.rows().map(|row| row.f64s().collect()).collect(),
+                gain: body.gain,
+            },
+        }
+    }
+}
+
+/// A value held by a tree node.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Value {
+    Int(i32),
+    Float(f32),
+    Str(String),
+}
+impl Value {
+    fn as_f32(&self) -> Option<f32> {
+        match self {
+            Value::Int(i) => Some(*i as f32),
+            Value::Float(f) => Some(*f),
+            Value::Str(s) => s.parse().ok(),
+        }
+    }
+}
+impl From<&CstVal> for Value {
+    fn from(val: &CstVal) -> Value {
+        match val {
+            CstVal::Int(i) | CstVal::Other(_, i) => Value::Int(*i),
+            CstVal::Float(f) => Value::Float(*f),
+            CstVal::Str(s) => Value::Str(s.clone()),
+        }
+    }
+}
+
+/// The question a tree node asks about a feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CartOp {
+    Is,
+    In,
+    Less,
+    Greater,
+    Matches,
+    /// Not a question: the node is a leaf and its value is the answer.
+    Leaf,
+    Other(u8),
+}
+impl From<u8> for CartOp {
+    fn from(op: u8) -> CartOp {
+        match op {
+            0 => CartOp::Is,
+            1 => CartOp::In,
+            2 => CartOp::Less,
+            3 => CartOp::Greater,
+            4 => CartOp::Matches,
+            255 => CartOp::Leaf,
+            other => CartOp::Other(other),
+        }
+    }
+}
+impl From<CartOp> for u8 {
+    fn from(op: CartOp) -> u8 {
+        match op {
+            CartOp::Is => 0,
+            CartOp::In => 1,
+            CartOp::Less => 2,
+            CartOp::Greater => 3,
+            CartOp::Matches => 4,
+            CartOp::Leaf => 255,
+            CartOp::Other(other) => other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct CartNode {
+    /// Index into [`Cart::features`].
+    pub feature: u8,
+    pub op: CartOp,
+    /// The node to continue with when the question is answered with no; yes is always the next
+    /// node.
+    pub no_node: u16,
+    pub value: Value,
+}
+
+/// A classification and regression tree, as flite stores them.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct Cart {
+    pub nodes: Vec<CartNode>,
+    /// The feature names the nodes ask about.
+    pub features: Vec<String>,
+}
+impl Cart {
+    fn from_raw(tree: &crate::voice::Tree) -> Cart {
+        Cart {
+            nodes: tree
+                .0
+                .iter()
+                .map(|node| CartNode {
+                    feature: node.0,
+                    op: node.1.into(),
+                    no_node: node.2,
+                    value: (&node.3).into(),
+                })
+                .collect(),
+            features: tree.1 .0.clone(),
+        }
+    }
+
+    /// Walk the tree from the root, asking `feature` for the value of every feature a node
+    /// questions, and return the value of the leaf that is reached.
+    ///
+    /// `Is`, `Less` and `Greater` are supported; any other question is answered with no.
+    /// Returns `None` for malformed trees.
+    pub fn interpret(&self, mut feature: impl FnMut(&str) -> Value) -> Option<&Value> {
+        let mut idx = 0;
+        // every step moves to a different node, so a well formed tree ends within this many
+        for _ in 0..=self.nodes.len() {
+            let node = self.nodes.get(idx)?;
+            if node.op == CartOp::Leaf {
+                return Some(&node.value);
+            }
+            let value = feature(self.features.get(usize::from(node.feature))?.as_str());
+            let yes = match node.op {
+                CartOp::Is => match (&value, &node.value) {
+                    (Value::Str(a), Value::Str(b)) => a == b,
+                    (a, b) => a.as_f32().is_some() && a.as_f32() == b.as_f32(),
+                },
+                CartOp::Less => {
+                    matches!((value.as_f32(), node.value.as_f32()), (Some(a), Some(b)) if a < b)
+                }
+                CartOp::Greater => {
+                    matches!((value.as_f32(), node.value.as_f32()), (Some(a), Some(b)) if a > b)
+                }
+                _ => false,
+            };
+            idx = if yes {
+                idx + 1
+            } else {
+                usize::from(node.no_node)
+            };
+        }
+        None
+    }
+}
+
+/// The frames of one parameter model.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct ParamModel {
+    pub num_channels: usize,
+    /// One row per frame, exactly as stored: little-endian `u16` cells per channel for the base
+    /// model shape, table indices for quantized shapes. All rows share one buffer.
+    pub frames: Array2d,
+}
+
+/// How the stored frame values map back to parameter values.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct Quantization {
+    pub model_shape: u32,
+    /// Per channel minimum.
+    pub model_min: Vec<f32>,
+    /// Per channel range.
+    pub model_range: Vec<f32>,
+    /// Only for shapes above 1: per parameter model and channel, the values the stored bytes
+    /// index.
+    pub qtables: Vec<Vec<Vec<f32>>>,
+}
+impl Quantization {
+    /// Map a stored 16 bit value of `channel` back to its parameter value, the way flite does
+    /// for the base model shape. Returns `None` if the channel is out of range.
+    pub fn dequantize(&self, channel: usize, raw: u16) -> Option<f32> {
+        let min = self.model_min.get(channel)?;
+        let range = self.model_range.get(channel)?;
+        Some(min + (f32::from(raw) / 65535.0) * range)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct DurStat {
+    pub phone: String,
+    pub mean: f32,
+    pub stddev: f32,
+}
+
+/// The duration statistics and tree of one duration model.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct DurModel {
+    pub stats: Vec<DurStat>,
+    pub tree: Cart,
+}
+impl DurModel {
+    pub fn stat(&self, phone: &str) -> Option<&DurStat> {
+        self.stats.iter().find(|stat| stat.phone == phone)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct PhoneState {
+    pub phone: String,
+    /// The HMM states of the phone, in order.
+    pub states: Vec<String>,
+}
+
+/// Which HMM states make up each phone.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct PhoneStates {
+    pub phones: Vec<PhoneState>,
+}
+impl PhoneStates {
+    pub fn states(&self, phone: &str) -> Option<&[String]> {
+        self.phones
+            .iter()
+            .find(|state| state.phone == phone)
+            .map(|state| state.states.as_slice())
+    }
+}
+
+/// Settings of the vocoder.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct Synthesis {
+    /// Whether the frames hold deltas, to be smoothed with maximum likelihood parameter
+    /// generation.
+    pub do_mlpg: bool,
+    /// The delta window.
+    pub dynwin: Vec<f32>,
+    pub mlsa_alpha: f32,
+    pub mlsa_beta: f32,
+    pub multimodel: bool,
+    pub mixed_excitation: bool,
+    /// The mixed excitation filters, one row of coefficients per band.
+    pub me_filters: Vec<Vec<f64>>,
+    pub gain: f32,
+}
+
+#[test]
+fn test_interpret() {
+    // is the phone "n"? if so 1, otherwise is the position below 0.5? if so 2, otherwise 3
+    let cart = Cart {
+        nodes: vec![
+            CartNode {
+                feature: 0,
+                op: CartOp::Is,
+                no_node: 2,
+                value: Value::Str("n".to_string()),
+            },
+            CartNode {
+                feature: 255,
+                op: CartOp::Leaf,
+                no_node: 0,
+                value: Value::Int(1),
+            },
+            CartNode {
+                feature: 1,
+                op: CartOp::Less,
+                no_node: 4,
+                value: Value::Float(0.5),
+            },
+            CartNode {
+                feature: 255,
+                op: CartOp::Leaf,
+                no_node: 0,
+                value: Value::Int(2),
+            },
+            CartNode {
+                feature: 255,
+                op: CartOp::Leaf,
+                no_node: 0,
+                value: Value::Int(3),
+            },
+        ],
+        features: vec!["name".to_string(), "position".to_string()],
+    };
+    let features = |name: &str, position: f32| {
+        let name = name.to_string();
+        move |feature: &str| match feature {
+            "name" => Value::Str(name.clone()),
+            _ => Value::Float(position),
+        }
+    };
+    assert_eq!(cart.interpret(features("n", 0.9)), Some(&Value::Int(1)));
+    assert_eq!(cart.interpret(features("t", 0.1)), Some(&Value::Int(2)));
+    assert_eq!(cart.interpret(features("t", 0.9)), Some(&Value::Int(3)));
+
+    // a node which points back at itself
+    let mut cycle = cart.clone();
+    cycle.nodes[2].no_node = 2;
+    assert_eq!(cycle.interpret(features("t", 0.9)), None);
+}
+
+#[test]
+fn test_dequantize() {
+    let quantization = Quantization {
+        model_shape: 1,
+        model_min: vec![-1.0, 100.0],
+        model_range: vec![2.0, 50.0],
+        qtables: Vec::new(),
+    };
+    assert_eq!(quantization.dequantize(0, 0), Some(-1.0));
+    assert_eq!(quantization.dequantize(0, u16::MAX), Some(1.0));
+    assert_eq!(quantization.dequantize(1, u16::MAX), Some(150.0));
+    assert_eq!(quantization.dequantize(2, 0), None);
+}
+
+#[test]
+fn test_from_voice() {
+    use crate::de::from_bytes;
+    let voice: TreeDb = from_bytes(include_bytes!("../data/cmu_us_slt.flitevox")).unwrap();
+    let model = CgModel::from_voice(&voice);
+    assert_eq!(model.frame_advance, 0.005);
+    assert_eq!(model.param_trees.len(), 3);
+    assert_eq!(model.f0_trees[0][0].nodes.len(), 17);
+    assert_eq!(model.param_models[0].num_channels, 114);
+    assert_eq!(model.param_models[0].frames.len(), 30238);
+    assert_eq!(model.param_models[0].frames, voice.body.param_models[0].2);
+    assert_eq!(model.quantization.model_min.len(), 114);
+    assert!(model.quantization.qtables[0]
+        .iter()
+        .all(|table| table.len() == 256));
+    assert_eq!(model.dur_models[0].stat("pau").unwrap().mean, 0.2);
+    assert_eq!(
+        model.phone_states.states("aa").unwrap(),
+        ["aa_1", "aa_2", "aa_3"]
+    );
+    assert_eq!(model.synthesis.dynwin, [-0.5, 0.0, 0.5]);
+    assert_eq!(model.synthesis.me_filters.len(), 5);
+    assert_eq!(model.synthesis.gain, 1.5);
+}
@@ -0,0 +1,88 @@
+//! An opt-in pass over the free-form strings of a [`Header`], for when they are about to be used
+//! as file names or UI strings.
+
+use crate::Header;
+
+/// A field which was changed by [`normalize`].
+#[derive(Debug, PartialEq)]
+pub struct Normalized {
+    /// The feature name of the field, or `"name"` for the voice name.
+    pub field: &'static str,
+    /// The value before normalization.
+    pub original: String,
+}
+
+/// What [`normalize`] changed, and what it could not fix.
+#[derive(Debug, PartialEq, Default)]
+pub struct NormalizeReport {
+    pub normalized: Vec<Normalized>,
+    /// Fields which still contain characters outside of printable ASCII.
+    pub non_printable: Vec<&'static str>,
+}
+impl NormalizeReport {
+    /// `true` if nothing was changed and every field is printable ASCII.
+    pub fn is_clean(&self) -> bool {
+        self.normalized.is_empty() && self.non_printable.is_empty()
+    }
+}
+
+/// Trim surrounding whitespace and strip control characters from the voice name and the string
+/// features, reporting every field that was touched.
+pub fn normalize(header: &mut Header) -> NormalizeReport {
+    let mut report = NormalizeReport::default();
+    let Header { features, name, .. } = header;
+    for (field, value) in [
+        ("name", name),
+        ("language", &mut features.language),
+        ("country", &mut features.country),
+        ("variant", &mut features.variant),
+        ("description", &mut features.description),
+        ("copyright", &mut features.copyright),
+    ] {
+        normalize_field(field, value, &mut report);
+    }
+    report
+}
+
+fn normalize_field(field: &'static str, value: &mut String, report: &mut NormalizeReport) {
+    let cleaned: String = value
+        .trim_matches(|c: char| c.is_whitespace() || c.is_control())
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect();
+    if cleaned != *value {
+        let original = core::mem::replace(value, cleaned);
+        report.normalized.push(Normalized { field, original });
+    }
+    if !value.chars().all(|c| c == ' ' || c.is_ascii_graphic()) {
+        report.non_printable.push(field);
+    }
+}
+
+#[test]
+fn test_normalize() {
+    use crate::de::from_bytes;
+    let mut header = from_bytes::<Header>(include_bytes!("../data/cmu_us_slt.flitevox")).unwrap();
+    assert!(normalize(&mut header).is_clean());
+
+    header.name = " cmu_us_slt\n".to_string();
+    header.features.description = "unk\x07nown".to_string();
+    header.features.copyright = "© CMU".to_string();
+    let report = normalize(&mut header);
+    assert_eq!(header.name, "cmu_us_slt");
+    assert_eq!(header.features.description, "unknown");
+    assert_eq!(
+        report.normalized,
+        vec![
+            Normalized {
+                field: "name",
+                original: " cmu_us_slt\n".to_string()
+            },
+            Normalized {
+                field: "description",
+                original: "unk\x07nown".to_string()
+            },
+        ]
+    );
+    assert_eq!(report.non_printable, vec!["copyright"]);
+}
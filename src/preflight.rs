@@ -0,0 +1,418 @@
+//! A cheap structural scan of a CG voice file.
+//!
+//! [`preflight`] walks the file the same way flite's loader does, but only looks at the declared
+//! sizes and counts: nothing is allocated, decoded or copied. This rejects truncated or corrupt
+//! files before any heavyweight parsing is attempted.
+
+use core::fmt::{self, Display, Formatter};
+
+use crate::header_ref::{
+    DEFAULT_MODEL_SHAPE, DEFAULT_NUM_DUR_MODELS, DEFAULT_NUM_F0_MODELS, DEFAULT_NUM_PARAM_MODELS,
+};
+use crate::{is_flitevox, FLITEVOX_MAGIC, MODEL_SHAPE_BASE_MINRANGE};
+
+const END_OF_FEATURES: &[u8] = b"end_of_features\0";
+// Value type tag of a string in a tree node; every other type is a 4 byte cell.
+const CST_VAL_TYPE_STRING: u16 = 5;
+
+/// The first place where the declared structure of a file and its actual length disagree.
+#[derive(Debug, PartialEq)]
+pub enum Inconsistency {
+    /// The file does not start with the CG voice magic.
+    InvalidHeader,
+    /// An entry at `offset` declares more bytes than are left in the input.
+    Overrun {
+        offset: usize,
+        section: &'static str,
+        declared: usize,
+        remaining: usize,
+    },
+    /// A feature the body layout depends on is not a number.
+    BadFeature {
+        offset: usize,
+        feature: &'static str,
+    },
+    /// A feature the body layout depends on appears again at `offset`.
+    DuplicateFeature {
+        offset: usize,
+        feature: &'static str,
+    },
+    /// The structure ends at `offset`, but the input does not.
+    TrailingBytes { offset: usize, remaining: usize },
+}
+impl Display for Inconsistency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Inconsistency::InvalidHeader => f.write_str("not a CG voice file"),
+            Inconsistency::Overrun {
+                offset,
+                section,
+                declared,
+                remaining,
+            } => write!(
+                f,
+                "{section} at offset {offset} declares {declared} bytes, but only {remaining} remain"
+            ),
+            Inconsistency::BadFeature { offset, feature } => {
+                write!(f, "feature {feature} at offset {offset} is not a number")
+            }
+            Inconsistency::DuplicateFeature { offset, feature } => {
+                write!(f, "feature {feature} appears again at offset {offset}")
+            }
+            Inconsistency::TrailingBytes { offset, remaining } => {
+                write!(f, "{remaining} unexpected bytes after the end of the voice at offset {offset}")
+            }
+        }
+    }
+}
+
+type Result<T> = core::result::Result<T, Inconsistency>;
+
+/// Walk every size prefix and count in `input`, returning the first one which does not fit.
+/// Trailers are skipped, not verified.
+pub fn preflight(input: &[u8]) -> Result<()> {
+    let (input, _) = crate::trailer::strip_trailers(input);
+    let mut walker = Walker::new(input, ())?;
+    walker.voice()?;
+    walker.finish()
+}
+
+/// Like [`preflight`], with the inconsistency as a [`Diagnostic`](crate::diagnostic::Diagnostic).
+#[cfg(feature = "alloc")]
+pub fn diagnose(input: &[u8]) -> Option<crate::diagnostic::Diagnostic> {
+    preflight(input).err().as_ref().map(Into::into)
+}
+
+/// Told about every piece of the file the [`Walker`] steps over.
+pub(crate) trait Sink {
+    /// `bytes` of `section` were read, which will need `allocations` heap allocations once decoded.
+    fn account(&mut self, section: &'static str, bytes: usize, allocations: usize);
+    /// `bytes` of `section` were read which flite keeps inline, in the struct which owns them.
+    fn inline(&mut self, _section: &'static str, _bytes: usize) {}
+    /// `bytes` of `section` were read from the file.
+    fn read(&mut self, _section: &'static str, _bytes: usize) {}
+    /// A tree of `section` starts.
+    fn tree(&mut self, _section: &'static str) {}
+    /// A number of `section` at `offset` was read; sizes and counts included.
+    fn int(&mut self, _section: &'static str, _offset: usize, _value: usize) {}
+    /// A count of `section` at `offset` was read.
+    fn list(&mut self, _section: &'static str, _offset: usize, _len: usize) {}
+    /// The count of an array of trees at `offset` was read.
+    fn trees(&mut self, _section: &'static str, _offset: usize, _len: usize) {}
+    /// A string at `offset` was read: its bytes, including the null terminator if it has one.
+    fn string(&mut self, _section: &'static str, _offset: usize, _bytes: &[u8]) {}
+}
+impl Sink for () {
+    fn account(&mut self, _section: &'static str, _bytes: usize, _allocations: usize) {}
+}
+
+/// The counts from the feature block which decide the shape of the body, with flite's defaults.
+struct Shape {
+    num_f0_models: usize,
+    num_param_models: usize,
+    num_dur_models: usize,
+    model_shape: usize,
+}
+
+pub(crate) struct Walker<'a, S = ()> {
+    input: &'a [u8],
+    pos: usize,
+    byteswapped: bool,
+    sink: S,
+}
+impl<'a, S: Sink> Walker<'a, S> {
+    pub(crate) fn new(input: &'a [u8], sink: S) -> Result<Self> {
+        if !is_flitevox(input) {
+            return Err(Inconsistency::InvalidHeader);
+        }
+        let mut walker = Walker {
+            input,
+            pos: FLITEVOX_MAGIC.len(),
+            byteswapped: false,
+            sink,
+        };
+        walker.byteswapped = walker.int("endianness")? != 1;
+        Ok(walker)
+    }
+    fn take(&mut self, offset: usize, declared: usize, section: &'static str) -> Result<&'a [u8]> {
+        let remaining = self.input.len() - self.pos;
+        if declared > remaining {
+            return Err(Inconsistency::Overrun {
+                offset,
+                section,
+                declared,
+                remaining,
+            });
+        }
+        let bytes = &self.input[self.pos..self.pos + declared];
+        self.pos += declared;
+        self.sink.read(section, declared);
+        Ok(bytes)
+    }
+    // `declared` bytes which end up in a buffer of their own once decoded.
+    fn take_heap(
+        &mut self,
+        offset: usize,
+        declared: usize,
+        section: &'static str,
+    ) -> Result<&'a [u8]> {
+        let bytes = self.take(offset, declared, section)?;
+        self.sink.account(section, declared, 0);
+        Ok(bytes)
+    }
+    fn int(&mut self, section: &'static str) -> Result<usize> {
+        let offset = self.pos;
+        let bytes: [u8; 4] = self.take(offset, 4, section)?.try_into().unwrap();
+        self.sink.inline(section, 4);
+        let val = if self.byteswapped {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        };
+        // a size which doesn't fit in a usize can't fit in the input either
+        let val = usize::try_from(val).unwrap_or(usize::MAX);
+        self.sink.int(section, offset, val);
+        Ok(val)
+    }
+    fn entry(&mut self, section: &'static str) -> Result<&'a [u8]> {
+        let offset = self.pos;
+        let size = self.int(section)?;
+        self.sink.account(section, 0, 1);
+        self.take_heap(offset, size, section)
+    }
+    fn string(&mut self, section: &'static str) -> Result<&'a [u8]> {
+        let offset = self.pos;
+        let bytes = self.entry(section)?;
+        self.sink.string(section, offset, bytes);
+        Ok(bytes)
+    }
+    fn list(&mut self, section: &'static str) -> Result<usize> {
+        let offset = self.pos;
+        let len = self.int(section)?;
+        self.sink.list(section, offset, len);
+        self.sink.account(
+            section,
+            len.saturating_mul(core::mem::size_of::<usize>()),
+            1,
+        );
+        Ok(len)
+    }
+    fn entries(&mut self, section: &'static str) -> Result<()> {
+        for _ in 0..self.list(section)? {
+            self.entry(section)?;
+        }
+        Ok(())
+    }
+    fn strings(&mut self, section: &'static str) -> Result<()> {
+        for _ in 0..self.list(section)? {
+            self.string(section)?;
+        }
+        Ok(())
+    }
+    fn array_2d(&mut self, section: &'static str) -> Result<()> {
+        self.entries(section)
+    }
+    fn tree(&mut self, section: &'static str) -> Result<()> {
+        self.sink.tree(section);
+        for _ in 0..self.list(section)? {
+            // feat, op and no_node, in the node vector
+            self.take_heap(self.pos, 4, section)?;
+            self.value(section)?;
+        }
+        self.strings(section)
+    }
+    // A type tag and the value after it: a string, or a cell for any other type.
+    fn value(&mut self, section: &'static str) -> Result<()> {
+        let tag: [u8; 2] = self.take_heap(self.pos, 2, section)?.try_into().unwrap();
+        let tag = if self.byteswapped {
+            u16::from_be_bytes(tag)
+        } else {
+            u16::from_le_bytes(tag)
+        };
+        if tag == CST_VAL_TYPE_STRING {
+            self.string(section)?;
+        } else {
+            self.int(section)?;
+        }
+        Ok(())
+    }
+    fn trees(&mut self, section: &'static str) -> Result<()> {
+        let offset = self.pos;
+        let len = self.list(section)?;
+        self.sink.trees(section, offset, len);
+        for _ in 0..len {
+            self.tree(section)?;
+        }
+        Ok(())
+    }
+    fn features(&mut self) -> Result<Shape> {
+        let mut shape = Shape {
+            num_f0_models: DEFAULT_NUM_F0_MODELS as usize,
+            num_param_models: DEFAULT_NUM_PARAM_MODELS as usize,
+            num_dur_models: DEFAULT_NUM_DUR_MODELS as usize,
+            model_shape: DEFAULT_MODEL_SHAPE as usize,
+        };
+        // a bit for every count read so far
+        let mut seen = 0u8;
+        loop {
+            let key_offset = self.pos;
+            let key = self.string("features")?;
+            let offset = self.pos;
+            let value = self.string("features")?;
+            let (slot, feature, bit) = match key {
+                END_OF_FEATURES => return Ok(shape),
+                b"num_f0_models\0" => (&mut shape.num_f0_models, "num_f0_models", 1),
+                b"num_param_models\0" => (&mut shape.num_param_models, "num_param_models", 2),
+                b"num_dur_models\0" => (&mut shape.num_dur_models, "num_dur_models", 4),
+                b"model_shape\0" => (&mut shape.model_shape, "model_shape", 8),
+                _ => continue,
+            };
+            if seen & bit != 0 {
+                let offset = key_offset;
+                return Err(Inconsistency::DuplicateFeature { offset, feature });
+            }
+            seen |= bit;
+            *slot = value
+                .strip_suffix(b"\0")
+                .and_then(|digits| core::str::from_utf8(digits).ok())
+                .and_then(|digits| digits.parse().ok())
+                .ok_or(Inconsistency::BadFeature { offset, feature })?;
+        }
+    }
+    // Mirrors `cst_cg_load_db` in flite.
+    pub(crate) fn voice(&mut self) -> Result<()> {
+        let shape = self.features()?;
+        self.string("name")?;
+        self.strings("types")?;
+        for section in ["num_types", "sample_rate", "f0_mean", "f0_stddev"] {
+            self.int(section)?;
+        }
+        for _ in 0..shape.num_f0_models {
+            self.trees("f0_trees")?;
+        }
+        for _ in 0..shape.num_param_models {
+            self.trees("param_trees")?;
+        }
+        let spamf0 = self.int("spamf0")? != 0;
+        if spamf0 {
+            self.tree("spamf0_accent_tree")?;
+            self.tree("spamf0_phrase_tree")?;
+        }
+        for _ in 0..shape.num_param_models {
+            self.int("num_channels")?;
+            self.int("num_frames")?;
+            self.array_2d("model_vectors")?;
+        }
+        if spamf0 {
+            self.int("num_channels_spamf0_accent")?;
+            self.int("num_frames_spamf0_accent")?;
+            self.array_2d("spamf0_accent_vectors")?;
+        }
+        self.entry("model_min")?;
+        self.entry("model_range")?;
+        if shape.model_shape > MODEL_SHAPE_BASE_MINRANGE as usize {
+            for _ in 0..shape.num_param_models {
+                self.array_2d("qtable")?;
+            }
+        }
+        self.int("frame_advance")?;
+        for _ in 0..shape.num_dur_models {
+            for _ in 0..self.list("dur_stats")? {
+                self.int("dur_stats")?;
+                self.int("dur_stats")?;
+                self.string("dur_stats")?;
+            }
+            self.tree("dur_cart")?;
+        }
+        for _ in 0..self.list("phone_states")? {
+            self.strings("phone_states")?;
+        }
+        self.int("do_mlpg")?;
+        self.entry("dynwin")?;
+        for section in [
+            "dynwinsize",
+            "mlsa_alpha",
+            "mlsa_beta",
+            "multimodel",
+            "mixed_excitation",
+            "me_num",
+            "me_order",
+        ] {
+            self.int(section)?;
+        }
+        self.array_2d("me_h")?;
+        self.int("spamf0")?;
+        self.int("gain")?;
+        Ok(())
+    }
+    /// Check that the walk consumed all of the input.
+    pub(crate) fn end(&self) -> Result<()> {
+        match self.input.len() - self.pos {
+            0 => Ok(()),
+            remaining => Err(Inconsistency::TrailingBytes {
+                offset: self.pos,
+                remaining,
+            }),
+        }
+    }
+    /// Check that the walk consumed all of the input, and hand back the sink.
+    pub(crate) fn finish(self) -> Result<S> {
+        self.end()?;
+        Ok(self.sink)
+    }
+    /// Hand back the sink, wherever the walk stopped.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn into_sink(self) -> S {
+        self.sink
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_preflight() {
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    assert_eq!(preflight(data), Ok(()));
+    assert_eq!(
+        preflight(&data[..data.len() - 1]),
+        Err(Inconsistency::Overrun {
+            offset: data.len() - 4,
+            section: "gain",
+            declared: 4,
+            remaining: 3,
+        })
+    );
+    let mut data = data.to_vec();
+    data.push(0);
+    assert_eq!(
+        preflight(&data),
+        Err(Inconsistency::TrailingBytes {
+            offset: data.len() - 1,
+            remaining: 1,
+        })
+    );
+    // the size of the "language" key
+    data[0x1e..0x22].copy_from_slice(&0x00ff_ffffu32.to_le_bytes());
+    assert_eq!(
+        preflight(&data),
+        Err(Inconsistency::Overrun {
+            offset: 0x1e,
+            section: "features",
+            declared: 0x00ff_ffff,
+            remaining: data.len() - 0x22,
+        })
+    );
+    assert_eq!(preflight(b"RIFF"), Err(Inconsistency::InvalidHeader));
+
+    // a count which appears twice leaves the shape of the body in doubt
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let end = 394 - 55;
+    let repeat = b"\x0c\0\0\0model_shape\0\x02\0\0\x003\0";
+    let repeated = [&data[..end], repeat, &data[end..]].concat();
+    assert_eq!(
+        preflight(&repeated),
+        Err(Inconsistency::DuplicateFeature {
+            offset: end,
+            feature: "model_shape",
+        })
+    );
+}
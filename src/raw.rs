@@ -0,0 +1,114 @@
+//! The entries of a dump, uninterpreted.
+//!
+//! [`RawEntries`] walks a dump with the cursor of the [`Deserializer`], but without a type to
+//! say what comes next: every entry which looks like a string is one, and anything else is a
+//! single 4 byte cell. That is enough to hexdump or inspect a voice this crate can't read, and
+//! [`RawEntries::deserializer`] hands over to a `DeserializeSeed` for sections it does not model.
+
+use crate::container::Container;
+use crate::de::Deserializer;
+use crate::error::Result;
+use crate::trailer::strip_trailers;
+
+/// Every entry after the magic and endianness marker, as `(offset, bytes)`: where it starts in
+/// the input, and all of its bytes, size prefix included. Integrity and digest trailers are not
+/// walked.
+pub struct RawEntries<'de> {
+    input: &'de [u8],
+    de: Deserializer<'de>,
+    failed: bool,
+}
+impl<'de> RawEntries<'de> {
+    pub fn new(input: &'de [u8]) -> Self {
+        let (input, _) = strip_trailers(input);
+        RawEntries {
+            input,
+            de: Deserializer::from_bytes(input),
+            failed: false,
+        }
+    }
+    /// Walk a dump starting with the magic of `container` instead of a voice's.
+    pub fn with_container(mut self, container: Container) -> Self {
+        self.de = self.de.with_container(container);
+        self
+    }
+    /// Where the next entry starts, once the first has been read.
+    pub fn offset(&self) -> usize {
+        self.de.offset()
+    }
+    /// A deserializer reading from the next entry on, in the byte order of the dump. Reading
+    /// with it doesn't advance the walk.
+    pub fn deserializer(&mut self) -> Result<Deserializer<'de>> {
+        self.de.fork().map_err(|e| self.de.locate_in(e, self.input))
+    }
+}
+impl<'de> Iterator for RawEntries<'de> {
+    type Item = Result<(usize, &'de [u8])>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        // the first entry starts after the magic and the endianness marker
+        if let Err(e) = self.de.validate_header() {
+            self.failed = true;
+            return Some(Err(self.de.locate_in(e, self.input)));
+        }
+        let start = self.de.offset();
+        match self.de.next_raw() {
+            Ok(Some(len)) => Some(Ok((start, &self.input[start..start + len]))),
+            Ok(None) => None,
+            Err(e) => {
+                self.failed = true;
+                Some(Err(self.de.locate_in(e, self.input)))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_raw_entries() {
+    use serde::Deserialize;
+
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let mut entries = RawEntries::new(data);
+    let (offset, language) = entries.next().unwrap().unwrap();
+    assert_eq!(offset, 30);
+    assert_eq!(language, b"\x09\0\0\0language\0");
+    assert_eq!(entries.nth(1).unwrap().unwrap().1, b"\x08\0\0\0country\0");
+
+    // every byte after the header is in exactly one entry
+    let mut end = 30;
+    for entry in RawEntries::new(data) {
+        let (offset, bytes) = entry.unwrap();
+        assert_eq!(offset, end);
+        end += bytes.len();
+    }
+    assert_eq!(end, data.len());
+
+    // hand over to serde at the name of the voice, after the 13 features and the end marker
+    let mut entries = RawEntries::new(&data[..394]);
+    let end_of_features = b"\x10\0\0\0end_of_features\0";
+    let key = entries.position(|e| e.unwrap().1 == end_of_features);
+    assert_eq!(key, Some(26));
+    entries.next().unwrap().unwrap();
+    let mut rest = entries.deserializer().unwrap();
+    assert_eq!(<&str>::deserialize(&mut rest).unwrap(), "cmu_us_slt");
+    assert!(entries.nth(1).is_none());
+
+    let mut truncated = RawEntries::new(&data[..32]);
+    assert!(truncated.next().unwrap().is_err());
+    assert!(truncated.next().is_none());
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_raw_entries_digests() {
+    use crate::integrity::{append_digests, Algorithm};
+
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let mut digested = data.to_vec();
+    append_digests(&mut digested, Algorithm::Crc32).unwrap();
+    crate::trailer::append_trailer(&mut digested);
+    let last = RawEntries::new(&digested).last().unwrap().unwrap();
+    assert_eq!(last.0 + last.1.len(), data.len());
+}
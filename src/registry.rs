@@ -0,0 +1,606 @@
+//! An index of the voices installed on a system.
+//!
+//! A [`Registry`] scans a list of directories, reads only the header of every voice it finds,
+//! and answers the questions a screen reader asks when offering voice choices, without opening
+//! any voice in full.
+
+extern crate std;
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::audit::{fingerprint, read_header, voice_files, ReadError, HEADER_PREFIX};
+use crate::de::from_bytes;
+use crate::sections::Sections;
+use crate::ser::to_bytes;
+use crate::Gender;
+
+// FNV-1a, 64 bit.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// The first value of a cache file; bumped whenever the layout of `VoiceInfo` changes.
+const CACHE_HEADER: &str = "serde_cst registry cache 2";
+
+/// Tells apart versions of a voice file: its length, and a hash of the bytes its header was
+/// read from.
+///
+/// This is meant for noticing that a voice was replaced, not for integrity checks.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    pub len: u64,
+    pub hash: u64,
+}
+impl Fingerprint {
+    pub(crate) fn new(len: u64, prefix: &[u8]) -> Fingerprint {
+        let hash = prefix.iter().fold(FNV_OFFSET, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+        });
+        Fingerprint { len, hash }
+    }
+}
+
+/// What the registry knows about a voice. It is cached in the format of this crate.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct VoiceInfo {
+    #[serde(with = "os_path")]
+    pub path: PathBuf,
+    pub fingerprint: Fingerprint,
+    /// When the file was last modified, if the platform knows.
+    #[serde(with = "modified")]
+    pub modified: Option<SystemTime>,
+    /// Where the body starts, right after the header.
+    pub body_offset: u64,
+    /// Where every section of the voice first appears, in file order. Empty until
+    /// [`Registry::sections`] reads the body, and kept in the cache from then on.
+    pub sections: Vec<(String, u64)>,
+    pub name: String,
+    pub language: String,
+    pub country: String,
+    pub variant: String,
+    pub gender: Gender,
+    pub age: u32,
+    pub description: String,
+}
+impl VoiceInfo {
+    /// Read the header of the voice at `path`.
+    pub fn read(path: impl Into<PathBuf>) -> Result<VoiceInfo, ReadError> {
+        let path = path.into();
+        let modified = fs::metadata(&path)?.modified().ok();
+        let (header, fingerprint, body_offset) = read_header(&path)?;
+        let features = header.features;
+        Ok(VoiceInfo {
+            path,
+            fingerprint,
+            modified,
+            body_offset,
+            sections: Vec::new(),
+            name: header.name,
+            language: features.language,
+            country: features.country,
+            variant: features.variant,
+            gender: features.gender,
+            age: features.age,
+            description: features.description,
+        })
+    }
+
+    /// Read where every section of the voice first appears, in file order. Unlike everything
+    /// else here, this reads the whole file, which must still be the one this was read from.
+    pub fn read_sections(&self) -> Result<Vec<(String, u64)>, ReadError> {
+        let data = fs::read(&self.path)?;
+        let prefix = data.get(..HEADER_PREFIX as usize).unwrap_or(&data);
+        if Fingerprint::new(data.len() as u64, prefix) != self.fingerprint {
+            let message = "the voice changed since its header was read";
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message).into());
+        }
+        let sections = Sections::new(&data)?
+            .offsets()
+            .map(|(section, offset)| (section.to_string(), offset as u64))
+            .collect();
+        Ok(sections)
+    }
+
+    /// Whether the file still has the fingerprint and modification time this was read with.
+    fn is_current(&self) -> bool {
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            return false;
+        };
+        metadata.len() == self.fingerprint.len
+            && metadata.modified().ok() == self.modified
+            && fingerprint(&self.path).is_ok_and(|fingerprint| fingerprint == self.fingerprint)
+    }
+
+    /// Language, country and variant joined with `-`, leaving out the ones which are empty or
+    /// `none`: `eng-USA`.
+    pub fn language_tag(&self) -> String {
+        let parts: Vec<&str> = [&self.language, &self.country, &self.variant]
+            .into_iter()
+            .map(|part| part.trim())
+            .filter(|part| !part.is_empty() && !part.eq_ignore_ascii_case("none"))
+            .collect();
+        parts.join("-")
+    }
+
+    /// Whether the language tag is `tag`, or more specific than it: `eng-USA` matches `eng`
+    /// and `eng-usa`, but not `en`.
+    pub fn matches_language(&self, tag: &str) -> bool {
+        let own = self.language_tag();
+        match own.get(..tag.len()) {
+            Some(prefix) if prefix.eq_ignore_ascii_case(tag) => {
+                own.len() == tag.len() || own.as_bytes()[tag.len()] == b'-'
+            }
+            _ => false,
+        }
+    }
+}
+
+// Paths are stored as the platform spells them, so ones which are not UTF-8 are kept too.
+mod os_path {
+    use core::fmt;
+    use std::path::{Path, PathBuf};
+
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::Serializer;
+    #[cfg(not(unix))]
+    use serde::{Deserialize, Serialize};
+
+    #[cfg(unix)]
+    pub fn serialize<S>(path: &Path, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use std::os::unix::ffi::OsStrExt;
+        ser.serialize_bytes(path.as_os_str().as_bytes())
+    }
+    #[cfg(unix)]
+    pub fn deserialize<'de, D>(deser: D) -> Result<PathBuf, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use std::os::unix::ffi::OsStringExt;
+        struct BytesVisitor;
+        impl Visitor<'_> for BytesVisitor {
+            type Value = Vec<u8>;
+            fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+                fmt.write_str("The bytes of a path")
+            }
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+                Ok(v.to_vec())
+            }
+        }
+        let bytes = deser.deserialize_byte_buf(BytesVisitor)?;
+        Ok(PathBuf::from(std::ffi::OsString::from_vec(bytes)))
+    }
+
+    #[cfg(windows)]
+    pub fn serialize<S>(path: &Path, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use std::os::windows::ffi::OsStrExt;
+        let wide: Vec<u16> = path.as_os_str().encode_wide().collect();
+        wide.serialize(ser)
+    }
+    #[cfg(windows)]
+    pub fn deserialize<'de, D>(deser: D) -> Result<PathBuf, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use std::os::windows::ffi::OsStringExt;
+        let wide = Vec::<u16>::deserialize(deser)?;
+        Ok(PathBuf::from(std::ffi::OsString::from_wide(&wide)))
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    pub fn serialize<S>(path: &Path, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let path = path
+            .to_str()
+            .ok_or_else(|| serde::ser::Error::custom("the path is not UTF-8"))?;
+        path.serialize(ser)
+    }
+    #[cfg(not(any(unix, windows)))]
+    pub fn deserialize<'de, D>(deser: D) -> Result<PathBuf, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deser).map(PathBuf::from)
+    }
+}
+
+// The format has no way to write `None`, so a modification time is a list of at most one: the
+// seconds and nanoseconds since the epoch.
+mod modified {
+    use std::time::{Duration, SystemTime};
+
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S>(modified: &Option<SystemTime>, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let since = modified
+            .and_then(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|since| (since.as_secs(), since.subsec_nanos()));
+        let since: Vec<(u64, u32)> = since.into_iter().collect();
+        since.serialize(ser)
+    }
+    pub fn deserialize<'de, D>(deser: D) -> Result<Option<SystemTime>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let since = Vec::<(u64, u32)>::deserialize(deser)?;
+        let Some(&(secs, nanos)) = since.first() else {
+            return Ok(None);
+        };
+        SystemTime::UNIX_EPOCH
+            .checked_add(Duration::new(secs, nanos))
+            .map(Some)
+            .ok_or_else(|| de::Error::custom("the modification time is out of range"))
+    }
+}
+
+/// The entries of a cache file. A file which cannot be parsed, or of another version, is
+/// ignored.
+fn load_cache(data: &[u8]) -> BTreeMap<PathBuf, VoiceInfo> {
+    match from_bytes::<(&str, Vec<VoiceInfo>)>(data) {
+        Ok((CACHE_HEADER, voices)) => voices
+            .into_iter()
+            .map(|voice| (voice.path.clone(), voice))
+            .collect(),
+        _ => BTreeMap::new(),
+    }
+}
+
+/// A change to a [`Registry`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// A voice was installed.
+    Added(VoiceInfo),
+    /// The voice with this name was removed, or can no longer be read.
+    Removed(String),
+    /// The voice with this name was replaced; this is its new fingerprint.
+    Changed(String, Fingerprint),
+}
+
+/// The voices found in a list of directories.
+#[derive(Debug, Default)]
+pub struct Registry {
+    dirs: Vec<PathBuf>,
+    voices: Vec<VoiceInfo>,
+}
+impl Registry {
+    /// A registry of `dirs`, in order of preference. Nothing is read until [`Registry::scan`].
+    pub fn new<P: Into<PathBuf>>(dirs: impl IntoIterator<Item = P>) -> Registry {
+        Registry {
+            dirs: dirs.into_iter().map(Into::into).collect(),
+            voices: Vec::new(),
+        }
+    }
+
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    /// Rebuild the index from the `.flitevox` files below the directories.
+    ///
+    /// Directories which do not exist are skipped, so a registry can list places voices may be
+    /// installed to later. Files whose header cannot be read are left out, and returned.
+    pub fn scan(&mut self) -> io::Result<Vec<(PathBuf, ReadError)>> {
+        self.scan_with(BTreeMap::new())
+    }
+
+    fn scan_with(
+        &mut self,
+        mut cached: BTreeMap<PathBuf, VoiceInfo>,
+    ) -> io::Result<Vec<(PathBuf, ReadError)>> {
+        let mut voices = Vec::new();
+        let mut unreadable = Vec::new();
+        for dir in &self.dirs {
+            let files = match voice_files(dir) {
+                Ok(files) => files,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+            for path in files {
+                match cached.remove(&path) {
+                    Some(voice) if voice.is_current() => voices.push(voice),
+                    _ => match VoiceInfo::read(&path) {
+                        Ok(voice) => voices.push(voice),
+                        Err(err) => unreadable.push((path, err)),
+                    },
+                }
+            }
+        }
+        self.voices = voices;
+        Ok(unreadable)
+    }
+
+    /// Like [`Registry::scan`], but take the entries of the cache at `path` whose file still has
+    /// the fingerprint and modification time it had when the cache was saved, instead of reading
+    /// them again. A missing or unreadable cache is the same as an empty one.
+    pub fn scan_cached(&mut self, path: impl AsRef<Path>) -> io::Result<Vec<(PathBuf, ReadError)>> {
+        let cached = fs::read(path)
+            .map(|data| load_cache(&data))
+            .unwrap_or_default();
+        self.scan_with(cached)
+    }
+
+    /// Save the index to `path`, for [`Registry::scan_cached`].
+    pub fn save_cache(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let data = to_bytes(&(CACHE_HEADER, &self.voices))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        // never leave a half written cache behind
+        let partial = path.with_extension("partial");
+        let mut out = File::create(&partial)?;
+        out.write_all(&data)?;
+        out.sync_all()?;
+        fs::rename(partial, path)
+    }
+
+    /// Bring the index up to date with `path` and everything below it, after it was created,
+    /// modified or removed.
+    pub fn refresh(&mut self, path: &Path) -> Vec<Event> {
+        let mut paths: Vec<PathBuf> = self
+            .voices
+            .iter()
+            .filter(|voice| voice.path.starts_with(path))
+            .map(|voice| voice.path.clone())
+            .collect();
+        if !self.dirs.iter().any(|dir| path.starts_with(dir)) {
+            // outside of the registry, only forget what was known
+        } else if path.is_dir() {
+            paths.extend(voice_files(path).unwrap_or_default());
+        } else if path.extension().is_some_and(|ext| ext == "flitevox") {
+            paths.push(path.to_path_buf());
+        }
+        paths.sort();
+        paths.dedup();
+        paths
+            .iter()
+            .filter_map(|path| self.refresh_file(path))
+            .collect()
+    }
+
+    fn refresh_file(&mut self, path: &Path) -> Option<Event> {
+        let known = self.voices.iter().position(|voice| voice.path == path);
+        let in_dirs = self.dirs.iter().any(|dir| path.starts_with(dir));
+        match (known, VoiceInfo::read(path)) {
+            (Some(idx), Ok(voice)) if in_dirs => {
+                // a new modification time alone is not worth an event
+                let changed = self.voices[idx].fingerprint != voice.fingerprint;
+                let event = Event::Changed(voice.name.clone(), voice.fingerprint);
+                self.voices[idx] = voice;
+                changed.then_some(event)
+            }
+            (Some(idx), _) => Some(Event::Removed(self.voices.remove(idx).name)),
+            (None, Ok(voice)) if in_dirs => {
+                // keep the order of the directories, then of the paths
+                let rank = |path: &Path| {
+                    let dir = self.dirs.iter().position(|dir| path.starts_with(dir));
+                    (dir, path.to_path_buf())
+                };
+                let key = rank(&voice.path);
+                let idx = self.voices.partition_point(|other| rank(&other.path) < key);
+                self.voices.insert(idx, voice.clone());
+                Some(Event::Added(voice))
+            }
+            (None, _) => None,
+        }
+    }
+
+    /// Every voice, in the order of the directories.
+    pub fn voices(&self) -> &[VoiceInfo] {
+        &self.voices
+    }
+
+    /// The [sections](VoiceInfo::sections) of the voice at `path`, read by
+    /// [`VoiceInfo::read_sections`] the first time they are asked for.
+    pub fn sections(&mut self, path: &Path) -> Result<&[(String, u64)], ReadError> {
+        let Some(voice) = self.voices.iter_mut().find(|voice| voice.path == path) else {
+            let message = "the voice is not in the registry";
+            return Err(io::Error::new(io::ErrorKind::NotFound, message).into());
+        };
+        if voice.sections.is_empty() {
+            voice.sections = voice.read_sections()?;
+        }
+        Ok(&voice.sections)
+    }
+
+    pub fn by_path(&self, path: &Path) -> Option<&VoiceInfo> {
+        self.voices.iter().find(|voice| voice.path == path)
+    }
+
+    /// The voice called `name`. If several are, the one from the earliest directory.
+    pub fn by_name(&self, name: &str) -> Option<&VoiceInfo> {
+        self.voices.iter().find(|voice| voice.name == name)
+    }
+
+    /// The voices matching the language `tag`, see [`VoiceInfo::matches_language`].
+    pub fn by_language<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a VoiceInfo> + 'a {
+        self.voices
+            .iter()
+            .filter(move |voice| voice.matches_language(tag))
+    }
+
+    pub fn by_gender(&self, gender: Gender) -> impl Iterator<Item = &VoiceInfo> + '_ {
+        self.voices
+            .iter()
+            .filter(move |voice| voice.gender == gender)
+    }
+}
+
+#[test]
+fn test_language_tag() {
+    let voice = VoiceInfo::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/data/cmu_us_slt.flitevox"
+    ))
+    .unwrap();
+    assert_eq!(voice.name, "cmu_us_slt");
+    assert_eq!(voice.language_tag(), "eng-USA");
+    assert!(voice.matches_language("eng"));
+    assert!(voice.matches_language("ENG-usa"));
+    assert!(!voice.matches_language("en"));
+    assert!(!voice.matches_language("eng-GBR"));
+}
+
+#[test]
+fn test_registry() {
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let root = std::env::temp_dir().join(format!("serde_cst_registry_{}", std::process::id()));
+    let (user, system) = (root.join("user"), root.join("system"));
+    fs::create_dir_all(&user).unwrap();
+    fs::create_dir_all(&system).unwrap();
+    fs::write(user.join("slt.flitevox"), data).unwrap();
+    fs::write(system.join("slt.flitevox"), data).unwrap();
+    fs::write(system.join("broken.flitevox"), b"RIFF").unwrap();
+    // only the header is read, so a damaged body does not hide a voice
+    let damaged = system.join("damaged.flitevox");
+    fs::write(&damaged, &data[..data.len() / 2]).unwrap();
+
+    let mut registry = Registry::new([&user, &root.join("missing"), &system]);
+    let unreadable = registry.scan().unwrap();
+    assert!(registry.sections(&damaged).is_err());
+    fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(unreadable.len(), 1);
+    assert_eq!(registry.voices().len(), 3);
+    assert!(registry.by_path(&damaged).unwrap().sections.is_empty());
+    assert_eq!(
+        registry.by_name("cmu_us_slt").unwrap().path,
+        user.join("slt.flitevox")
+    );
+    assert_eq!(registry.by_language("eng").count(), 3);
+    assert_eq!(registry.by_language("fra").count(), 0);
+    assert_eq!(registry.by_gender(Gender::Unknown).count(), 3);
+    assert_eq!(registry.by_gender(Gender::Female).count(), 0);
+    assert!(registry.by_path(&system.join("slt.flitevox")).is_some());
+}
+
+#[test]
+fn test_refresh() {
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let dir = std::env::temp_dir().join(format!("serde_cst_refresh_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut registry = Registry::new([&dir]);
+    registry.scan().unwrap();
+    assert!(registry.voices().is_empty());
+
+    let path = dir.join("slt.flitevox");
+    fs::write(&path, data).unwrap();
+    let events = registry.refresh(&path);
+    assert!(matches!(&events[..], [Event::Added(voice)] if voice.name == "cmu_us_slt"));
+    assert_eq!(registry.voices().len(), 1);
+    // nothing changed
+    assert!(registry.refresh(&path).is_empty());
+
+    // a different copyright, of the same length
+    let mut patched = data.to_vec();
+    let copyright = b"copyright\0\x08\0\0\0unknown";
+    let at = data
+        .windows(copyright.len())
+        .position(|w| w == copyright)
+        .unwrap();
+    patched[at + copyright.len() - 7..at + copyright.len()].copy_from_slice(b"UNKNOWN");
+    fs::write(&path, &patched).unwrap();
+    let events = registry.refresh(&path);
+    let fingerprint = registry.voices()[0].fingerprint;
+    assert_eq!(fingerprint.len, data.len() as u64);
+    assert_eq!(
+        events,
+        [Event::Changed("cmu_us_slt".to_string(), fingerprint)]
+    );
+
+    // removing the whole directory
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        registry.refresh(&dir),
+        [Event::Removed("cmu_us_slt".to_string())]
+    );
+    assert!(registry.voices().is_empty());
+}
+
+#[test]
+fn test_cache() {
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let root = std::env::temp_dir().join(format!("serde_cst_cache_{}", std::process::id()));
+    let (dir, cache) = (root.join("voices"), root.join("registry.cache"));
+    fs::create_dir_all(&dir).unwrap();
+    let voice = dir.join("slt.flitevox");
+    fs::write(&voice, data).unwrap();
+
+    let mut registry = Registry::new([&dir]);
+    registry.scan().unwrap();
+    assert!(registry.voices()[0].sections.is_empty());
+    let sections = registry.sections(&voice).unwrap();
+    assert_eq!(sections[1], ("features".to_string(), 30));
+    assert!(sections.iter().any(|(section, _)| section == "me_h"));
+    registry.save_cache(&cache).unwrap();
+    let mut cached = Registry::new([&dir]);
+    assert!(cached.scan_cached(&cache).unwrap().is_empty());
+    assert_eq!(cached.voices(), registry.voices());
+
+    // an entry which is still current is not read again
+    let renamed = |data: &[u8], name: &[u8; 10]| {
+        let at = data.windows(11).position(|w| w == b"cmu_us_slt\0").unwrap();
+        [&data[..at], name, &data[at + 10..]].concat()
+    };
+    fs::write(&cache, renamed(&fs::read(&cache).unwrap(), b"from_cache")).unwrap();
+    cached.scan_cached(&cache).unwrap();
+    assert_eq!(cached.voices()[0].name, "from_cache");
+
+    // but one whose file changed is, even if it kept its length and modification time
+    let modified = fs::metadata(&voice).unwrap().modified().unwrap();
+    fs::write(&voice, renamed(data, b"cmu_us_xyz")).unwrap();
+    File::options()
+        .write(true)
+        .open(&voice)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+    cached.scan_cached(&cache).unwrap();
+    assert_eq!(cached.voices()[0].name, "cmu_us_xyz");
+    fs::write(&voice, data).unwrap();
+    cached.scan_cached(&cache).unwrap();
+    assert_eq!(cached.voices()[0].name, "cmu_us_slt");
+
+    // as is everything, if the cache is unusable
+    fs::write(&cache, "serde_cst registry cache 1\n").unwrap();
+    cached.scan_cached(root.join("missing")).unwrap();
+    assert_eq!(cached.voices()[0].name, "cmu_us_slt");
+    cached.scan_cached(&cache).unwrap();
+    assert_eq!(cached.voices()[0].name, "cmu_us_slt");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cache_non_utf8_path() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let root = std::env::temp_dir().join(format!("serde_cst_cache_os_{}", std::process::id()));
+    let (dir, cache) = (root.join("voices"), root.join("registry.cache"));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join(OsStr::from_bytes(b"sl\xfft.flitevox")), data).unwrap();
+
+    let mut registry = Registry::new([&dir]);
+    registry.scan().unwrap();
+    registry.save_cache(&cache).unwrap();
+    assert_eq!(load_cache(&fs::read(&cache).unwrap()).len(), 1);
+    let mut cached = Registry::new([&dir]);
+    cached.scan_cached(&cache).unwrap();
+    fs::remove_dir_all(&root).unwrap();
+    assert_eq!(cached.voices(), registry.voices());
+}
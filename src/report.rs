@@ -0,0 +1,192 @@
+//! Human-readable summaries of what changed between two builds of a voice.
+//!
+//! A [`VoiceDiff`] renders as plain text through [`Display`], or as Markdown with
+//! [`VoiceDiff::to_markdown`], for inclusion in voice-build changelogs: the changed features, how
+//! many bytes every section grew or shrank, and how many trees of every section were added,
+//! removed or changed.
+
+use core::fmt::{self, Display, Formatter, Write};
+
+use crate::diff::{FeatureChange, TreeChangeKind, VoiceDiff};
+
+/// Trees added, removed and changed per section, in the order sections first appear.
+fn tree_counts(diff: &VoiceDiff) -> Vec<(&'static str, [usize; 3])> {
+    let mut counts: Vec<(&'static str, [usize; 3])> = Vec::new();
+    for change in &diff.trees {
+        let idx = match counts
+            .iter()
+            .position(|(known, _)| *known == change.section)
+        {
+            Some(idx) => idx,
+            None => {
+                counts.push((change.section, [0; 3]));
+                counts.len() - 1
+            }
+        };
+        let kind = match change.kind {
+            TreeChangeKind::Added => 0,
+            TreeChangeKind::Removed => 1,
+            TreeChangeKind::Changed => 2,
+        };
+        counts[idx].1[kind] += 1;
+    }
+    counts
+}
+
+impl VoiceDiff {
+    fn title(&self) -> String {
+        if self.old_name == self.new_name {
+            self.new_name.clone()
+        } else {
+            format!("{} -> {}", self.old_name, self.new_name)
+        }
+    }
+
+    /// Render as Markdown: a heading, then one table per kind of change.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        self.write_markdown(&mut out)
+            .expect("writing to a String cannot fail");
+        out
+    }
+
+    fn write_markdown(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "## {}", self.title())?;
+        if self.is_empty() {
+            return writeln!(out, "\nNo changes.");
+        }
+        if !self.features.is_empty() {
+            writeln!(out, "\n| Feature | Old | New |\n| --- | --- | --- |")?;
+            for change in &self.features {
+                writeln!(
+                    out,
+                    "| {} | {} | {} |",
+                    markdown_cell(&change.feature),
+                    markdown_value(&change.old),
+                    markdown_value(&change.new)
+                )?;
+            }
+        }
+        if !self.sections.is_empty() {
+            writeln!(
+                out,
+                "\n| Section (bytes) | Old | New | Change |\n| --- | ---: | ---: | ---: |"
+            )?;
+            for delta in &self.sections {
+                writeln!(
+                    out,
+                    "| {} | {} | {} | {:+} |",
+                    delta.section,
+                    delta.old,
+                    delta.new,
+                    delta.change()
+                )?;
+            }
+        }
+        let trees = tree_counts(self);
+        if !trees.is_empty() {
+            writeln!(
+                out,
+                "\n| Trees | Added | Removed | Changed |\n| --- | ---: | ---: | ---: |"
+            )?;
+            for (section, [added, removed, changed]) in trees {
+                writeln!(out, "| {section} | {added} | {removed} | {changed} |")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn markdown_cell(s: &str) -> String {
+    s.replace('|', "\\|").replace('\n', " ")
+}
+
+fn markdown_value(value: &str) -> String {
+    format!("`{}`", markdown_cell(value))
+}
+
+impl Display for VoiceDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.title())?;
+        if self.is_empty() {
+            return writeln!(f, "  no changes");
+        }
+        if !self.features.is_empty() {
+            writeln!(f, "features:")?;
+        }
+        for change in &self.features {
+            let FeatureChange { feature, old, new } = change;
+            writeln!(f, "  {feature}: {old:?} -> {new:?}")?;
+        }
+        if !self.sections.is_empty() {
+            writeln!(f, "sections:")?;
+        }
+        for delta in &self.sections {
+            writeln!(
+                f,
+                "  {}: {} -> {} bytes ({:+})",
+                delta.section,
+                delta.old,
+                delta.new,
+                delta.change()
+            )?;
+        }
+        let trees = tree_counts(self);
+        if !trees.is_empty() {
+            writeln!(f, "trees:")?;
+        }
+        for (section, counts) in trees {
+            write!(f, "  {section}:")?;
+            let kinds = ["added", "removed", "changed"];
+            let mut sep = " ";
+            for (count, kind) in counts.into_iter().zip(kinds) {
+                if count > 0 {
+                    write!(f, "{sep}{count} {kind}")?;
+                    sep = ", ";
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_diff_report() {
+    use crate::de::from_bytes;
+    use crate::diff::diff_bytes;
+    use crate::ser::to_bytes;
+    use crate::voice::{CstVal, TreeDb};
+
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let old: TreeDb = from_bytes(data).unwrap();
+    let report = diff_bytes(data, data).unwrap();
+    assert_eq!(report.to_string(), "cmu_us_slt\n  no changes\n");
+    assert_eq!(report.to_markdown(), "## cmu_us_slt\n\nNo changes.\n");
+
+    // a shorter copyright, a changed f0 tree and a param tree less
+    let mut new = old.clone();
+    new.header.features.copyright = "BSD".to_string();
+    new.body.f0_trees[1].0[7].0[0].3 = CstVal::Float(-12345.0);
+    new.body.param_trees[2].0.pop();
+    let report = diff_bytes(data, &to_bytes(&new).unwrap()).unwrap();
+    assert_eq!(
+        report.features,
+        [FeatureChange {
+            feature: "copyright".to_string(),
+            old: "unknown".to_string(),
+            new: "BSD".to_string(),
+        }]
+    );
+    let text = report.to_string();
+    assert!(text.starts_with(
+        "cmu_us_slt\n\
+         features:\n  copyright: \"unknown\" -> \"BSD\"\n\
+         sections:\n  features: 349 -> 345 bytes (-4)\n"
+    ));
+    assert!(text.ends_with("trees:\n  f0_trees: 1 changed\n  param_trees: 1 removed\n"));
+    let markdown = report.to_markdown();
+    assert!(markdown.contains("| copyright | `unknown` | `BSD` |"));
+    assert!(markdown.contains("| features | 349 | 345 | -4 |"));
+    assert!(markdown.contains("| f0_trees | 0 | 0 | 1 |\n| param_trees | 0 | 1 | 0 |\n"));
+}
@@ -0,0 +1,179 @@
+//! An index of the sections of a voice which can be decoded independently.
+//!
+//! Most of the decoding time of a voice goes to its trees and frames, which flite stores one
+//! model after the other. [`Sections`] finds where every model starts with a
+//! [`preflight`](crate::preflight) walk, so each can be decoded on its own, and on its own
+//! thread: with the `parallel` feature, [`TreeDb::from_sections_parallel`] decodes them with
+//! rayon.
+
+use crate::error::Result;
+use crate::lazy::LazyVoice;
+use crate::voice::{Body, DurModel, ModelVectors, Models, TreeDb, Trees};
+use crate::Header;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionKind {
+    F0Trees,
+    ParamTrees,
+    ParamModel,
+    DurModel,
+}
+
+/// One model, which decodes without any of the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Section {
+    pub kind: SectionKind,
+    /// Which model of its kind it is.
+    pub model: usize,
+    /// Where it starts in the file.
+    pub offset: usize,
+}
+
+/// A decoded [`Section`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decoded {
+    F0Trees(Trees),
+    ParamTrees(Trees),
+    ParamModel(ModelVectors),
+    DurModel(DurModel),
+}
+
+/// The header of a voice, and where the sections of its body are.
+#[derive(Debug, Clone)]
+pub struct Sections<'de> {
+    voice: LazyVoice<'de>,
+}
+impl<'de> Sections<'de> {
+    /// Read the header of `input` and index its body. Its trailers, if it has any, are verified.
+    pub fn new(input: &'de [u8]) -> Result<Self> {
+        Ok(Sections {
+            voice: LazyVoice::new(input)?,
+        })
+    }
+    pub fn header(&self) -> &Header {
+        self.voice.header()
+    }
+    /// Where every section first appears, in file order.
+    pub fn offsets(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        self.voice.sections()
+    }
+    /// How many bytes every section takes in all, in file order.
+    pub fn sizes(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        self.voice.sizes()
+    }
+    /// Every model, in file order.
+    pub fn models(&self) -> &[Section] {
+        self.voice.models()
+    }
+    /// Decode one model. Nothing but the model is read, so models decode in any order.
+    pub fn decode(&self, section: &Section) -> Result<Decoded> {
+        Ok(self.decode_to(section)?.0)
+    }
+    // A model, and the offset it ends at.
+    fn decode_to(&self, section: &Section) -> Result<(Decoded, usize)> {
+        let voice = &self.voice;
+        let offset = section.offset;
+        Ok(match section.kind {
+            SectionKind::F0Trees => {
+                let (trees, end) = voice.read_at(offset)?;
+                (Decoded::F0Trees(trees), end)
+            }
+            SectionKind::ParamTrees => {
+                let (trees, end) = voice.read_at(offset)?;
+                (Decoded::ParamTrees(trees), end)
+            }
+            SectionKind::ParamModel => {
+                let (model, end) = voice.read_at(offset)?;
+                (Decoded::ParamModel(model), end)
+            }
+            SectionKind::DurModel => {
+                let (model, end) = voice.read_at(offset)?;
+                (Decoded::DurModel(model), end)
+            }
+        })
+    }
+    /// Put the body together from its decoded models, with the offsets they end at, and the
+    /// small sections between them.
+    fn body(&self, decoded: Vec<(Decoded, usize)>) -> Result<Body> {
+        let mut models = Models {
+            f0_trees: Vec::new(),
+            param_trees: Vec::new(),
+            param_models: Vec::new(),
+            dur_models: Vec::new(),
+        };
+        // the models of a kind follow each other, and are stepped over at once
+        let mut runs: Vec<(SectionKind, usize, usize)> = Vec::new();
+        for (section, (decoded, end)) in self.models().iter().zip(decoded) {
+            match runs.last_mut() {
+                Some((kind, _, run_end)) if *kind == section.kind => *run_end = end,
+                _ => runs.push((section.kind, section.offset, end)),
+            }
+            match decoded {
+                Decoded::F0Trees(trees) => models.f0_trees.push(trees),
+                Decoded::ParamTrees(trees) => models.param_trees.push(trees),
+                Decoded::ParamModel(model) => models.param_models.push(model),
+                Decoded::DurModel(model) => models.dur_models.push(model),
+            }
+        }
+        let runs: Vec<_> = runs
+            .into_iter()
+            .map(|(_, start, end)| (start, end))
+            .collect();
+        self.voice.body_with(models, &runs)
+    }
+}
+
+impl TreeDb {
+    /// Decode the voice indexed by `sections`, one model after the other.
+    pub fn from_sections(sections: &Sections) -> Result<TreeDb> {
+        let decoded = sections
+            .models()
+            .iter()
+            .map(|section| sections.decode_to(section))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(TreeDb {
+            header: sections.header().clone(),
+            body: sections.body(decoded)?,
+        })
+    }
+    /// [`TreeDb::from_sections`], decoding the models on the rayon thread pool.
+    #[cfg(feature = "parallel")]
+    pub fn from_sections_parallel(sections: &Sections) -> Result<TreeDb> {
+        use rayon::prelude::*;
+
+        let decoded = sections
+            .models()
+            .par_iter()
+            .map(|section| sections.decode_to(section))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(TreeDb {
+            header: sections.header().clone(),
+            body: sections.body(decoded)?,
+        })
+    }
+}
+
+#[test]
+fn test_sections() {
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let sections = Sections::new(data).unwrap();
+    assert_eq!(sections.header().name, "cmu_us_slt");
+    let count = |kind| sections.models().iter().filter(|s| s.kind == kind).count();
+    assert_eq!(count(SectionKind::F0Trees), 3);
+    assert_eq!(count(SectionKind::ParamTrees), 3);
+    assert_eq!(count(SectionKind::ParamModel), 3);
+    assert_eq!(count(SectionKind::DurModel), 3);
+
+    let voice: TreeDb = crate::de::from_bytes(data).unwrap();
+    let last = sections.models().last().unwrap();
+    assert_eq!((last.kind, last.model), (SectionKind::DurModel, 2));
+    assert_eq!(
+        sections.decode(last).unwrap(),
+        Decoded::DurModel(voice.body.dur_models[2].clone())
+    );
+    assert_eq!(TreeDb::from_sections(&sections).unwrap(), voice);
+    #[cfg(feature = "parallel")]
+    assert_eq!(TreeDb::from_sections_parallel(&sections).unwrap(), voice);
+
+    assert!(Sections::new(&data[..data.len() - 1]).is_err());
+}
@@ -1 +1,696 @@
+//! Writing the CST binary layout.
+//!
+//! The output mirrors what [`crate::de`] reads: the magic and the endianness marker, then
+//! length-prefixed, null-terminated strings, numbers in 4 byte little-endian cells, sequences
+//! prefixed with their length, and structs as runs of key/value pairs, which is the layout of
+//! the feature block. Narrower numbers are extended to fill a cell, and 64 bit ones take two.
+//!
+//! Byte arrays are written like strings, but without the null terminator. The nodes of trees
+//! are packed like a C struct: their 8 and 16 bit fields take one and two bytes.
+//! Enums are written as the name of their variant, or as a type tag followed by its fields if
+//! they are [`TAGGED`].
+//!
+//! Like the deserializer, a document has to start with a string, a sequence or a struct, as
+//! every voice does: the magic is only skipped when one of those is read.
 
+use serde::ser::{self, Serialize};
+
+use crate::container::Container;
+use crate::de::{ANY_TAG, PACKED, TAGGED};
+use crate::error::{Error, Result};
+use crate::integrity::{append_digests, Algorithm};
+use crate::version::VERSION;
+use crate::{FormatVersion, FLITEVOX_MAGIC};
+
+const CST_LITTLE_ENDIAN_BYTE_VALUE: u32 = 1;
+
+pub struct Serializer {
+    output: Vec<u8>,
+    big_endian: bool,
+    // Set inside a `PACKED` tuple struct.
+    packed: bool,
+    // Set while the text of a `VERSION` newtype, which goes into the magic, is serialized.
+    version: bool,
+    // Where the output holds the magic of a voice, which the `VERSION` rewrites; `None` if the
+    // dump starts with another magic.
+    version_magic: Option<usize>,
+}
+
+impl Serializer {
+    /// A serializer whose output starts with the magic and the endianness marker.
+    pub fn new() -> Self {
+        Serializer::for_container(Container::Voxdata)
+    }
+    /// A serializer whose output starts with the magic of `container` instead of that of a
+    /// voice.
+    pub fn for_container(container: Container) -> Self {
+        let magic = container.magic();
+        let version_magic = FormatVersion::detect(magic).map(|_| 0);
+        let mut output = magic.to_vec();
+        output.extend_from_slice(&CST_LITTLE_ENDIAN_BYTE_VALUE.to_le_bytes());
+        Serializer {
+            output,
+            big_endian: false,
+            packed: false,
+            version: false,
+            version_magic,
+        }
+    }
+    /// A serializer writing the byte order of a voice built on a big-endian host.
+    pub fn big_endian() -> Self {
+        let mut output = FLITEVOX_MAGIC.to_vec();
+        output.extend_from_slice(&CST_LITTLE_ENDIAN_BYTE_VALUE.to_be_bytes());
+        Serializer {
+            output,
+            big_endian: true,
+            packed: false,
+            version: false,
+            version_magic: Some(0),
+        }
+    }
+    pub fn into_inner(self) -> Vec<u8> {
+        self.output
+    }
+    /// Write a cell given in little-endian order.
+    fn cell(&mut self, cell: [u8; 4]) {
+        self.number(cell);
+    }
+    /// Write a number of any width given in little-endian order.
+    fn number<const N: usize>(&mut self, mut bytes: [u8; N]) {
+        if self.big_endian {
+            bytes.reverse();
+        }
+        self.output.extend_from_slice(&bytes);
+    }
+    /// Write the tag of a variant of a [`TAGGED`] enum, which is its name. The variant taking
+    /// any tag has it as its first field instead.
+    fn tag(&mut self, name: &'static str, variant: &'static str, kind: &'static str) -> Result<()> {
+        if name != TAGGED {
+            return Err(Error::Unsupported(kind));
+        }
+        if variant == ANY_TAG {
+            return Ok(());
+        }
+        let tag: u16 = variant
+            .parse()
+            .map_err(|_| Error::Unsupported("a tagged variant not named by its tag"))?;
+        ser::Serializer::serialize_u16(self, tag)
+    }
+    fn size(&mut self, len: usize) -> Result<()> {
+        let len = u32::try_from(len).map_err(|_| Error::WrongLength(len))?;
+        self.cell(len.to_le_bytes());
+        Ok(())
+    }
+}
+impl Default for Serializer {
+    fn default() -> Self {
+        Serializer::new()
+    }
+}
+
+/// Serialize `value` into a complete document.
+pub fn to_bytes<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut serializer = Serializer::new();
+    value.serialize(&mut serializer)?;
+    Ok(serializer.into_inner())
+}
+
+/// [`to_bytes`], followed by the digest trailer of every section, see [`crate::integrity`].
+/// Only voices have sections, so `value` has to serialize to one.
+pub fn to_bytes_with_digests<T>(value: &T, algorithm: Algorithm) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut output = to_bytes(value)?;
+    append_digests(&mut output, algorithm)?;
+    Ok(output)
+}
+
+/// Serialize `value` into a complete document, written to `writer`.
+#[cfg(feature = "std")]
+pub fn to_writer<W, T>(mut writer: W, value: &T) -> Result<()>
+where
+    W: std::io::Write,
+    T: ?Sized + Serialize,
+{
+    writer.write_all(&to_bytes(value)?).map_err(Error::Io)
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = TupleStruct<'a>;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        // a one byte string
+        self.size(1)?;
+        self.output.extend_from_slice(&[u8::from(v), 0]);
+        Ok(())
+    }
+
+    // Narrow signed numbers are sign extended to fill their cell.
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        if self.packed {
+            self.number(v.to_le_bytes());
+        } else {
+            self.cell(i32::from(v).to_le_bytes());
+        }
+        Ok(())
+    }
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        if self.packed {
+            self.number(v.to_le_bytes());
+        } else {
+            self.cell(i32::from(v).to_le_bytes());
+        }
+        Ok(())
+    }
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.cell(v.to_le_bytes());
+        Ok(())
+    }
+    // Wide numbers take two cells.
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.number(v.to_le_bytes());
+        Ok(())
+    }
+
+    // Narrow numbers still take a whole cell, unless they are packed.
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        if self.packed {
+            self.number([v]);
+        } else {
+            self.cell([v, 0, 0, 0]);
+        }
+        Ok(())
+    }
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        let [lo, hi] = v.to_le_bytes();
+        if self.packed {
+            self.number([lo, hi]);
+        } else {
+            self.cell([lo, hi, 0, 0]);
+        }
+        Ok(())
+    }
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.cell(v.to_le_bytes());
+        Ok(())
+    }
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.number(v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.cell(v.to_le_bytes());
+        Ok(())
+    }
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.number(v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, _v: char) -> Result<()> {
+        Err(Error::Unsupported("char"))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        if self.version {
+            self.version = false;
+            let version = FormatVersion::parse(v).ok_or(Error::InvalidHeader)?;
+            let magic = version.magic();
+            let at = self.version_magic.ok_or(Error::Unsupported(
+                "a format version in a dump without a voice magic",
+            ))?;
+            self.output[at..at + magic.len()].copy_from_slice(&magic);
+            return Ok(());
+        }
+        self.size(v.len() + 1)?;
+        self.output.extend_from_slice(v.as_bytes());
+        self.output.push(0);
+        Ok(())
+    }
+
+    // Arrays are stored as is, without a null terminator.
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.size(v.len())?;
+        self.output.extend_from_slice(v);
+        Ok(())
+    }
+
+    // There is no way to write nothing in place of a value: a `None` field has to be skipped
+    // with `skip_serializing_if`, which leaves its key out too.
+    fn serialize_none(self) -> Result<()> {
+        Err(Error::Unsupported("None"))
+    }
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+    // Enums are stored by the name of the variant, like `end_of_features`, unless `TAGGED`.
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        if name == TAGGED {
+            return self.tag(name, variant, "unit variant");
+        }
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.version = name == VERSION;
+        value.serialize(&mut *self)?;
+        self.version = false;
+        Ok(())
+    }
+    // Variants with fields are only stored by their tag.
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.tag(name, variant, "newtype variant")?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.size(len.ok_or(Error::Unsupported("sequence of unknown length"))?)?;
+        Ok(self)
+    }
+    // Tuples have a length known to both sides, so it is not written.
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Ok(self)
+    }
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        let packed = self.packed;
+        self.packed = packed || name == PACKED;
+        Ok(TupleStruct { ser: self, packed })
+    }
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.tag(name, variant, "tuple variant")?;
+        Ok(self)
+    }
+
+    // Maps run to the end of the input, so neither they nor structs have a length.
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(self)
+    }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(self)
+    }
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.tag(name, variant, "struct variant")?;
+        Ok(self)
+    }
+}
+
+impl ser::SerializeSeq for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+// The fields of a struct variant are written like those of a tuple, without their keys.
+impl ser::SerializeStructVariant for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Serializes the fields of a tuple struct, and restores the packing around it at the end.
+pub struct TupleStruct<'a> {
+    ser: &'a mut Serializer,
+    packed: bool,
+}
+impl<'a> ser::SerializeTupleStruct for TupleStruct<'a> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut *self.ser)
+    }
+    fn end(self) -> Result<()> {
+        self.ser.packed = self.packed;
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        key.serialize(&mut **self)
+    }
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::Serializer::serialize_str(&mut **self, key)?;
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_header() {
+    use crate::de::from_bytes;
+    use crate::Header;
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let header: Header = from_bytes(data).unwrap();
+    // the features and the name, byte for byte
+    assert_eq!(to_bytes(&header).unwrap(), data[..394]);
+
+    // the version goes where the magic of the voice was written, and nowhere else
+    let mut ser = Serializer::for_container(Container::Magic(b"CMU_FLITE_CG_VOXDATA-v1.5\0"));
+    header.serialize(&mut ser).unwrap();
+    assert_eq!(ser.into_inner(), data[..394]);
+    let mut ser = Serializer::for_container(Container::LEXICON);
+    assert!(matches!(
+        header.serialize(&mut ser),
+        Err(Error::Unsupported(_))
+    ));
+    assert!(ser
+        .into_inner()
+        .starts_with(crate::container::LEXDATA_MAGIC));
+}
+
+#[test]
+fn test_primitives() {
+    let data = to_bytes(&("lang", true, 7u8, 0x1234u16, -2i32, 1.5f32)).unwrap();
+    let mut expected = FLITEVOX_MAGIC.to_vec();
+    expected.extend(b"\x01\0\0\0\x05\0\0\0lang\0\x01\0\0\0\x01\0");
+    expected.extend(b"\x07\0\0\0\x34\x12\0\0\xfe\xff\xff\xff");
+    expected.extend(1.5f32.to_le_bytes());
+    assert_eq!(data, expected);
+    assert!(to_bytes(&1u128).is_err());
+
+    let data = to_bytes(&("q", -2i8, -300i16, -2i64, 1u64 << 40, 0.1f64)).unwrap();
+    assert_eq!(data[36..44], *b"\xfe\xff\xff\xff\xd4\xfe\xff\xff");
+    assert_eq!(data[44..52], (-2i64).to_le_bytes());
+    assert_eq!(data[52..60], (1u64 << 40).to_le_bytes());
+    assert_eq!(data[60..], 0.1f64.to_le_bytes());
+}
+
+#[test]
+fn test_option() {
+    use crate::de::from_bytes;
+    use crate::EndOfFeatures;
+    use serde::{Deserialize, Serialize};
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Features {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        variant: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        copyright: Option<String>,
+        end_of_features: EndOfFeatures,
+    }
+    let features = Features {
+        variant: None,
+        copyright: Some("BSD".to_string()),
+        end_of_features: EndOfFeatures::EndOfFeatures,
+    };
+    let data = to_bytes(&features).unwrap();
+    assert_eq!(data[30..44], *b"\x0a\0\0\0copyright\0");
+    assert_eq!(from_bytes::<Features>(&data).unwrap(), features);
+    assert!(matches!(
+        to_bytes(&None::<u32>),
+        Err(Error::Unsupported("None"))
+    ));
+}
+
+#[test]
+fn test_voice() {
+    use crate::de::from_bytes;
+    use crate::voice::{ModelVectors, SpamF0, TreeDb};
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let mut voice: TreeDb = from_bytes(data).unwrap();
+    // the whole voice, byte for byte
+    assert_eq!(to_bytes(&voice).unwrap(), data);
+
+    let mut serializer = Serializer::big_endian();
+    voice.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        from_bytes::<TreeDb>(&serializer.into_inner()).unwrap(),
+        voice
+    );
+
+    // the spamf0 sections are only there when the flag is set
+    voice.body.spamf0 = Some(SpamF0 {
+        accent_tree: voice.body.dur_models[0].1.clone(),
+        phrase_tree: voice.body.dur_models[1].1.clone(),
+        accent_vectors: ModelVectors(2, 1, [[1u8, 2, 3, 4]].into_iter().collect()),
+    });
+    let data = to_bytes(&voice).unwrap();
+    assert_eq!(from_bytes::<TreeDb>(&data).unwrap(), voice);
+}
+
+#[test]
+fn test_packed() {
+    use crate::voice::{Array, CstVal, TreeNode};
+    let start = FLITEVOX_MAGIC.len() + 4;
+    let node = TreeNode(0, 2, 6, CstVal::Float(1.5));
+    let data = to_bytes(&node).unwrap();
+    assert_eq!(data[start..start + 6], [0, 2, 6, 0, 3, 0]);
+    assert_eq!(data[start + 6..], 1.5f32.to_le_bytes());
+
+    let mut serializer = Serializer::big_endian();
+    node.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        serializer.into_inner()[start..start + 6],
+        [0, 2, 0, 6, 0, 3]
+    );
+
+    // only inside the node
+    let data = to_bytes(&(node, 7u8)).unwrap();
+    assert_eq!(data[start + 10..], [7, 0, 0, 0]);
+
+    // arrays have no null terminator
+    let data = to_bytes(&Array(vec![1, 2, 3])).unwrap();
+    assert_eq!(data[start..], [3, 0, 0, 0, 1, 2, 3]);
+}
+
+#[test]
+fn test_big_endian() {
+    use crate::de::{from_bytes, Deserializer};
+    use crate::Header;
+    use serde::Deserialize;
+    let header: Header = from_bytes(include_bytes!("../data/cmu_us_slt.flitevox")).unwrap();
+    let mut serializer = Serializer::big_endian();
+    header.serialize(&mut serializer).unwrap();
+    let data = serializer.into_inner();
+    assert_eq!(data[26..34], [0, 0, 0, 1, 0, 0, 0, 9]);
+    assert_eq!(from_bytes::<Header>(&data).unwrap(), header);
+
+    let mut serializer = Serializer::big_endian();
+    ("f0", 7u8, 0x1234u16, -2i32, 1.5f32)
+        .serialize(&mut serializer)
+        .unwrap();
+    let data = serializer.into_inner();
+    assert_eq!(
+        data[37..],
+        [0, 0, 0, 7, 0, 0, 0x12, 0x34, 0xff, 0xff, 0xff, 0xfe, 0x3f, 0xc0, 0, 0]
+    );
+    let mut de = Deserializer::from_bytes(&data);
+    assert_eq!(
+        <(&str, u8, u16, i32, f32)>::deserialize(&mut de).unwrap(),
+        ("f0", 7, 0x1234, -2, 1.5)
+    );
+    assert_eq!(de.is_byteswapped(), Some(true));
+
+    let mut serializer = Serializer::big_endian();
+    ("q", -2i8, -300i16, -2i64, 0.1f64)
+        .serialize(&mut serializer)
+        .unwrap();
+    let data = serializer.into_inner();
+    assert_eq!(
+        data[36..44],
+        [0xff, 0xff, 0xff, 0xfe, 0xff, 0xff, 0xfe, 0xd4]
+    );
+    assert_eq!(data[44..52], (-2i64).to_be_bytes());
+    assert_eq!(data[52..], 0.1f64.to_be_bytes());
+    let mut de = Deserializer::from_bytes(&data);
+    assert_eq!(
+        <(&str, i8, i16, i64, f64)>::deserialize(&mut de).unwrap(),
+        ("q", -2, -300, -2, 0.1)
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_to_writer() {
+    let mut out = Vec::new();
+    to_writer(&mut out, &["a", "bc"][..]).unwrap();
+    assert_eq!(out, to_bytes(&["a", "bc"][..]).unwrap());
+}
+
+#[cfg(test)]
+mod proptests {
+    use super::*;
+    use crate::de::from_bytes;
+    use crate::de::proptests::{cst_val, features, header, tree};
+    use crate::voice::{CstVal, Tree};
+    use crate::{Features, Header};
+    use proptest::prelude::*;
+    use std::collections::BTreeMap;
+
+    proptest! {
+        #[test]
+        fn strings_round_trip(strings in prop::collection::vec("[a-zA-Z0-9_ :.-]{0,32}", 0..16)) {
+            prop_assert_eq!(&strings, &from_bytes::<Vec<String>>(&to_bytes(&strings).unwrap()).unwrap());
+        }
+
+        #[test]
+        fn pairs_round_trip(pairs in prop::collection::btree_map("[a-z_]{1,16}", "[a-zA-Z0-9_ :.-]{0,32}", 0..16)) {
+            let data = to_bytes(&pairs).unwrap();
+            prop_assert_eq!(&pairs, &from_bytes::<BTreeMap<String, String>>(&data).unwrap());
+        }
+
+        #[test]
+        fn numbers_round_trip(values in prop::collection::vec((any::<i32>(), any::<u32>(), any::<u16>(), any::<u8>()), 0..16)) {
+            let data = to_bytes(&values).unwrap();
+            prop_assert_eq!(&values, &from_bytes::<Vec<(i32, u32, u16, u8)>>(&data).unwrap());
+        }
+
+        #[test]
+        fn floats_round_trip(values in prop::collection::vec(any::<f32>().prop_filter("NaN", |f| !f.is_nan()), 0..16)) {
+            let data = to_bytes(&values).unwrap();
+            prop_assert_eq!(&values, &from_bytes::<Vec<f32>>(&data).unwrap());
+        }
+
+        #[test]
+        fn header_round_trip(header in header()) {
+            let data = to_bytes(&header).unwrap();
+            prop_assert_eq!(&header, &from_bytes::<Header>(&data).unwrap());
+        }
+
+        #[test]
+        fn features_round_trip(features in features()) {
+            let data = to_bytes(&features).unwrap();
+            prop_assert_eq!(&features, &from_bytes::<Features>(&data).unwrap());
+        }
+
+        #[test]
+        fn cst_val_round_trip(vals in prop::collection::vec(cst_val(), 0..8)) {
+            let data = to_bytes(&vals).unwrap();
+            prop_assert_eq!(&vals, &from_bytes::<Vec<CstVal>>(&data).unwrap());
+        }
+
+        #[test]
+        fn tree_round_trip(trees in prop::collection::vec(tree(), 0..4)) {
+            let data = to_bytes(&trees).unwrap();
+            prop_assert_eq!(&trees, &from_bytes::<Vec<Tree>>(&data).unwrap());
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Identify a voice without a heap.
+//!
+//! [`HeaderSummary`] holds the identifying part of the header in fixed-size fields, so firmware
+//! built without the `alloc` feature can tell which voice blob it was given.
+
+use core::fmt::{self, Debug, Formatter};
+
+use serde::de::{self, DeserializeSeed};
+
+use crate::de::Deserializer;
+use crate::error::Result;
+use crate::header_ref::{parse, FeatureSink, HeaderBlock};
+use crate::header_ref::{
+    DEFAULT_MODEL_SHAPE, DEFAULT_NUM_DUR_MODELS, DEFAULT_NUM_F0_MODELS, DEFAULT_NUM_PARAM_MODELS,
+};
+use crate::Gender;
+
+/// A string of at most `N` bytes, stored inline. Longer strings are cut at a character boundary.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct FixedStr<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+    truncated: bool,
+}
+impl<const N: usize> FixedStr<N> {
+    pub fn new(s: &str) -> Self {
+        let mut len = s.len().min(N);
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        let mut bytes = [0; N];
+        bytes[..len].copy_from_slice(&s.as_bytes()[..len]);
+        FixedStr {
+            bytes,
+            len,
+            truncated: len < s.len(),
+        }
+    }
+    pub fn as_str(&self) -> &str {
+        // only ever cut at a character boundary of a `str`
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+    /// Whether the original string was longer than `N` bytes.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+impl<const N: usize> Default for FixedStr<N> {
+    fn default() -> Self {
+        FixedStr::new("")
+    }
+}
+impl<const N: usize> Debug for FixedStr<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())?;
+        if self.truncated {
+            f.write_str("...")?;
+        }
+        Ok(())
+    }
+}
+impl<const N: usize> PartialEq<str> for FixedStr<N> {
+    fn eq(&self, other: &str) -> bool {
+        !self.truncated && self.as_str() == other
+    }
+}
+impl<const N: usize> PartialEq<&str> for FixedStr<N> {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+/// The identifying features of a voice, with flite's defaults for missing model counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderSummary {
+    pub name: FixedStr<64>,
+    pub language: FixedStr<16>,
+    pub country: FixedStr<16>,
+    pub variant: FixedStr<16>,
+    /// [`Gender::Unknown`] if the voice does not say, or says something unexpected.
+    pub gender: Gender,
+    pub age: u32,
+    pub num_f0_models: u32,
+    pub num_param_models: u32,
+    pub num_dur_models: u32,
+    pub model_shape: u32,
+}
+impl Default for HeaderSummary {
+    fn default() -> Self {
+        HeaderSummary {
+            name: FixedStr::default(),
+            language: FixedStr::default(),
+            country: FixedStr::default(),
+            variant: FixedStr::default(),
+            gender: Gender::Unknown,
+            age: 0,
+            num_f0_models: DEFAULT_NUM_F0_MODELS,
+            num_param_models: DEFAULT_NUM_PARAM_MODELS,
+            num_dur_models: DEFAULT_NUM_DUR_MODELS,
+            model_shape: DEFAULT_MODEL_SHAPE,
+        }
+    }
+}
+
+impl HeaderSummary {
+    /// Read the summary from the start of a voice file. Only the header needs to be present.
+    pub fn parse(input: &[u8]) -> Result<HeaderSummary> {
+        let mut de = Deserializer::from_bytes(input);
+        let (_, mut summary, name) = HeaderBlock(HeaderSummary::default()).deserialize(&mut de)?;
+        summary.name = FixedStr::new(name);
+        Ok(summary)
+    }
+}
+impl<'de> FeatureSink<'de> for HeaderSummary {
+    fn feature<E: de::Error>(
+        &mut self,
+        key: &'de str,
+        value: &'de str,
+    ) -> core::result::Result<(), E> {
+        match key {
+            "language" => self.language = FixedStr::new(value),
+            "country" => self.country = FixedStr::new(value),
+            "variant" => self.variant = FixedStr::new(value),
+            "gender" => self.gender = value.parse().unwrap_or_default(),
+            "age" => self.age = parse(key, value)?,
+            "num_f0_models" => self.num_f0_models = parse(key, value)?,
+            "num_param_models" => self.num_param_models = parse(key, value)?,
+            "num_dur_models" => self.num_dur_models = parse(key, value)?,
+            "model_shape" => self.model_shape = parse(key, value)?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_fixed_str() {
+    let s = FixedStr::<2>::new("año");
+    assert_eq!(s.as_str(), "a");
+    assert!(s.is_truncated());
+    assert_ne!(s, "a");
+    assert_eq!(FixedStr::<4>::new("eng"), "eng");
+}
+
+#[test]
+fn test_summary() {
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let summary = HeaderSummary::parse(&data[..512]).unwrap();
+    assert_eq!(summary.name, "cmu_us_slt");
+    assert_eq!(summary.language, "eng");
+    assert_eq!(summary.country, "USA");
+    assert_eq!(summary.variant, "none");
+    assert_eq!(summary.gender, Gender::Unknown);
+    assert_eq!(summary.age, 30);
+    // read like the borrowed header is
+    let header = crate::HeaderRef::parse(&data[..512]).unwrap();
+    assert_eq!(summary.num_f0_models, header.features.num_f0_models);
+    assert_eq!(summary.model_shape, header.features.model_shape);
+    assert!(HeaderSummary::parse(&data[..100]).is_err());
+
+    // a feature may not repeat, even one the summary does not keep
+    let end = 394 - 55;
+    for repeat in [
+        &b"\x04\0\0\0age\0\x03\0\0\x0031\0"[..],
+        b"\x0a\0\0\0copyright\0\x02\0\0\0x\0",
+    ] {
+        let repeated = [&data[..end], repeat, &data[end..394]].concat();
+        let err = HeaderSummary::parse(&repeated).unwrap_err();
+        assert!(matches!(err.kind(), crate::error::Error::DuplicateFeature(_, at) if *at == end));
+    }
+}
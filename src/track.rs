@@ -0,0 +1,203 @@
+//! The frames of a parameter model as parameter values.
+//!
+//! [`ParamModel`] keeps the frames exactly as they are stored. A [`Track`] maps them back to the
+//! values flite feeds its vocoder: for the base model shape every channel is a `u16` scaled by
+//! the minimum and range of its channel, and for quantized shapes every stored byte indexes the
+//! table of its column. Quantized frames hold fewer columns than the model has channels, and a
+//! track of them has as many channels as stored columns.
+
+use crate::model::{CgModel, ParamModel, Quantization};
+use crate::voice::Array2d;
+use crate::MODEL_SHAPE_BASE_MINRANGE;
+
+// A quantization table has a value for every byte.
+const QTABLE_LEN: usize = 256;
+
+#[derive(Debug, Clone, Copy)]
+enum Decode<'a> {
+    MinRange(&'a Quantization),
+    Tables(&'a [Vec<f32>]),
+}
+impl Decode<'_> {
+    fn value(self, channel: usize, raw: &[u8]) -> Option<f32> {
+        match self {
+            Decode::MinRange(quantization) => {
+                let cell = raw.get(channel * 2..channel * 2 + 2)?;
+                quantization.dequantize(channel, u16::from_le_bytes([cell[0], cell[1]]))
+            }
+            Decode::Tables(tables) => {
+                let table = tables.get(channel)?;
+                table.get(usize::from(*raw.get(channel)?)).copied()
+            }
+        }
+    }
+}
+
+/// The frames of one parameter model, `frame_shift` seconds apart.
+#[derive(Debug, Clone, Copy)]
+pub struct Track<'a> {
+    frames: &'a Array2d,
+    decode: Decode<'a>,
+    num_channels: usize,
+    frame_shift: f32,
+}
+impl<'a> Track<'a> {
+    /// The frames of the parameter model `index` of `model`. Returns `None` if there is no such
+    /// model, or if its frames don't match its model shape: rows of differing widths, or missing
+    /// ranges or tables.
+    pub fn new(model: &'a CgModel, index: usize) -> Option<Self> {
+        Track::from_params(
+            model.param_models.get(index)?,
+            &model.quantization,
+            index,
+            model.frame_advance,
+        )
+    }
+    fn from_params(
+        params: &'a ParamModel,
+        quantization: &'a Quantization,
+        index: usize,
+        frame_shift: f32,
+    ) -> Option<Self> {
+        let (decode, num_channels) = if quantization.model_shape > MODEL_SHAPE_BASE_MINRANGE {
+            let tables = quantization.qtables.get(index)?;
+            let width = params.frames.row(0).map_or(0, |row| row.0.len());
+            if tables.len() < width || tables[..width].iter().any(|t| t.len() < QTABLE_LEN) {
+                return None;
+            }
+            (Decode::Tables(tables), width)
+        } else {
+            let ranges = quantization
+                .model_min
+                .len()
+                .min(quantization.model_range.len());
+            if ranges < params.num_channels {
+                return None;
+            }
+            (Decode::MinRange(quantization), params.num_channels)
+        };
+        let width = match decode {
+            Decode::MinRange(_) => num_channels * 2,
+            Decode::Tables(_) => num_channels,
+        };
+        if !params.frames.is_empty() && params.frames.row_len() != Some(width) {
+            return None;
+        }
+        Some(Track {
+            frames: &params.frames,
+            decode,
+            num_channels,
+            frame_shift,
+        })
+    }
+    pub fn num_frames(&self) -> usize {
+        self.frames.len()
+    }
+    pub fn num_channels(&self) -> usize {
+        self.num_channels
+    }
+    /// Seconds between two frames.
+    pub fn frame_shift(&self) -> f32 {
+        self.frame_shift
+    }
+    /// Seconds covered by all of the frames.
+    pub fn duration(&self) -> f32 {
+        self.frames.len() as f32 * self.frame_shift
+    }
+    pub fn frame(&self, idx: usize) -> Option<Frame<'a>> {
+        let raw = self.frames.row(idx)?.0;
+        Some(Frame {
+            raw,
+            decode: self.decode,
+            num_channels: self.num_channels,
+        })
+    }
+    pub fn frames(&self) -> impl Iterator<Item = Frame<'a>> + '_ {
+        (0..self.num_frames()).filter_map(|idx| self.frame(idx))
+    }
+    /// Every value of `channel`, one per frame.
+    pub fn channel(&self, channel: usize) -> Option<Vec<f32>> {
+        if channel >= self.num_channels {
+            return None;
+        }
+        let values = self
+            .frames
+            .rows()
+            .map(|raw| self.decode.value(channel, raw.0));
+        values.collect()
+    }
+    /// Every channel, as returned by [`Track::channel`].
+    pub fn channels(&self) -> Vec<Vec<f32>> {
+        (0..self.num_channels)
+            .filter_map(|channel| self.channel(channel))
+            .collect()
+    }
+}
+
+/// The values of one frame.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame<'a> {
+    raw: &'a [u8],
+    decode: Decode<'a>,
+    num_channels: usize,
+}
+impl Frame<'_> {
+    /// The bytes of the frame, as stored.
+    pub fn raw(&self) -> &[u8] {
+        self.raw
+    }
+    pub fn len(&self) -> usize {
+        self.num_channels
+    }
+    pub fn is_empty(&self) -> bool {
+        self.num_channels == 0
+    }
+    pub fn get(&self, channel: usize) -> Option<f32> {
+        self.decode.value(channel, self.raw)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = f32> + '_ {
+        (0..self.num_channels).filter_map(|channel| self.get(channel))
+    }
+    pub fn to_vec(&self) -> Vec<f32> {
+        self.iter().collect()
+    }
+}
+
+#[test]
+fn test_track() {
+    let params = ParamModel {
+        num_channels: 2,
+        frames: [[0, 0, 0xff, 0xff], [0xff, 0xff, 0, 0]]
+            .into_iter()
+            .collect(),
+    };
+    let quantization = Quantization {
+        model_shape: 1,
+        model_min: vec![-1.0, 100.0],
+        model_range: vec![2.0, 50.0],
+        qtables: Vec::new(),
+    };
+    let track = Track::from_params(&params, &quantization, 0, 0.005).unwrap();
+    assert_eq!((track.num_frames(), track.num_channels()), (2, 2));
+    assert_eq!(track.duration(), 0.01);
+    assert_eq!(track.frame(0).unwrap().to_vec(), [-1.0, 150.0]);
+    assert_eq!(track.channels(), [[-1.0, 1.0], [150.0, 100.0]]);
+    assert_eq!(track.channel(2), None);
+    // a frame short of a channel
+    let short = ParamModel {
+        num_channels: 2,
+        frames: [[0, 0]].into_iter().collect(),
+    };
+    assert!(Track::from_params(&short, &quantization, 0, 0.005).is_none());
+
+    use crate::de::from_bytes;
+    let voice = from_bytes(include_bytes!("../data/cmu_us_slt.flitevox")).unwrap();
+    let model = CgModel::from_voice(&voice);
+    let track = Track::new(&model, 0).unwrap();
+    assert_eq!(track.num_frames(), 30238);
+    assert_eq!(track.num_channels(), 82);
+    assert_eq!(track.frame_shift(), 0.005);
+    assert!(track.frames().all(|frame| frame.iter().count() == 82));
+    assert_eq!(track.channel(0).unwrap().len(), 30238);
+    assert!(Track::new(&model, 3).is_none());
+}
@@ -0,0 +1,197 @@
+//! An optional integrity trailer: a CRC32 of everything before it.
+//!
+//! A voice with a trailer ends in [`TRAILER_MAGIC`] followed by the little-endian CRC32 (IEEE) of
+//! the preceding bytes. flite itself stops reading after the last section, so a voice with a
+//! trailer still loads there. [`crate::de::from_bytes`] verifies the trailer when one is present,
+//! and [`strip_trailers`] splits it off for readers which don't.
+//!
+//! A voice may also carry a [digest trailer](crate::integrity), which goes before the CRC32
+//! trailer. [`strip_trailers`] splits both off, and is what every reader of voices starts with.
+
+use crate::error::{Error, Result};
+use crate::integrity::{self, Algorithm, DigestTrailer};
+use crate::is_flitevox;
+
+/// Marks the start of the trailer.
+pub const TRAILER_MAGIC: &[u8; 8] = b"CSTCRC32";
+/// The length of the whole trailer: the magic and the checksum.
+pub const TRAILER_LEN: usize = TRAILER_MAGIC.len() + 4;
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+static CRC_TABLE: [u32; 256] = crc_table();
+
+/// The CRC32 (IEEE, as used by zip and PNG) of `data`.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &b| {
+        CRC_TABLE[usize::from(crc as u8 ^ b)] ^ (crc >> 8)
+    })
+}
+
+/// The trailer for `data`.
+pub fn trailer(data: &[u8]) -> [u8; TRAILER_LEN] {
+    let mut trailer = [0; TRAILER_LEN];
+    trailer[..TRAILER_MAGIC.len()].copy_from_slice(TRAILER_MAGIC);
+    trailer[TRAILER_MAGIC.len()..].copy_from_slice(&crc32(data).to_le_bytes());
+    trailer
+}
+
+/// Append the trailer for everything already in `out`.
+#[cfg(feature = "alloc")]
+pub fn append_trailer(out: &mut Vec<u8>) {
+    let trailer = trailer(out);
+    out.extend_from_slice(&trailer);
+}
+
+/// Whether `input` ends in a trailer, valid or not.
+pub fn has_trailer(input: &[u8]) -> bool {
+    input.len() >= TRAILER_LEN && input[input.len() - TRAILER_LEN..].starts_with(TRAILER_MAGIC)
+}
+
+/// The trailers split off a voice by [`strip_trailers`]. They are not verified until
+/// [`Trailers::verify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Trailers<'a> {
+    // the stored checksum, and the bytes it covers
+    crc: Option<(u32, &'a [u8])>,
+    digests: Option<DigestTrailer<'a>>,
+}
+impl Trailers<'_> {
+    /// Whether the voice ends in a CRC32 trailer.
+    pub fn has_crc(&self) -> bool {
+        self.crc.is_some()
+    }
+    /// The algorithm of the digest trailer, if the voice has one this crate can read.
+    pub fn digests(&self) -> Option<Algorithm> {
+        match self.digests {
+            Some(DigestTrailer::Intact { algorithm, .. }) => Some(algorithm),
+            _ => None,
+        }
+    }
+    /// Check the CRC32 trailer, then the digest trailer.
+    pub fn verify(&self) -> Result<()> {
+        if let Some((stored, data)) = self.crc {
+            let computed = crc32(data);
+            if stored != computed {
+                return Err(Error::ChecksumMismatch(stored, computed));
+            }
+        }
+        match self.digests {
+            Some(digests) => digests.verify(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Split `input` into the voice and its trailers, without verifying them. Only CG voice files
+/// (see [`is_flitevox`]) are looked at: anything else is returned whole. A digest trailer this
+/// crate can't read stays with the voice, for [`Trailers::verify`] to report.
+pub fn strip_trailers(input: &[u8]) -> (&[u8], Trailers<'_>) {
+    let mut trailers = Trailers::default();
+    if !is_flitevox(input) {
+        return (input, trailers);
+    }
+    let mut data = input;
+    if has_trailer(data) {
+        let (covered, trailer) = data.split_at(data.len() - TRAILER_LEN);
+        let stored = u32::from_le_bytes(trailer[TRAILER_MAGIC.len()..].try_into().unwrap());
+        trailers.crc = Some((stored, covered));
+        data = covered;
+    }
+    trailers.digests = integrity::split(data);
+    if let Some(DigestTrailer::Intact { data: voice, .. }) = trailers.digests {
+        data = voice;
+    }
+    (data, trailers)
+}
+
+/// Verify the trailers of `input`, and return the voice before them. Input without trailers is
+/// returned unchanged.
+pub fn verify(input: &[u8]) -> Result<&[u8]> {
+    let (data, trailers) = strip_trailers(input);
+    trailers.verify()?;
+    Ok(data)
+}
+
+#[test]
+fn test_crc32() {
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_trailer() {
+    use crate::de::from_bytes;
+    use crate::Header;
+
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    assert!(!has_trailer(data));
+    assert_eq!(verify(data).unwrap().len(), data.len());
+    assert_eq!(strip_trailers(data), (&data[..], Trailers::default()));
+
+    let mut with_trailer = data.to_vec();
+    append_trailer(&mut with_trailer);
+    assert!(has_trailer(&with_trailer));
+    assert_eq!(verify(&with_trailer).unwrap(), &data[..]);
+    assert!(strip_trailers(&with_trailer).1.has_crc());
+    // readers which don't verify it skip it
+    assert!(crate::preflight::preflight(&with_trailer).is_ok());
+    #[cfg(feature = "accounting")]
+    assert_eq!(
+        crate::accounting::estimate(&with_trailer).unwrap(),
+        crate::accounting::estimate(data).unwrap()
+    );
+    assert_eq!(
+        from_bytes::<Header>(&with_trailer).unwrap(),
+        from_bytes::<Header>(data).unwrap()
+    );
+
+    // one flipped bit in the body
+    with_trailer[data.len() / 2] ^= 1;
+    assert!(matches!(
+        from_bytes::<Header>(&with_trailer),
+        Err(Error::ChecksumMismatch(..))
+    ));
+
+    // both trailers, which every reader strips
+    let mut both = data.to_vec();
+    integrity::append_digests(&mut both, Algorithm::XxHash32).unwrap();
+    append_trailer(&mut both);
+    let (voice, trailers) = strip_trailers(&both);
+    assert_eq!(voice, &data[..]);
+    assert!(trailers.has_crc());
+    assert_eq!(trailers.digests(), Some(Algorithm::XxHash32));
+    assert!(trailers.verify().is_ok());
+    assert!(crate::preflight::preflight(&both).is_ok());
+    #[cfg(feature = "accounting")]
+    assert_eq!(
+        crate::accounting::estimate(&both).unwrap(),
+        crate::accounting::estimate(data).unwrap()
+    );
+    assert!(crate::validate::validate(&both).unwrap().is_valid());
+    assert!(crate::lazy::LazyVoice::new(&both).is_ok());
+
+    // anything but a voice is left alone, even if it happens to end like one
+    let mut other = b"RIFF".to_vec();
+    append_trailer(&mut other);
+    other[4 + TRAILER_MAGIC.len()] ^= 1;
+    assert_eq!(strip_trailers(&other).0, &other[..]);
+}
@@ -0,0 +1,186 @@
+//! Checking a voice file for every structural problem at once.
+//!
+//! [`validate`] walks the file the way [`preflight`](crate::preflight) does, but does not stop at
+//! problems it can step over: strings without their null terminator or which are not UTF-8, an
+//! unknown endianness marker, counts which disagree with each other, and a bad integrity
+//! trailer. A size which overruns the input ends the walk, as nothing after it can be found.
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::error::{Error, Result};
+use crate::preflight::{Sink, Walker};
+use crate::trailer;
+
+// The endianness marker, as read by a host of either byte order.
+const NATIVE: usize = 1;
+const SWAPPED: usize = 0x0100_0000;
+
+/// Every problem found in a voice, in file order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationReport {
+    pub diagnostics: Vec<Diagnostic>,
+}
+impl ValidationReport {
+    /// Whether nothing is wrong enough to keep the voice from loading.
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Warning)
+    }
+}
+
+#[derive(Default)]
+struct Checks {
+    diagnostics: Vec<Diagnostic>,
+    // the key of the feature whose value comes next
+    feature: Option<String>,
+    types: Option<usize>,
+    num_frames: Option<usize>,
+    me_num: Option<usize>,
+}
+impl Checks {
+    fn mismatch(&mut self, field: &str, offset: usize, what: &str, actual: usize, expected: usize) {
+        if actual != expected {
+            let message = format!("{actual} {what}, but {expected} expected");
+            self.diagnostics
+                .push(Diagnostic::error("count-mismatch", field, message).at(offset));
+        }
+    }
+}
+impl Sink for Checks {
+    fn account(&mut self, _section: &'static str, _bytes: usize, _allocations: usize) {}
+    fn int(&mut self, section: &'static str, offset: usize, value: usize) {
+        match section {
+            "endianness" if value != NATIVE && value != SWAPPED => {
+                let message = format!("{value:#010x} is neither byte order's marker");
+                let diagnostic = Diagnostic::error("bad-endianness", section, message);
+                self.diagnostics.push(diagnostic.at(offset));
+            }
+            "num_types" => {
+                let types = self.types.unwrap_or(value);
+                self.mismatch(section, offset, "types are listed", types, value);
+            }
+            "num_frames" | "num_frames_spamf0_accent" => self.num_frames = Some(value),
+            "me_num" => self.me_num = Some(value),
+            _ => {}
+        }
+    }
+    fn list(&mut self, section: &'static str, offset: usize, len: usize) {
+        match section {
+            "types" => self.types = Some(len),
+            "model_vectors" | "spamf0_accent_vectors" => {
+                let frames = self.num_frames.unwrap_or(len);
+                self.mismatch(section, offset, "rows", len, frames);
+            }
+            "me_h" => {
+                let bands = self.me_num.unwrap_or(len);
+                self.mismatch(section, offset, "rows", len, bands);
+            }
+            _ => {}
+        }
+    }
+    fn trees(&mut self, section: &'static str, offset: usize, len: usize) {
+        let types = self.types.unwrap_or(len);
+        self.mismatch(section, offset, "trees", len, types);
+    }
+    fn string(&mut self, section: &'static str, offset: usize, bytes: &[u8]) {
+        let field = match (section, self.feature.take()) {
+            ("features", Some(key)) => key,
+            ("features", None) => {
+                self.feature = Some(String::from_utf8_lossy(bytes).trim_end_matches('\0').into());
+                "features".to_string()
+            }
+            _ => section.to_string(),
+        };
+        let Some(text) = bytes.strip_suffix(b"\0") else {
+            let message = "does not end in a null terminator".to_string();
+            let diagnostic = Diagnostic::error("unterminated-string", &field, message);
+            self.diagnostics.push(diagnostic.at(offset));
+            return;
+        };
+        if let Err(err) = core::str::from_utf8(text) {
+            let message = format!("is not UTF-8: {err}");
+            let diagnostic = Diagnostic::warning("not-utf8", &field, message);
+            self.diagnostics.push(diagnostic.at(offset));
+        }
+    }
+}
+
+/// Check the structure of a voice, collecting every problem instead of stopping at the first.
+/// Only input which is not a CG voice file at all is an error.
+pub fn validate(input: &[u8]) -> Result<ValidationReport> {
+    let mut diagnostics = Vec::new();
+    let (data, trailers) = trailer::strip_trailers(input);
+    if let Err(err) = trailers.verify() {
+        let message = err.to_string();
+        let diagnostic = match err {
+            Error::SectionChecksumMismatch(section, offset) => {
+                Diagnostic::error("checksum-mismatch", section, message).at(offset)
+            }
+            Error::TruncatedTrailer => {
+                Diagnostic::error("truncated-trailer", "trailer", message).at(data.len())
+            }
+            _ => Diagnostic::error("checksum-mismatch", "trailer", message).at(data.len()),
+        };
+        diagnostics.push(diagnostic);
+    }
+    let mut walker = Walker::new(data, Checks::default())?;
+    let inconsistency = walker.voice().and_then(|()| walker.end()).err();
+    let checks = walker.into_sink();
+    diagnostics.extend(checks.diagnostics);
+    diagnostics.extend(inconsistency.as_ref().map(Diagnostic::from));
+    diagnostics.sort_by_key(|diagnostic| diagnostic.offset);
+    Ok(ValidationReport { diagnostics })
+}
+
+#[test]
+fn test_validate() {
+    use crate::error::Error;
+    use crate::preflight::Inconsistency;
+
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let report = validate(data).unwrap();
+    assert_eq!(report.diagnostics, []);
+    assert!(report.is_valid());
+
+    let mut data = data.to_vec();
+    // "USA" loses its terminator, the gender its UTF-8, and num_types says 123
+    let country = data.windows(4).position(|w| w == b"USA\0").unwrap();
+    data[country + 3] = b'!';
+    let gender = data.windows(8).position(|w| w == b"unknown\0").unwrap();
+    data[gender] = 0xff;
+    data[1470] = 123;
+    let report = validate(&data).unwrap();
+    let found: Vec<_> = report
+        .diagnostics
+        .iter()
+        .map(|found| (found.code, found.field.as_str(), found.offset))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("unterminated-string", "country", Some(country - 4)),
+            ("not-utf8", "gender", Some(gender - 4)),
+            ("count-mismatch", "num_types", Some(1470)),
+        ]
+    );
+    assert_eq!(report.errors().count(), 2);
+    assert!(!report.is_valid());
+
+    // a truncated voice still reports what came before the end
+    let report = validate(&data[..data.len() - 1]).unwrap();
+    assert_eq!(report.diagnostics.len(), 4);
+    assert_eq!(report.diagnostics[3].code, "overrun");
+
+    assert!(matches!(
+        validate(b"RIFF"),
+        Err(Error::Structure(Inconsistency::InvalidHeader))
+    ));
+}
@@ -0,0 +1,184 @@
+//! A document of any shape, like `serde_json::Value`.
+//!
+//! The format does not say what type an entry has, so [`from_bytes`] guesses it from the bytes:
+//! a size followed by as many bytes ending in a null terminator is a [`Value::Str`], a feature
+//! block right after the magic is a [`Value::Table`], and any other cell is a [`Value::Int`], or a
+//! [`Value::Float`] when its integer is too large to be a count and its bits make a reasonable
+//! float. The guesses are right for the header of every voice and for most entries after it, but
+//! a number may come out as a string or the other way around.
+
+use core::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeTuple, Serializer};
+
+use crate::de::VALUE;
+use crate::error::Result;
+
+/// One entry, or the entries of a whole document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Int(i32),
+    Float(f32),
+    Bool(bool),
+    /// The entries of a document, or a sequence.
+    Array(Vec<Value>),
+    /// Key/value pairs in the order they were read, so `end_of_features` stays last.
+    Table(Vec<(String, Value)>),
+}
+impl Value {
+    /// The value of `key`, if this is a table which has it.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Table(pairs) => pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Read every entry of a document.
+pub fn from_bytes(input: &[u8]) -> Result<Value> {
+    crate::de::from_bytes(input)
+}
+/// Write a document: the entries of an [`Value::Array`] follow the magic one after the other,
+/// without the count a nested sequence has.
+pub fn to_bytes(value: &Value) -> Result<Vec<u8>> {
+    match value {
+        Value::Array(entries) => crate::ser::to_bytes(&Entries(entries)),
+        value => crate::ser::to_bytes(value),
+    }
+}
+struct Entries<'a>(&'a [Value]);
+impl Serialize for Entries<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(self.0.len())?;
+        for entry in self.0 {
+            tuple.serialize_element(entry)?;
+        }
+        tuple.end()
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        match self {
+            Value::Str(s) => serializer.serialize_str(s),
+            Value::Int(i) => serializer.serialize_i32(*i),
+            Value::Float(f) => serializer.serialize_f32(*f),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Array(values) => values.serialize(serializer),
+            Value::Table(pairs) => {
+                let mut map = serializer.serialize_map(Some(pairs.len()))?;
+                for (key, value) in pairs {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+struct ValueVisitor;
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+    fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("any entry")
+    }
+    fn visit_newtype_struct<D>(self, deserializer: D) -> core::result::Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+    fn visit_bool<E: de::Error>(self, v: bool) -> core::result::Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+    fn visit_i64<E: de::Error>(self, v: i64) -> core::result::Result<Value, E> {
+        i32::try_from(v)
+            .map(Value::Int)
+            .map_err(|_| E::invalid_value(de::Unexpected::Signed(v), &"a 32 bit integer"))
+    }
+    fn visit_u64<E: de::Error>(self, v: u64) -> core::result::Result<Value, E> {
+        i32::try_from(v)
+            .map(Value::Int)
+            .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &"a 32 bit integer"))
+    }
+    fn visit_f32<E: de::Error>(self, v: f32) -> core::result::Result<Value, E> {
+        Ok(Value::Float(v))
+    }
+    fn visit_f64<E: de::Error>(self, v: f64) -> core::result::Result<Value, E> {
+        Ok(Value::Float(v as f32))
+    }
+    fn visit_str<E: de::Error>(self, v: &str) -> core::result::Result<Value, E> {
+        Ok(Value::Str(v.to_string()))
+    }
+    fn visit_string<E: de::Error>(self, v: String) -> core::result::Result<Value, E> {
+        Ok(Value::Str(v))
+    }
+    fn visit_seq<A>(self, mut seq: A) -> core::result::Result<Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::Array(values))
+    }
+    fn visit_map<A>(self, mut map: A) -> core::result::Result<Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut pairs = Vec::new();
+        while let Some(pair) = map.next_entry()? {
+            pairs.push(pair);
+        }
+        Ok(Value::Table(pairs))
+    }
+}
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(VALUE, ValueVisitor)
+    }
+}
+
+#[test]
+fn test_value() {
+    let data = include_bytes!("../data/cmu_us_slt.flitevox");
+    let header = from_bytes(&data[..394]).unwrap();
+    let Value::Array(entries) = &header else {
+        panic!("a document is an array of its entries");
+    };
+    assert_eq!(entries.len(), 2);
+    let features = &entries[0];
+    assert_eq!(
+        features.get("language").and_then(Value::as_str),
+        Some("eng")
+    );
+    assert_eq!(
+        features.get("build_date").and_then(Value::as_str),
+        Some("2017-09-14_23:37")
+    );
+    assert_eq!(entries[1], Value::Str("cmu_us_slt".to_string()));
+    assert_eq!(to_bytes(&header).unwrap(), data[..394]);
+
+    // numbers, including floats which would be far too large as integers
+    let doc = Value::Array(vec![
+        Value::Int(16000),
+        Value::Float(0.005),
+        Value::Int(-3),
+        Value::Bool(true),
+        Value::Str("pau".to_string()),
+    ]);
+    assert_eq!(from_bytes(&to_bytes(&doc).unwrap()).unwrap(), doc);
+}
@@ -0,0 +1,160 @@
+//! The version in the magic of a CG voice file.
+//!
+//! Every voice starts with `CMU_FLITE_CG_VOXDATA-v<major>.<minor>` and a null terminator. flite 2
+//! writes v2.0, while older releases and some third-party voices carry a v1.x magic. The sections
+//! are laid out the same way, but v1 voices predate the features which count the f0, parameter
+//! and duration models, so [`Features`](crate::Features) falls back to flite's defaults for them.
+
+use core::fmt::{self, Display, Formatter};
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+/// The name of the newtype a [`FormatVersion`] is (de)serialized as: this crate reads it from
+/// and writes it to the magic, other formats see its text, like `"v2.0"`.
+pub(crate) const VERSION: &str = "$serde_cst::Version";
+
+const PREFIX: &[u8; 22] = b"CMU_FLITE_CG_VOXDATA-v";
+
+/// A version v1.x or v2.x, whose minor version is a single digit like in every magic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FormatVersion {
+    major: u8,
+    minor: u8,
+}
+impl FormatVersion {
+    /// The version this crate writes, and flite 2 reads.
+    pub const CURRENT: FormatVersion = FormatVersion { major: 2, minor: 0 };
+
+    /// Returns `None` if this is no version this crate reads: the major version is not 1 or 2,
+    /// or the minor version takes more than one digit.
+    pub const fn new(major: u8, minor: u8) -> Option<FormatVersion> {
+        match (major, minor) {
+            (1 | 2, 0..=9) => Some(FormatVersion { major, minor }),
+            _ => None,
+        }
+    }
+
+    /// The version of the magic `input` starts with, if it is one this crate reads.
+    pub const fn detect(input: &[u8]) -> Option<FormatVersion> {
+        if input.len() < PREFIX.len() + 4 {
+            return None;
+        }
+        let mut i = 0;
+        while i < PREFIX.len() {
+            if input[i] != PREFIX[i] {
+                return None;
+            }
+            i += 1;
+        }
+        FormatVersion::from_text(&[input[i], input[i + 1], input[i + 2], input[i + 3]])
+    }
+    // `<major>.<minor>` and the null terminator.
+    const fn from_text(text: &[u8; 4]) -> Option<FormatVersion> {
+        match (text[0], text[1], text[2], text[3]) {
+            (major @ b'0'..=b'9', b'.', minor @ b'0'..=b'9', 0) => {
+                FormatVersion::new(major - b'0', minor - b'0')
+            }
+            _ => None,
+        }
+    }
+    pub const fn major(self) -> u8 {
+        self.major
+    }
+    pub const fn minor(self) -> u8 {
+        self.minor
+    }
+    /// The magic of this version, including its null terminator.
+    pub const fn magic(self) -> [u8; 26] {
+        let mut magic = [0; 26];
+        let mut i = 0;
+        while i < PREFIX.len() {
+            magic[i] = PREFIX[i];
+            i += 1;
+        }
+        magic[22] = b'0' + self.major;
+        magic[23] = b'.';
+        magic[24] = b'0' + self.minor;
+        magic
+    }
+    // `v<major>.<minor>`
+    pub(crate) fn text(self) -> [u8; 4] {
+        [b'v', b'0' + self.major, b'.', b'0' + self.minor]
+    }
+    /// Parse the text of a version, like `"v2.0"`.
+    pub fn parse(text: &str) -> Option<FormatVersion> {
+        match text.as_bytes() {
+            &[b'v', major, b'.', minor] => FormatVersion::from_text(&[major, b'.', minor, 0]),
+            _ => None,
+        }
+    }
+}
+impl Default for FormatVersion {
+    fn default() -> Self {
+        FormatVersion::CURRENT
+    }
+}
+impl Display for FormatVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // only ASCII goes into the text
+        f.write_str(core::str::from_utf8(&self.text()).unwrap())
+    }
+}
+
+impl Serialize for FormatVersion {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let text = self.text();
+        serializer.serialize_newtype_struct(VERSION, core::str::from_utf8(&text).unwrap())
+    }
+}
+
+struct VersionVisitor;
+impl<'de> Visitor<'de> for VersionVisitor {
+    type Value = FormatVersion;
+    fn expecting(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.write_str("A format version, like v2.0")
+    }
+    fn visit_str<E>(self, v: &str) -> Result<FormatVersion, E>
+    where
+        E: de::Error,
+    {
+        FormatVersion::parse(v).ok_or_else(|| E::invalid_value(de::Unexpected::Str(v), &self))
+    }
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<FormatVersion, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self)
+    }
+}
+impl<'de> Deserialize<'de> for FormatVersion {
+    fn deserialize<D>(deserializer: D) -> Result<FormatVersion, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(VERSION, VersionVisitor)
+    }
+}
+
+#[test]
+fn test_format_version() {
+    assert_eq!(
+        FormatVersion::detect(crate::FLITEVOX_MAGIC),
+        Some(FormatVersion::CURRENT)
+    );
+    assert_eq!(FormatVersion::CURRENT.magic(), crate::FLITEVOX_MAGIC);
+    let v1 = FormatVersion::new(1, 5).unwrap();
+    assert_eq!(&v1.magic(), b"CMU_FLITE_CG_VOXDATA-v1.5\0");
+    assert_eq!(FormatVersion::detect(&v1.magic()), Some(v1));
+    assert_eq!(FormatVersion::parse(&v1.to_string()), Some(v1));
+    assert!(v1 < FormatVersion::CURRENT);
+    assert_eq!(FormatVersion::detect(b"CMU_FLITE_CG_VOXDATA-v3.0\0"), None);
+    assert_eq!(FormatVersion::detect(b"CMU_FLITE_CG_VOXDATA-v1.10"), None);
+    assert_eq!(FormatVersion::detect(b"CMU_FLITE_CG_VOXDATA-v1."), None);
+    assert_eq!(FormatVersion::new(2, 10), None);
+    assert_eq!(FormatVersion::new(3, 0), None);
+    assert_eq!(FormatVersion::parse("v0.9"), None);
+}
@@ -1,90 +1,97 @@
-use crate::{error::Error, Header};
-use serde::{Deserialize, Deserializer, de::DeserializeSeed, de::value::SeqDeserializer, Serialize, de::Visitor, de::SeqAccess, de};
+use crate::{error::Error, Header, MODEL_SHAPE_BASE_MINRANGE};
+pub use crate::array::{Array, Array2d};
+use serde::{Deserialize, Deserializer, de::DeserializeSeed, de::value::SeqDeserializer, Serialize, Serializer, ser::SerializeTuple, de::Visitor, de::SeqAccess, de};
 use serde_dis::{DeserializeWithDiscriminant};
 use core::{fmt, marker::PhantomData};
 
-#[derive(Debug, PartialEq)]
-#[repr(u8)]
+// The type tags flite gives its values.
+const CST_VAL_TYPE_INT: u16 = 1;
+const CST_VAL_TYPE_FLOAT: u16 = 3;
+const CST_VAL_TYPE_STRING: u16 = 5;
+
+/// A value in a tree node: a 16 bit type tag, then the value.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename = "$serde_cst::Tagged")]
 pub enum CstVal {
-    // no idea what this means
-    Cons(i32) = 0,
-    Int(i32) = 1,
-    Float(f32) = 3,
-    Str(String) = 5,
-    FirstFree(i32) = 7,
-    Other(i32) = 54
+    #[serde(rename = "1")]
+    Int(i32),
+    #[serde(rename = "3")]
+    Float(f32),
+    #[serde(rename = "5")]
+    Str(String),
+    /// A value of any other type, like a cons cell or a user type. flite reads all of them as an
+    /// int cell, so they are kept as their type tag and that cell.
+    #[serde(rename = "_")]
+    Other(u16, i32),
 }
-struct CstValVisitor;
-impl<'de> Visitor<'de> for CstValVisitor {
-    type Value = CstVal;
-    fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt.write_str("A CstVal consisting of a singe byte, which determintes the type that follows")
-    }
-    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error> 
-    where A: SeqAccess<'de> {
-        let discrim = seq.next_element()?
-                    .ok_or(de::Error::invalid_length(0, &self))?;
-        println!("CstValue discriminant: {}", discrim);
-        match discrim {
-            0 => {
-                let v = seq.next_element()?
-                    .ok_or(de::Error::invalid_length(1, &self))?;
-                Ok(CstVal::Cons(v))
-            },
-            1 => {
-                let v = seq.next_element()?
-                    .ok_or(de::Error::invalid_length(1, &self))?;
-                Ok(CstVal::Int(v))
-            },
-            3 => {
-                let v = seq.next_element()?
-                    .ok_or(de::Error::invalid_length(1, &self))?;
-                Ok(CstVal::Float(v))
-            },
-            5 => {
-                let v = seq.next_element()?
-                    .ok_or(de::Error::invalid_length(1, &self))?;
-                Ok(CstVal::Str(v))
-            },
-            7 => {
-                let v = seq.next_element()?
-                    .ok_or(de::Error::invalid_length(1, &self))?;
-                Ok(CstVal::FirstFree(v))
-            },
-            _ => {
-                let v = seq.next_element()?
-                    .ok_or(de::Error::invalid_length(1, &self))?;
-                Ok(CstVal::Other(v))
-            },
+impl CstVal {
+    /// The type tag of the value.
+    pub fn tag(&self) -> u16 {
+        match self {
+            CstVal::Int(_) => CST_VAL_TYPE_INT,
+            CstVal::Float(_) => CST_VAL_TYPE_FLOAT,
+            CstVal::Str(_) => CST_VAL_TYPE_STRING,
+            CstVal::Other(tag, _) => *tag,
         }
     }
 }
-impl<'de> Deserialize<'de> for CstVal {
-    fn deserialize<D>(deser: D) -> Result<Self, D::Error> 
-    where D: Deserializer<'de> {
-        deser.deserialize_seq(CstValVisitor)
-    }
-}
 
-#[derive(Deserialize, Debug, PartialEq)]
+// Nodes are packed like a C struct: the three numbers share a single cell.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename = "$serde_cst::Packed")]
 pub struct TreeNode (
-    u8, // feat
-    u8, // op
-    u16, // no of tree
-    CstVal, // value expession
+    pub u8, // feat
+    pub u8, // op
+    pub u16, // no of tree
+    pub CstVal, // value expession
 );
 
-#[derive(Deserialize, Debug, PartialEq)]
-pub struct TreeFeatures(Vec<String>);
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct TreeFeatures(pub Vec<String>);
 
-#[derive(Deserialize, Debug, PartialEq)]
+/// A CART: its nodes, then the names of the features they ask about.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct Tree (
-    TreeNode,
-    TreeFeatures,
+    pub Vec<TreeNode>,
+    pub TreeFeatures,
+);
+
+/// The trees of one model, one per state in the order of [`Body::db_types`].
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Trees(pub Vec<Tree>);
+
+/// The frames of one parameter model.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ModelVectors (
+    pub i32, // number of channels
+    pub i32, // number of frames
+    pub Array2d, // one row per frame
+);
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct DurStat (
+    pub f32, // mean
+    pub f32, // standard deviation
+    pub String, // phone
+);
+
+/// The duration statistics and tree of one duration model.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct DurModel (
+    pub Vec<DurStat>,
+    pub Tree,
 );
 
-#[derive(Deserialize, Debug, PartialEq)]
-pub struct F0Tree(Vec<Tree>);
+/// The accent and phrase models of voices built with SPAM f0 modelling.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpamF0 {
+    pub accent_tree: Tree,
+    pub phrase_tree: Tree,
+    pub accent_vectors: ModelVectors,
+}
+
+// Upper bound on elements reserved up front for a length read from the input.
+const MAX_PREALLOC: usize = 4096;
 
 struct FixedSeqValuesVisitor<'de, D> {
     len: usize,
@@ -108,7 +115,8 @@ where D: Deserialize<'de> {
     }
     fn visit_seq<A>(self, mut seq: A) -> core::result::Result<Self::Value, A::Error>
     where A: SeqAccess<'de> {
-        let mut vec = Vec::with_capacity(self.len);
+        // the length comes straight from the file, so don't trust it for the allocation
+        let mut vec = Vec::with_capacity(self.len.min(MAX_PREALLOC));
         for i in 0..self.len {
             let val = seq.next_element()?
                 .ok_or(de::Error::invalid_length(i, &self))?;
@@ -118,10 +126,10 @@ where D: Deserialize<'de> {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TreeDb {
-    header: Header,
-    body: Body,
+    pub header: Header,
+    pub body: Body,
 }
 struct TreeDbVisitor;
 impl<'de> Visitor<'de> for TreeDbVisitor {
@@ -139,6 +147,15 @@ impl<'de> Visitor<'de> for TreeDbVisitor {
         Ok(TreeDb { header, body })
     }
 }
+impl Serialize for TreeDb {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(&self.header)?;
+        tuple.serialize_element(&self.body)?;
+        tuple.end()
+    }
+}
 impl<'de> Deserialize<'de> for TreeDb {
     fn deserialize<D>(deserializer: D) -> Result<TreeDb, D::Error> 
     where D: Deserializer<'de> {
@@ -146,22 +163,115 @@ impl<'de> Deserialize<'de> for TreeDb {
     }
 }
 
-#[derive(Debug, PartialEq)]
+// The number of elements of the body, including the ones which are empty for some voices.
+const BODY_LEN: usize = 29;
+
+/// The clustergen database, in the order `cst_cg_load_db` in flite reads it. How many models
+/// there are of each kind comes from the header.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Body {
     pub db_types: Vec<String>,
     pub num_types: i32,
     pub sample_rate: i32,
     pub f0_mean: f32,
     pub f0_stddev: f32,
-    pub f0_trees: Vec<F0Tree>,
+    /// `num_f0_models` of them.
+    pub f0_trees: Vec<Trees>,
+    /// `num_param_models` of them.
+    pub param_trees: Vec<Trees>,
+    pub spamf0: Option<SpamF0>,
+    /// `num_param_models` of them.
+    pub param_models: Vec<ModelVectors>,
+    /// Per channel minimum, as `f32`s.
+    pub model_min: Array,
+    /// Per channel range, as `f32`s.
+    pub model_range: Array,
+    /// For model shapes above 1, for every parameter model one table of `f32`s per channel.
+    pub qtables: Vec<Array2d>,
+    /// Seconds between two frames.
+    pub frame_advance: f32,
+    /// `num_dur_models` of them.
+    pub dur_models: Vec<DurModel>,
+    /// For every phone, its name and then its states.
+    pub phone_states: Vec<Vec<String>>,
+    pub do_mlpg: i32,
+    /// The delta window, as `f32`s.
+    pub dynwin: Array,
+    pub dynwinsize: i32,
+    pub mlsa_alpha: f32,
+    pub mlsa_beta: f32,
+    pub multimodel: i32,
+    pub mixed_excitation: i32,
+    pub me_num: i32,
+    pub me_order: i32,
+    /// The mixed excitation filters, one row of `f64`s per band.
+    pub me_h: Array2d,
+    pub gain: f32,
+}
+
+// A list whose length comes from the header, so it is written without one.
+struct Fixed<'a, T>(&'a [T]);
+impl<T: Serialize> Serialize for Fixed<'_, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        let mut tuple = serializer.serialize_tuple(self.0.len())?;
+        for element in self.0 {
+            tuple.serialize_element(element)?;
+        }
+        tuple.end()
+    }
+}
+
+impl Serialize for Body {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        let spamf0 = i32::from(self.spamf0.is_some());
+        let mut tuple = serializer.serialize_tuple(BODY_LEN)?;
+        tuple.serialize_element(&self.db_types)?;
+        tuple.serialize_element(&self.num_types)?;
+        tuple.serialize_element(&self.sample_rate)?;
+        tuple.serialize_element(&self.f0_mean)?;
+        tuple.serialize_element(&self.f0_stddev)?;
+        tuple.serialize_element(&Fixed(&self.f0_trees))?;
+        tuple.serialize_element(&Fixed(&self.param_trees))?;
+        tuple.serialize_element(&spamf0)?;
+        match &self.spamf0 {
+            Some(spamf0) => tuple.serialize_element(&(&spamf0.accent_tree, &spamf0.phrase_tree))?,
+            None => tuple.serialize_element(&())?,
+        }
+        tuple.serialize_element(&Fixed(&self.param_models))?;
+        match &self.spamf0 {
+            Some(spamf0) => tuple.serialize_element(&spamf0.accent_vectors)?,
+            None => tuple.serialize_element(&())?,
+        }
+        tuple.serialize_element(&self.model_min)?;
+        tuple.serialize_element(&self.model_range)?;
+        tuple.serialize_element(&Fixed(&self.qtables))?;
+        tuple.serialize_element(&self.frame_advance)?;
+        tuple.serialize_element(&Fixed(&self.dur_models))?;
+        tuple.serialize_element(&self.phone_states)?;
+        tuple.serialize_element(&self.do_mlpg)?;
+        tuple.serialize_element(&self.dynwin)?;
+        tuple.serialize_element(&self.dynwinsize)?;
+        tuple.serialize_element(&self.mlsa_alpha)?;
+        tuple.serialize_element(&self.mlsa_beta)?;
+        tuple.serialize_element(&self.multimodel)?;
+        tuple.serialize_element(&self.mixed_excitation)?;
+        tuple.serialize_element(&self.me_num)?;
+        tuple.serialize_element(&self.me_order)?;
+        tuple.serialize_element(&self.me_h)?;
+        tuple.serialize_element(&spamf0)?;
+        tuple.serialize_element(&self.gain)?;
+        tuple.end()
+    }
 }
 
-struct FixedLengthSeq<T> {
+pub(crate) struct FixedLengthSeq<T> {
     pub len: usize,
     pub _marker: PhantomData<T>,
 }
 impl<T> FixedLengthSeq<T> {
-    fn from_len(len: usize) -> Self {
+    pub(crate) fn from_len(len: usize) -> Self {
         FixedLengthSeq { len, _marker: PhantomData }
     }
 }
@@ -170,16 +280,47 @@ where T: Deserialize<'de> + 'de, {
     type Value = Vec<T>;
     fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error> 
     where D: Deserializer<'de> {
-        deserializer.deserialize_seq(FixedSeqValuesVisitor::new(self.len))
+        deserializer.deserialize_tuple(self.len, FixedSeqValuesVisitor::new(self.len))
+    }
+}
+
+// The elements of a sequence, counted for the error when it ends early.
+struct Elements<'a, A> {
+    seq: A,
+    idx: usize,
+    expected: &'a dyn de::Expected,
+}
+impl<'a, 'de, A> Elements<'a, A>
+where A: SeqAccess<'de> {
+    fn next<T>(&mut self) -> Result<T, A::Error>
+    where T: Deserialize<'de> {
+        self.next_seed(PhantomData)
+    }
+    fn next_seed<T>(&mut self, seed: T) -> Result<T::Value, A::Error>
+    where T: DeserializeSeed<'de> {
+        let value = self.seq.next_element_seed(seed)?
+            .ok_or_else(|| de::Error::invalid_length(self.idx, self.expected))?;
+        self.idx += 1;
+        Ok(value)
     }
 }
 
+/// The models of a body, decoded apart from the rest of it.
+pub(crate) struct Models {
+    pub(crate) f0_trees: Vec<Trees>,
+    pub(crate) param_trees: Vec<Trees>,
+    pub(crate) param_models: Vec<ModelVectors>,
+    pub(crate) dur_models: Vec<DurModel>,
+}
+
 struct BodyVisitor<'a> {
     header: &'a Header,
+    // Given, the models are not read from the body, which has none of them.
+    models: Option<Models>,
 }
 impl<'a> BodyVisitor<'a> {
     fn new(header: &'a Header) -> Self {
-        BodyVisitor { header } 
+        BodyVisitor { header, models: None } 
     }
 }
 impl<'a, 'de> Visitor<'de> for BodyVisitor<'a> {
@@ -187,37 +328,121 @@ impl<'a, 'de> Visitor<'de> for BodyVisitor<'a> {
     fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.write_str("A body of a Festivel CG (cluster gen) voice")
     }
-    fn visit_seq<V>(self, mut seq: V) -> Result<Body, V::Error> 
+    fn visit_seq<V>(mut self, seq: V) -> Result<Body, V::Error> 
     where V: SeqAccess<'de> {
+        let mut models = self.models.take();
+        let features = &self.header.features;
+        let num_f0_models = features.num_f0_models as usize;
+        let num_param_models = features.num_param_models as usize;
+        let num_dur_models = features.num_dur_models as usize;
+        let num_qtables = if features.model_shape > MODEL_SHAPE_BASE_MINRANGE {
+            num_param_models
+        } else {
+            0
+        };
+        let mut seq = Elements { seq, idx: 0, expected: &self };
+        let db_types = seq.next()?;
+        let num_types = seq.next()?;
+        let sample_rate = seq.next()?;
+        let f0_mean = seq.next()?;
+        let f0_stddev = seq.next()?;
+        let f0_trees = match &mut models {
+            Some(models) => core::mem::take(&mut models.f0_trees),
+            None => seq.next_seed(FixedLengthSeq::from_len(num_f0_models))?,
+        };
+        let param_trees = match &mut models {
+            Some(models) => core::mem::take(&mut models.param_trees),
+            None => seq.next_seed(FixedLengthSeq::from_len(num_param_models))?,
+        };
+        let num_spamf0 = usize::from(seq.next::<i32>()? != 0);
+        let mut spamf0_trees: Vec<Tree> =
+            seq.next_seed(FixedLengthSeq::from_len(2 * num_spamf0))?;
+        let param_models = match &mut models {
+            Some(models) => core::mem::take(&mut models.param_models),
+            None => seq.next_seed(FixedLengthSeq::from_len(num_param_models))?,
+        };
+        let mut spamf0_vectors: Vec<ModelVectors> =
+            seq.next_seed(FixedLengthSeq::from_len(num_spamf0))?;
+        let spamf0 = match (spamf0_trees.pop(), spamf0_trees.pop(), spamf0_vectors.pop()) {
+            (Some(phrase_tree), Some(accent_tree), Some(accent_vectors)) => Some(SpamF0 {
+                accent_tree,
+                phrase_tree,
+                accent_vectors,
+            }),
+            _ => None,
+        };
+        let model_min = seq.next()?;
+        let model_range = seq.next()?;
+        let qtables = seq.next_seed(FixedLengthSeq::from_len(num_qtables))?;
+        let frame_advance = seq.next()?;
+        let dur_models = match &mut models {
+            Some(models) => core::mem::take(&mut models.dur_models),
+            None => seq.next_seed(FixedLengthSeq::from_len(num_dur_models))?,
+        };
+        let phone_states = seq.next()?;
+        let do_mlpg = seq.next()?;
+        let dynwin = seq.next()?;
+        let dynwinsize = seq.next()?;
+        let mlsa_alpha = seq.next()?;
+        let mlsa_beta = seq.next()?;
+        let multimodel = seq.next()?;
+        let mixed_excitation = seq.next()?;
+        let me_num = seq.next()?;
+        let me_order = seq.next()?;
+        let me_h = seq.next()?;
+        // flite reads the spamf0 flag a second time; it is written back from `spamf0`
+        seq.next::<i32>()?;
+        let gain = seq.next()?;
         Ok(Body {
-            db_types: seq.next_element()?
-                .ok_or(de::Error::invalid_length(0, &self))?,
-            num_types: seq.next_element()?
-                .ok_or(de::Error::invalid_length(1, &self))?,
-            sample_rate: seq.next_element()?
-                .ok_or(de::Error::invalid_length(2, &self))?,
-            f0_mean: seq.next_element()?
-                .ok_or(de::Error::invalid_length(3, &self))?,
-            f0_stddev: seq.next_element()?
-                .ok_or(de::Error::invalid_length(4, &self))?,
-            f0_trees: seq.next_element_seed(FixedLengthSeq::from_len(self.header.features.num_f0_models.try_into().unwrap()))?
-                .ok_or(de::Error::invalid_length(5, &self))?,
+            db_types,
+            num_types,
+            sample_rate,
+            f0_mean,
+            f0_stddev,
+            f0_trees,
+            param_trees,
+            spamf0,
+            param_models,
+            model_min,
+            model_range,
+            qtables,
+            frame_advance,
+            dur_models,
+            phone_states,
+            do_mlpg,
+            dynwin,
+            dynwinsize,
+            mlsa_alpha,
+            mlsa_beta,
+            multimodel,
+            mixed_excitation,
+            me_num,
+            me_order,
+            me_h,
+            gain,
         })
     }
 }
 
-struct BodyDeserializer<'a> {
-    header: &'a Header,
+pub(crate) struct BodyDeserializer<'a> {
+    pub(crate) header: &'a Header,
 }
 
 impl<'de, 'a> DeserializeSeed<'de> for BodyDeserializer<'a> {
     type Value = Body;
     fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error> 
     where D: Deserializer<'de> {
-        deserializer.deserialize_tuple(6, BodyVisitor::new(self.header))
+        deserializer.deserialize_tuple(BODY_LEN, BodyVisitor::new(self.header))
     }
 }
 
+/// Put a body together from its `models` and the elements of `seq`, which are the rest of it in
+/// the order they are stored.
+pub(crate) fn body_with_models<'de, A>(header: &Header, models: Models, seq: A) -> Result<Body, A::Error>
+where A: SeqAccess<'de> {
+    BodyVisitor { header, models: Some(models) }.visit_seq(seq)
+}
+
 /*
 struct BodyDeserializer;
 impl<'de> Visitor<'de> for BodyVisitor {
@@ -236,20 +461,17 @@ impl<'de> Visitor<'de> for BodyVisitor {
 
 #[test]
 fn test_cluster_voice() {
-    use crate::{de::from_bytes, EndOfFeatures, Features, Gender, Language};
-    use chrono::NaiveDateTime;
+    use crate::{de::from_bytes, EndOfFeatures, Features, FormatVersion, Gender, Language};
     let data = include_bytes!("../data/cmu_us_slt.flitevox");
     let header = Header {
+        version: FormatVersion::CURRENT,
         features: Features {
             language: "eng".to_string(),
             country: "USA".to_string(),
             variant: "none".to_string(),
             age: 30,
             gender: Gender::Unknown,
-            build_date: chrono::NaiveDateTime::new(
-                chrono::NaiveDate::from_ymd_opt(2017, 9, 14).unwrap(),
-                chrono::NaiveTime::from_hms_opt(23, 37, 0).unwrap(),
-            ),
+            build_date: crate::BuildDate::new(2017, 9, 14, 23, 37, 0).unwrap(),
             description: "unknown".to_string(),
             eng_shared: 0,
             copyright: "unknown".to_string(),
@@ -261,8 +483,12 @@ fn test_cluster_voice() {
         },
         name: "cmu_us_slt".to_string(),
     };
-    let body = Body {
-            db_types: vec![
+    let voice = from_bytes::<TreeDb>(data).unwrap();
+    assert_eq!(voice.header, header);
+    let body = voice.body;
+    assert_eq!(
+            body.db_types,
+            vec![
                 "aa_1".to_string(),
                 "aa_2".to_string(),
                 "aa_3".to_string(),
@@ -387,15 +613,68 @@ fn test_cluster_voice() {
                 "zh_1".to_string(),
                 "zh_2".to_string(),
                 "zh_3".to_string(),
-            ],
-            num_types: 0x7c,
-            sample_rate: 0x3e80,
-            f0_mean: f32::from_le_bytes([0, 0, 0x2c, 0x43]),
-            f0_stddev: f32::from_le_bytes([0, 0, 0xd8, 0x41]),
-            f0_trees: vec![]
-        };
-    let expected = TreeDb {
-        header, body
-    };
-    assert_eq!(expected, from_bytes::<TreeDb>(data).unwrap());
+            ]);
+    assert_eq!(body.num_types, 0x7c);
+    assert_eq!(body.sample_rate, 0x3e80);
+    assert_eq!(body.f0_mean, f32::from_le_bytes([0, 0, 0x2c, 0x43]));
+    assert_eq!(body.f0_stddev, f32::from_le_bytes([0, 0, 0xd8, 0x41]));
+
+    // one tree per state for each of the three f0 and parameter models
+    assert_eq!(body.f0_trees.len(), 3);
+    assert_eq!(body.param_trees.len(), 3);
+    assert!(body.f0_trees.iter().chain(&body.param_trees).all(|trees| trees.0.len() == 124));
+    let Tree(nodes, TreeFeatures(features)) = &body.f0_trees[0].0[0];
+    assert_eq!(nodes.len(), 17);
+    assert_eq!(nodes[1], TreeNode(1, 0, 3, CstVal::Str("n_3".to_string())));
+    let leaf = CstVal::Float(f32::from_le_bytes([0x29, 0xdc, 0x35, 0x43]));
+    assert_eq!(nodes[2], TreeNode(255, 255, 0, leaf));
+    assert_eq!(features[0], "lisp_cg_position_in_phrasep");
+    assert_eq!(body.spamf0, None);
+
+    let frames: Vec<_> = body.param_models.iter()
+        .map(|model| (model.0, model.1, model.2.len()))
+        .collect();
+    assert_eq!(frames, [(114, 30238, 30238), (114, 30135, 30135), (114, 30220, 30220)]);
+    assert!(body.param_models[0].2.rows().all(|row| row.0.len() == 82));
+    assert_eq!(body.model_min.0.len(), 114 * 4);
+    assert_eq!(body.model_range.0.len(), 114 * 4);
+    // model shape 3 is quantized
+    assert_eq!(body.qtables.len(), 3);
+    assert!(body.qtables.iter().all(|tables| tables.len() == 114));
+    assert_eq!(body.frame_advance, 0.005);
+
+    assert_eq!(body.dur_models.len(), 3);
+    assert_eq!(body.dur_models[0].0.len(), 125);
+    assert_eq!(body.dur_models[0].0[0], DurStat(0.2, 0.1, "pau".to_string()));
+    assert_eq!(body.phone_states.len(), 42);
+    assert_eq!(body.phone_states[0], ["aa", "aa_1", "aa_2", "aa_3"]);
+
+    assert_eq!(body.do_mlpg, 1);
+    assert_eq!(body.dynwin.f32s().collect::<Vec<_>>(), [-0.5, 0.0, 0.5]);
+    assert_eq!(body.dynwinsize, 3);
+    assert_eq!((body.mlsa_alpha, body.mlsa_beta), (0.42, 0.4));
+    assert_eq!((body.multimodel, body.mixed_excitation), (0, 1));
+    assert_eq!((body.me_num, body.me_order), (5, 47));
+    assert_eq!(body.me_h.len(), 5);
+    assert!(body.me_h.rows().all(|row| row.f64s().count() == 47));
+    assert_eq!(body.gain, 1.5);
+}
+
+#[test]
+fn test_cst_val() {
+    use crate::{de::from_bytes, ser::to_bytes};
+    let node = vec![TreeNode(0, 0, 0, CstVal::Str("a".to_string()))];
+    let mut data = to_bytes(&node).unwrap();
+    assert_eq!(data[34..], *b"\0\0\0\0\x05\0\x02\0\0\0a\0");
+    // like flite, every tag but a string, a float and an int is read as an int cell: here a cons
+    // cell, a user type (a wave) and a tag flite gives no type
+    for tag in [0, 9, 2] {
+        data[38] = tag;
+        data.truncate(40);
+        data.extend_from_slice(&7i32.to_le_bytes());
+        let nodes = from_bytes::<Vec<TreeNode>>(&data).unwrap();
+        assert_eq!(nodes, [TreeNode(0, 0, 0, CstVal::Other(tag.into(), 7))]);
+        assert_eq!(nodes[0].3.tag(), u16::from(tag));
+        assert_eq!(to_bytes(&nodes).unwrap(), data);
+    }
 }
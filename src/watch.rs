@@ -0,0 +1,79 @@
+//! Keep a [`Registry`] up to date while voices are installed and removed.
+
+extern crate std;
+
+use std::sync::mpsc::{self, Receiver};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+use crate::registry::{Event, Registry};
+
+/// A [`Registry`] which follows filesystem notifications for its directories.
+pub struct Watcher {
+    registry: Registry,
+    notifications: Receiver<notify::Result<notify::Event>>,
+    // notifications stop when this is dropped
+    _watcher: RecommendedWatcher,
+}
+impl Watcher {
+    /// Start watching the directories of `registry`, which should have been scanned already.
+    ///
+    /// Directories which do not exist yet cannot be watched, and are skipped.
+    pub fn new(registry: Registry) -> notify::Result<Watcher> {
+        let (sender, notifications) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        for dir in registry.dirs() {
+            if dir.is_dir() {
+                watcher.watch(dir, RecursiveMode::Recursive)?;
+            }
+        }
+        Ok(Watcher {
+            registry,
+            notifications,
+            _watcher: watcher,
+        })
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub fn into_registry(self) -> Registry {
+        self.registry
+    }
+
+    fn apply(&mut self, notification: notify::Event) -> Vec<Event> {
+        if matches!(notification.kind, EventKind::Access(_)) {
+            return Vec::new();
+        }
+        notification
+            .paths
+            .iter()
+            .flat_map(|path| self.registry.refresh(path))
+            .collect()
+    }
+
+    /// Block until the registry changes, and return how. `None` once notifications stopped.
+    pub fn wait(&mut self) -> Option<notify::Result<Vec<Event>>> {
+        loop {
+            match self.notifications.recv().ok()? {
+                Ok(notification) => {
+                    let events = self.apply(notification);
+                    if !events.is_empty() {
+                        return Some(Ok(events));
+                    }
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+
+    /// Apply the notifications which arrived so far, without blocking.
+    pub fn poll(&mut self) -> notify::Result<Vec<Event>> {
+        let mut events = Vec::new();
+        while let Ok(notification) = self.notifications.try_recv() {
+            events.extend(self.apply(notification?));
+        }
+        Ok(events)
+    }
+}